@@ -1,6 +1,8 @@
 use clap::{Parser, Subcommand};
-use ferrum_core::parser;
-use std::path::Path;
+use ferrum_dev_server::{RustDevServer, DEFAULT_PORT};
+use std::path::PathBuf;
+
+mod routes;
 
 fn create_project(name: &str, _template: &str) -> std::io::Result<()> {
     println!("Initializing Ferrum project: {}", name);
@@ -108,49 +110,27 @@ features = [
     Ok(())
 }
 
-fn start_dev_server() -> std::io::Result<()> {
+fn start_dev_server(port: u16, host: &str, open: bool) -> Result<(), Box<dyn std::error::Error>> {
     // Check if current directory is a Ferrum project
-    if !std::path::Path::new("src/main.frr").exists() {
-        eprintln!("❌ Error: Not a Ferrum project directory");
-        eprintln!("   Make sure you're in a project with src/main.frr");
-        eprintln!("   Pure Rust - NO JavaScript, NO Single HTML");
-        std::process::exit(1);
-    }
+    let server = match RustDevServer::builder()
+        .root(std::env::current_dir()?)
+        .host(host)
+        .port(port)
+        .open_browser(open)
+        .build()
+    {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("❌ Error: {}", e);
+            eprintln!("   Make sure you're in a project with src/main.frr");
+            eprintln!("   Pure Rust - NO JavaScript, NO Single HTML");
+            std::process::exit(1);
+        }
+    };
 
-    println!("🦀 Starting Pure Rust Ferrum Server");
-    println!("📁 Project: {}", std::env::current_dir().unwrap().display());
-    println!("🌐 Port: 7777");
-    println!("🔥 Pure Rust: NO JavaScript, NO Single HTML");
-    println!("👀 Watching .frr files...");
-
-    // Start the actual dev server
-    let dev_server_path = std::env::current_dir()
-        .unwrap()
-        .join("../target/debug/ferrum-dev-server");
-
-    if dev_server_path.exists() {
-        println!("🚀 Launching dev server...");
-        std::process::Command::new(&dev_server_path)
-            .arg("7777")
-            .spawn()
-            .expect("Failed to start dev server");
-
-        println!("✨ Server started at: http://localhost:7777");
-        println!("📝 Features:");
-        println!("   • Pure Rust server (NO JavaScript)");
-        println!("   • Hot reload for .frr files");
-        println!("   • HTML generation from .frr");
-        println!("   • CSS-in-Rust styling");
-        println!("   • Component compilation");
-    } else {
-        println!("⚠️  Dev server not built yet. Run: cargo build --package ferrum-dev-server");
-        println!("📝 Features (when built):");
-        println!("   • Pure Rust server (NO JavaScript)");
-        println!("   • Hot reload for .frr files");
-        println!("   • HTML generation from .frr");
-        println!("   • CSS-in-Rust styling");
-        println!("   • Component compilation");
-    }
+    // Run the dev server in-process until Ctrl-C
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(server.run())?;
 
     Ok(())
 }
@@ -174,7 +154,24 @@ pub enum Commands {
         template: String,
     },
     /// Start development server with hot reload
-    Dev,
+    Dev {
+        #[arg(long, short, default_value_t = DEFAULT_PORT)]
+        port: u16,
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Open the app in the default browser
+        #[arg(long)]
+        open: bool,
+    },
+    /// List page routes and warn about links that match none
+    Routes {
+        /// Write a typed route enum to this file
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Name of the generated enum
+        #[arg(long, default_value = "AppRoute")]
+        name: String,
+    },
     /// Build for production
     Build,
     /// Run tests
@@ -196,10 +193,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             create_project(&name, &template)?;
             Ok(())
         }
-        Commands::Dev => {
+        Commands::Dev { port, host, open } => {
             println!("Starting Ferrum development server...");
-            Ok(start_dev_server()?)
+            start_dev_server(port, &host, open)
         }
+        Commands::Routes { out, name } => routes::list_routes(out.as_deref(), &name),
         Commands::Build => {
             println!("Building Ferrum application for production...");
             // TODO: Implement build process
@@ -0,0 +1,170 @@
+//! `ferrum routes`: the project's page routes, dead links and typed routes
+
+use ferrum_core::routing::generate_route_enum;
+use ferrum_dev_server::pages;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A `Link(to: "...")` whose target no page serves
+#[derive(Debug, PartialEq)]
+struct DeadLink {
+    file: PathBuf,
+    line: usize,
+    target: String,
+}
+
+pub fn list_routes(out: Option<&Path>, enum_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let root = std::env::current_dir()?;
+    let pages_dir = root.join("src/pages");
+    let routes = pages::discover(&pages_dir);
+
+    if routes.is_empty() {
+        println!("No routable pages in src/pages");
+    }
+    for route in &routes {
+        let file = route.file.strip_prefix(&root).unwrap_or(&route.file);
+        println!("  {:<32} {}", route.pattern, file.display());
+    }
+
+    for dead in dead_links(&root.join("src"), &pages_dir) {
+        let file = dead.file.strip_prefix(&root).unwrap_or(&dead.file);
+        eprintln!(
+            "⚠️  {}:{}: Link(to: \"{}\") matches no route",
+            file.display(),
+            dead.line,
+            dead.target
+        );
+    }
+
+    if let Some(out) = out {
+        let table: Vec<(String, String)> = routes
+            .iter()
+            .map(|route| (variant_name(&pages_dir, &route.file), route.pattern.clone()))
+            .collect();
+        fs::write(out, generate_route_enum(enum_name, &table)?)?;
+        println!("✅ Wrote {} to {}", enum_name, out.display());
+    }
+
+    Ok(())
+}
+
+/// Variant for a page, from its path below `pages_dir`: `users/[id].frr`
+/// becomes `UsersId`
+fn variant_name(pages_dir: &Path, file: &Path) -> String {
+    let relative = file
+        .strip_prefix(pages_dir)
+        .unwrap_or(file)
+        .with_extension("");
+
+    relative
+        .to_string_lossy()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Internal `Link(to: "...")` targets in `.frr` files below `src_dir` that
+/// match no page
+fn dead_links(src_dir: &Path, pages_dir: &Path) -> Vec<DeadLink> {
+    let link = Regex::new(r#"Link\s*\(([^)]*)\)"#).expect("valid regex");
+    let to = Regex::new(r#"\bto\s*:\s*"([^"]*)""#).expect("valid regex");
+
+    let mut files = Vec::new();
+    collect_frr_files(src_dir, &mut files);
+    files.sort();
+
+    let mut dead = Vec::new();
+    for file in files {
+        let Ok(source) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for props in link.captures_iter(&source) {
+            let Some(target) = to.captures(&props[1]).map(|c| c[1].to_string()) else {
+                continue;
+            };
+            // Only plain in-app paths can be checked
+            if !target.starts_with('/') || target.starts_with("//") || target.contains('{') {
+                continue;
+            }
+
+            let path = target.split(['?', '#']).next().unwrap_or_default();
+            if path != "/" && pages::resolve(pages_dir, path).is_none() {
+                let offset = props.get(0).map_or(0, |m| m.start());
+                dead.push(DeadLink {
+                    file: file.clone(),
+                    line: source[..offset].matches('\n').count() + 1,
+                    target,
+                });
+            }
+        }
+    }
+    dead
+}
+
+fn collect_frr_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_frr_files(&path, files);
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some("frr") {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dead_links_and_variant_names() {
+        let root = std::env::temp_dir().join(format!("ferrum-routes-{}", std::process::id()));
+        let pages_dir = root.join("src/pages");
+        fs::create_dir_all(pages_dir.join("users")).unwrap();
+        fs::write(pages_dir.join("about.frr"), "").unwrap();
+        fs::write(pages_dir.join("users/[id].frr"), "").unwrap();
+        fs::write(
+            root.join("src/main.frr"),
+            concat!(
+                "Nav()\n",
+                "    Link(to: \"/about\") \"About\"\n",
+                "    Link(to: \"/abot\", class: \"nav\") \"Typo\"\n",
+                "    Link(to: \"/users/7?tab=posts\") \"User\"\n",
+                "    Link(to: \"https://example.com\") \"Elsewhere\"\n",
+                "    Link(to: \"/\") \"Home\"\n",
+            ),
+        )
+        .unwrap();
+
+        let dead = dead_links(&root.join("src"), &pages_dir);
+        assert_eq!(
+            dead,
+            [DeadLink {
+                file: root.join("src/main.frr"),
+                line: 3,
+                target: "/abot".to_string(),
+            }]
+        );
+        assert_eq!(
+            variant_name(&pages_dir, &pages_dir.join("users/[id].frr")),
+            "UsersId"
+        );
+        assert_eq!(
+            variant_name(&pages_dir, &pages_dir.join("docs/[...rest].frr")),
+            "DocsRest"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,6 +1,27 @@
-use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+mod boundary;
+mod context;
+#[cfg(feature = "client")]
+mod driver;
+mod lifecycle;
+mod list;
+mod prop_value;
+mod registry;
+mod runtime;
+mod view;
+
+pub use boundary::{
+    suspend, ErrorBoundary, ErrorBoundaryMsg, ErrorBoundaryProps, Suspense, SuspenseProps,
+};
+pub use context::{provide_context, use_context};
+pub use lifecycle::{on_cleanup, on_mount, on_update};
+pub use list::{For, ForMsg, ForProps};
+pub use prop_value::{from_prop_value, to_prop_value, PropValueSerializer};
+pub use registry::{ComponentRegistry, Namespace, NAMESPACE_SEPARATOR};
+pub use runtime::{child, handler, scope, AnyComponent, RemoteScope, Runtime, Scope};
+pub use view::{ComponentNode, ComponentView, Element, Event, EventHandler};
+
 /// Trait that all components must implement
 pub trait Component: 'static {
     type Props;
@@ -11,18 +32,25 @@ pub trait Component: 'static {
         Self: Sized;
     fn update(&mut self, msg: Self::Msg) -> bool;
     fn view(&self) -> ComponentView;
-}
 
-/// Represents a rendered component view
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ComponentView {
-    pub tag: String,
-    pub props: HashMap<String, PropValue>,
-    pub children: Vec<ComponentView>,
+    /// Take the props a re-rendering parent passed; return true to re-render
+    ///
+    /// The default keeps the current state and ignores the new props.
+    fn changed(&mut self, _props: Self::Props) -> bool {
+        false
+    }
+
+    /// Handle an error from a component below this one
+    ///
+    /// Returning `Ok` renders this component again; the default gives the
+    /// error back, passing it on to this component's parent.
+    fn catch(&mut self, error: crate::FerrumError) -> crate::Result<()> {
+        Err(error)
+    }
 }
 
 /// Values that can be assigned to component properties
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PropValue {
     String(String),
     Number(f64),
@@ -32,43 +60,134 @@ pub enum PropValue {
     Null,
 }
 
-/// Component registry for managing component instances
-pub struct ComponentRegistry {
-    components: HashMap<String, String>, // Simplified for now - store component names
-}
-
-impl ComponentRegistry {
-    pub fn new() -> Self {
-        Self {
-            components: HashMap::new(),
-        }
-    }
-
-    pub fn register(&mut self, name: String) {
-        self.components
-            .insert(name.clone(), "registered".to_string());
-        log::debug!("Registered component: {}", name);
-    }
-
-    pub fn get_component(&self, name: &str) -> Option<&str> {
-        self.components.get(name).map(|s| s.as_str())
-    }
-}
-
-/// Macro for creating components with reduced boilerplate
+/// Define a component from its props, state and messages
+///
+/// Generates `<Name>Props`, with `Default` built from each prop's `= value`
+/// (or the type's default) and a builder method per prop; `<Name>Msg`, an
+/// enum of the messages; and `<Name>` holding `props` and the state fields,
+/// with a [`Component`] impl. `update` passes each message to the method
+/// `on_<message>` (in snake case) along with its data, and `view` calls
+/// `render`; both go in an `impl <Name>` block.
+///
+/// ```ignore
+/// component! {
+///     pub Counter {
+///         props: { label: String, step: i32 = 1 },
+///         state: { count: i32 },
+///         msg: { Increment, Set(i32) }
+///     }
+/// }
+///
+/// impl Counter {
+///     fn on_increment(&mut self) -> bool {
+///         self.count += self.props.step;
+///         true
+///     }
+///
+///     fn on_set(&mut self, count: i32) -> bool {
+///         self.count = count;
+///         true
+///     }
+///
+///     fn render(&self) -> ComponentView {
+///         ComponentView::text(format!("{}: {}", self.props.label, self.count))
+///     }
+/// }
+/// ```
 #[macro_export]
 macro_rules! component {
     (
-        $name:ident {
-            props: { $($prop_name:ident: $prop_type:ty),* $(,)? },
-            state: { $($state_name:ident: $state_type:ty),* $(,)? },
-            msg: { $($msg_variant:ident $(($msg_data:ty))? ),* $(,)? }
+        $vis:vis $name:ident {
+            props: { $($prop_name:ident: $prop_type:ty $(= $prop_default:expr)?),* $(,)? },
+            state: { $($state_name:ident: $state_type:ty $(= $state_init:expr)?),* $(,)? },
+            msg: { $($msg_variant:ident $(($msg_data:ty))?),* $(,)? } $(,)?
         }
     ) => {
-        // This macro would expand to generate the full component implementation
-        // For now, it's a placeholder for the macro we'll implement later
-        struct $name {
-            $($state_name: $state_type,)*
+        $crate::paste::paste! {
+            #[derive(Debug, Clone)]
+            $vis struct [<$name Props>] {
+                $(pub $prop_name: $prop_type,)*
+            }
+
+            impl ::std::default::Default for [<$name Props>] {
+                fn default() -> Self {
+                    Self {
+                        $($prop_name: $crate::__component_value!($($prop_default)?),)*
+                    }
+                }
+            }
+
+            impl [<$name Props>] {
+                $(
+                    pub fn $prop_name(mut self, value: impl ::std::convert::Into<$prop_type>) -> Self {
+                        self.$prop_name = value.into();
+                        self
+                    }
+                )*
+            }
+
+            #[derive(Debug)]
+            $vis enum [<$name Msg>] {
+                $($msg_variant $(($msg_data))?,)*
+            }
+
+            $vis struct $name {
+                props: [<$name Props>],
+                $($state_name: $state_type,)*
+            }
+
+            impl $crate::component::Component for $name {
+                type Props = [<$name Props>];
+                type Msg = [<$name Msg>];
+
+                fn create(props: Self::Props) -> Self {
+                    Self {
+                        props,
+                        $($state_name: $crate::__component_value!($($state_init)?),)*
+                    }
+                }
+
+                fn update(&mut self, msg: Self::Msg) -> bool {
+                    match msg {
+                        $(
+                            [<$name Msg>]::$msg_variant $(($crate::__component_bind!($msg_data, data)))? => {
+                                self.[<on_ $msg_variant:snake>]($($crate::__component_bind!($msg_data, data))?)
+                            }
+                        )*
+                    }
+                }
+
+                fn changed(&mut self, props: Self::Props) -> bool {
+                    self.props = props;
+                    true
+                }
+
+                fn view(&self) -> $crate::component::ComponentView {
+                    self.render()
+                }
+            }
         }
     };
 }
+
+/// The given value, or the default without one
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __component_value {
+    () => {
+        ::std::default::Default::default()
+    };
+    ($value:expr) => {
+        $value
+    };
+}
+
+/// `$binding`, once per `$data`; lets a message's data be bound only for
+/// variants that have it
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __component_bind {
+    ($data:ty, $binding:ident) => {
+        $binding
+    };
+}
@@ -0,0 +1,556 @@
+//! Components that stand in for their children when those fail or load
+//!
+//! An [`ErrorBoundary`] shows a fallback once a component below it panics
+//! or returns an error from `view` or `update`. A [`Suspense`] shows one
+//! while a [`Resource`] read with [`suspend`] below it is loading, keeping
+//! the children mounted but hidden so their state survives.
+
+use super::runtime::panic_message;
+use super::{
+    on_mount, on_update, provide_context, scope, use_context, Component, ComponentView, Element,
+    RemoteScope,
+};
+use crate::state::{Resource, Signal, Subscription};
+use crate::FerrumError;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+pub struct ErrorBoundaryProps {
+    pub children: Rc<dyn Fn() -> ComponentView>,
+    pub fallback: Rc<dyn Fn(&FerrumError) -> ComponentView>,
+}
+
+impl ErrorBoundaryProps {
+    pub fn new(
+        children: impl Fn() -> ComponentView + 'static,
+        fallback: impl Fn(&FerrumError) -> ComponentView + 'static,
+    ) -> Self {
+        Self {
+            children: Rc::new(children),
+            fallback: Rc::new(fallback),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ErrorBoundaryMsg {
+    /// Forget the error and show the children again
+    Reset,
+}
+
+/// Shows `fallback` instead of `children` once something below fails
+///
+/// Catches errors from the components `children` mounts, and panics from
+/// `children` itself. Errors in `fallback` go on to the boundary's parent.
+pub struct ErrorBoundary {
+    props: ErrorBoundaryProps,
+    error: Option<FerrumError>,
+}
+
+impl Component for ErrorBoundary {
+    type Props = ErrorBoundaryProps;
+    type Msg = ErrorBoundaryMsg;
+
+    fn create(props: ErrorBoundaryProps) -> Self {
+        Self { props, error: None }
+    }
+
+    fn update(&mut self, msg: ErrorBoundaryMsg) -> bool {
+        match msg {
+            ErrorBoundaryMsg::Reset => self.error.take().is_some(),
+        }
+    }
+
+    fn changed(&mut self, props: ErrorBoundaryProps) -> bool {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> ComponentView {
+        if let Some(error) = &self.error {
+            return (self.props.fallback)(error);
+        }
+
+        panic::catch_unwind(AssertUnwindSafe(|| (self.props.children)())).unwrap_or_else(
+            |payload| {
+                let error = FerrumError::Component(format!(
+                    "error boundary children panicked: {}",
+                    panic_message(&*payload)
+                ));
+                log::warn!("Error boundary caught: {}", error);
+                (self.props.fallback)(&error)
+            },
+        )
+    }
+
+    fn catch(&mut self, error: FerrumError) -> crate::Result<()> {
+        log::warn!("Error boundary caught: {}", error);
+        self.error = Some(error);
+        Ok(())
+    }
+}
+
+pub struct SuspenseProps {
+    pub children: Rc<dyn Fn() -> ComponentView>,
+    pub fallback: ComponentView,
+}
+
+impl SuspenseProps {
+    pub fn new(
+        children: impl Fn() -> ComponentView + 'static,
+        fallback: impl Into<ComponentView>,
+    ) -> Self {
+        Self {
+            children: Rc::new(children),
+            fallback: fallback.into(),
+        }
+    }
+}
+
+/// Shows `fallback` while any resource read with [`suspend`] below it loads
+///
+/// The children stay mounted under a hidden `div` in the meantime.
+pub struct Suspense {
+    props: SuspenseProps,
+    watching: Rc<Watching>,
+}
+
+/// The loading signals of the resources read below a [`Suspense`]
+#[derive(Default)]
+struct Watching {
+    signals: RefCell<Vec<Watched>>,
+    /// Tells the suspense when one of them changes
+    scope: RefCell<Option<RemoteScope<()>>>,
+    renders: Cell<usize>,
+}
+
+struct Watched {
+    signal: Signal<bool>,
+    _subscription: Subscription,
+    /// Whether it was read during the current render
+    seen: bool,
+}
+
+impl Watching {
+    fn watch(&self, loading: &Signal<bool>) {
+        let mut signals = self.signals.borrow_mut();
+        if let Some(watched) = signals.iter_mut().find(|w| w.signal.ptr_eq(loading)) {
+            watched.seen = true;
+            return;
+        }
+        let Some(scope) = self.scope.borrow().clone() else {
+            return;
+        };
+
+        if loading.get_untracked() {
+            scope.send(());
+        }
+        let subscription = loading.subscribe(move |_| scope.send(()));
+        signals.push(Watched {
+            signal: loading.clone(),
+            _subscription: subscription,
+            seen: true,
+        });
+    }
+
+    /// Start a render, in which the children read their resources again;
+    /// returns how many renders there were before it
+    fn begin(&self) -> usize {
+        for watched in self.signals.borrow_mut().iter_mut() {
+            watched.seen = false;
+        }
+        self.renders.replace(self.renders.get() + 1)
+    }
+
+    /// Stop watching what the render didn't read, rendering again if that
+    /// changes whether the fallback should show
+    fn settle(&self, shown: bool) {
+        let removed = {
+            let mut signals = self.signals.borrow_mut();
+            let before = signals.len();
+            signals.retain(|watched| watched.seen);
+            signals.len() < before
+        };
+        if removed && self.loading() != shown {
+            if let Some(scope) = self.scope.borrow().as_ref() {
+                scope.send(());
+            }
+        }
+    }
+
+    fn loading(&self) -> bool {
+        self.signals
+            .borrow()
+            .iter()
+            .any(|watched| watched.signal.get_untracked())
+    }
+}
+
+/// Differs on every render of its suspense, so every child renders again
+/// and the resources read below are exactly those they read this time
+#[derive(Clone)]
+struct SuspenseContext {
+    watching: Rc<Watching>,
+    render: usize,
+}
+
+impl PartialEq for SuspenseContext {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.watching, &other.watching) && self.render == other.render
+    }
+}
+
+impl Component for Suspense {
+    type Props = SuspenseProps;
+    /// A watched resource started or finished loading
+    type Msg = ();
+
+    fn create(props: SuspenseProps) -> Self {
+        Self {
+            props,
+            watching: Rc::default(),
+        }
+    }
+
+    fn update(&mut self, _: ()) -> bool {
+        true
+    }
+
+    fn changed(&mut self, props: SuspenseProps) -> bool {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> ComponentView {
+        if let Some(scope) = scope::<Self>() {
+            *self.watching.scope.borrow_mut() = Some(scope.remote());
+        }
+        provide_context(SuspenseContext {
+            watching: self.watching.clone(),
+            render: self.watching.begin(),
+        });
+        let loading = self.watching.loading();
+        let watching = self.watching.clone();
+        let settle = move || watching.settle(loading);
+        on_mount(settle.clone());
+        on_update(settle);
+
+        let children = (self.props.children)();
+        if !loading {
+            return children;
+        }
+        ComponentView::Fragment(vec![
+            Element::new("div")
+                .prop("hidden", true)
+                .child(children)
+                .into(),
+            self.props.fallback.clone(),
+        ])
+    }
+}
+
+/// Have the nearest [`Suspense`] show its fallback while `resource` loads
+///
+/// Call it from a `view`; returns whether `resource` is loading.
+pub fn suspend<T: Clone + Send + Sync + 'static>(resource: &Resource<T>) -> bool {
+    let loading = resource.loading();
+    if let Some(SuspenseContext { watching, .. }) = use_context() {
+        watching.watch(&loading);
+    }
+    loading.get_untracked()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::{child, on_cleanup, Runtime, Scope};
+    use std::cell::Cell;
+
+    type Slot = Rc<RefCell<Option<Scope<bool>>>>;
+
+    /// Panics in `update` on `true`, sharing its scope through the slot
+    struct Fragile(Slot);
+
+    impl Component for Fragile {
+        type Props = Slot;
+        type Msg = bool;
+
+        fn create(slot: Slot) -> Self {
+            Self(slot)
+        }
+
+        fn update(&mut self, fail: bool) -> bool {
+            assert!(!fail, "update failed");
+            false
+        }
+
+        fn view(&self) -> ComponentView {
+            *self.0.borrow_mut() = scope::<Self>();
+            ComponentView::text("fine")
+        }
+    }
+
+    struct Broken;
+
+    impl Component for Broken {
+        type Props = ();
+        type Msg = ();
+
+        fn create(_: ()) -> Self {
+            Self
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> ComponentView {
+            panic!("view failed")
+        }
+    }
+
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+
+    /// Logs its mount and cleanup hooks
+    struct Logged(Log);
+
+    impl Component for Logged {
+        type Props = Log;
+        type Msg = ();
+
+        fn create(log: Log) -> Self {
+            Self(log)
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> ComponentView {
+            let log = self.0.clone();
+            on_mount(move || log.borrow_mut().push("mount"));
+            let log = self.0.clone();
+            on_cleanup(move || log.borrow_mut().push("cleanup"));
+            ComponentView::text("logged")
+        }
+    }
+
+    fn boundary(children: impl Fn() -> ComponentView + 'static) -> ErrorBoundaryProps {
+        ErrorBoundaryProps::new(children, |error| {
+            ComponentView::text(format!("caught: {}", error))
+        })
+    }
+
+    fn text(view: &ComponentView) -> String {
+        view.nodes()
+            .into_iter()
+            .map(|node| match node {
+                ComponentView::Text(text) => text.as_str(),
+                _ => "",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_error_boundary_catches_view_and_update() {
+        let runtime =
+            Runtime::<ErrorBoundary>::mount(boundary(|| child::<Broken>("broken", ()))).unwrap();
+        let shown = text(runtime.view());
+        assert!(shown.starts_with("caught:") && shown.contains("view failed"));
+
+        let slot = Slot::default();
+        let props = boundary({
+            let slot = slot.clone();
+            move || child::<Fragile>("fragile", slot.clone())
+        });
+        let mut runtime = Runtime::<ErrorBoundary>::mount(props).unwrap();
+        assert_eq!(text(runtime.view()), "fine");
+
+        slot.borrow().clone().unwrap().send(true);
+        assert!(runtime.process().unwrap());
+        assert!(text(runtime.view()).contains("update failed"));
+
+        runtime.scope().send(ErrorBoundaryMsg::Reset);
+        runtime.process().unwrap();
+        assert_eq!(text(runtime.view()), "fine");
+
+        // Without a boundary the error reaches the caller
+        assert!(Runtime::<Broken>::mount(()).is_err());
+    }
+
+    #[test]
+    fn test_error_boundary_drops_hooks_of_the_failed_render() {
+        let log = Log::default();
+        let props = boundary({
+            let log = log.clone();
+            move || {
+                ComponentView::Fragment(vec![
+                    child::<Logged>("logged", log.clone()),
+                    child::<Broken>("broken", ()),
+                ])
+            }
+        });
+        let runtime = Runtime::<ErrorBoundary>::mount(props).unwrap();
+        assert!(text(runtime.view()).starts_with("caught:"));
+
+        // The child rendered before its sibling failed, but never mounted,
+        // so neither its mount hook nor its cleanup runs
+        assert!(log.borrow().is_empty());
+    }
+
+    /// Logs its cleanups, and mounts a [`Broken`] child once sent `true`
+    struct Breaking {
+        log: Log,
+        slot: Slot,
+        broken: bool,
+    }
+
+    impl Component for Breaking {
+        type Props = (Log, Slot);
+        type Msg = bool;
+
+        fn create((log, slot): (Log, Slot)) -> Self {
+            Self {
+                log,
+                slot,
+                broken: false,
+            }
+        }
+
+        fn update(&mut self, broken: bool) -> bool {
+            self.broken = broken;
+            true
+        }
+
+        fn view(&self) -> ComponentView {
+            *self.slot.borrow_mut() = scope::<Self>();
+            let log = self.log.clone();
+            on_cleanup(move || log.borrow_mut().push("cleanup"));
+            if self.broken {
+                child::<Broken>("broken", ())
+            } else {
+                ComponentView::text("fine")
+            }
+        }
+    }
+
+    #[test]
+    fn test_failed_render_keeps_no_cleanups() {
+        let log = Log::default();
+        let slot = Slot::default();
+        let props = boundary({
+            let (log, slot) = (log.clone(), slot.clone());
+            move || child::<Breaking>("breaking", (log.clone(), slot.clone()))
+        });
+        let mut runtime = Runtime::<ErrorBoundary>::mount(props).unwrap();
+
+        slot.borrow().clone().unwrap().send(true);
+        runtime.process().unwrap();
+        assert!(text(runtime.view()).starts_with("caught:"));
+
+        // The first render's cleanup ran before the failed one, whose
+        // cleanup is dropped as the boundary unmounts the component
+        assert_eq!(*log.borrow(), ["cleanup"]);
+    }
+
+    /// Reads a resource, showing whether it has data
+    struct Reader(Resource<i32>);
+
+    impl Component for Reader {
+        type Props = Resource<i32>;
+        type Msg = ();
+
+        fn create(resource: Resource<i32>) -> Self {
+            Self(resource)
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> ComponentView {
+            suspend(&self.0);
+            ComponentView::text("ready")
+        }
+    }
+
+    #[test]
+    fn test_suspense_shows_fallback_while_loading() {
+        let resource = Resource::<i32>::new();
+        let props = SuspenseProps::new(
+            {
+                let resource = resource.clone();
+                move || child::<Reader>("reader", resource.clone())
+            },
+            ComponentView::text("loading"),
+        );
+        let mut runtime = Runtime::<Suspense>::mount(props).unwrap();
+        assert_eq!(text(runtime.view()), "ready");
+
+        let loading = resource.loading();
+        std::thread::spawn(move || loading.set(true))
+            .join()
+            .unwrap();
+        assert!(runtime.process().unwrap());
+        let nodes = runtime.view().nodes();
+        assert!(matches!(nodes[0], ComponentView::Element(element) if element.tag == "div"));
+        assert_eq!(text(runtime.view()), "loading");
+
+        resource.loading().set(false);
+        runtime.process().unwrap();
+        assert_eq!(text(runtime.view()), "ready");
+    }
+
+    /// Reads its resource while the shared flag is set
+    struct Sometimes(Resource<i32>, Rc<Cell<bool>>);
+
+    impl Component for Sometimes {
+        type Props = (Resource<i32>, Rc<Cell<bool>>);
+        type Msg = ();
+
+        fn create((resource, reading): Self::Props) -> Self {
+            Self(resource, reading)
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> ComponentView {
+            if self.1.get() {
+                suspend(&self.0);
+            }
+            ComponentView::text("ready")
+        }
+    }
+
+    #[test]
+    fn test_suspense_forgets_resources_no_longer_read() {
+        let resource = Resource::<i32>::new();
+        let reading = Rc::new(Cell::new(true));
+        let props = SuspenseProps::new(
+            {
+                let props = (resource.clone(), reading.clone());
+                move || child::<Sometimes>("sometimes", props.clone())
+            },
+            ComponentView::text("loading"),
+        );
+        let mut runtime = Runtime::<Suspense>::mount(props).unwrap();
+
+        resource.loading().set(true);
+        runtime.process().unwrap();
+        assert_eq!(text(runtime.view()), "loading");
+
+        // Once the child stops reading it, the resource no longer suspends
+        reading.set(false);
+        runtime.scope().send(());
+        runtime.process().unwrap();
+        assert_eq!(text(runtime.view()), "ready");
+
+        resource.loading().set(false);
+        resource.loading().set(true);
+        assert!(!runtime.process().unwrap());
+        assert_eq!(text(runtime.view()), "ready");
+    }
+}
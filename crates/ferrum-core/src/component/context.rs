@@ -0,0 +1,289 @@
+//! Values passed down a component tree without props
+//!
+//! A component calls [`provide_context`] in its `view` to make a value
+//! available to itself and every component below it, and [`use_context`]
+//! to read the nearest value of a type. A provider lower in the tree
+//! overrides one above it for its own subtree. Values for the whole tree
+//! come from [`Runtime::provide_context`](super::Runtime::provide_context).
+
+use super::runtime::current_contexts;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A provided value, comparable with the one provided on the last render
+trait Provided {
+    fn as_any(&self) -> &dyn Any;
+    fn same(&self, other: &dyn Provided) -> bool;
+}
+
+impl<T: PartialEq + 'static> Provided for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn same(&self, other: &dyn Provided) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+}
+
+type Values = HashMap<TypeId, Rc<dyn Provided>>;
+
+/// Values one component provides, and the ones provided above it
+#[derive(Default)]
+pub(super) struct Contexts {
+    values: RefCell<Values>,
+    /// The values the owner's children last rendered with
+    committed: RefCell<Values>,
+    parent: Option<Rc<Contexts>>,
+}
+
+impl Contexts {
+    /// Contexts for a component below the one owning `parent`
+    pub(super) fn below(parent: &Rc<Contexts>) -> Rc<Contexts> {
+        Rc::new(Self {
+            values: RefCell::default(),
+            committed: RefCell::default(),
+            parent: Some(parent.clone()),
+        })
+    }
+
+    pub(super) fn insert<T: PartialEq + 'static>(&self, value: T) {
+        self.values
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), Rc::new(value));
+    }
+
+    /// Forget the values provided here, before the owner renders again
+    pub(super) fn clear(&self) {
+        self.values.borrow_mut().clear();
+    }
+
+    /// Whether the values provided here differ from the committed ones
+    pub(super) fn changed(&self) -> bool {
+        let values = self.values.borrow();
+        let committed = self.committed.borrow();
+        values.len() != committed.len()
+            || values.iter().any(|(type_id, value)| {
+                committed.get(type_id).is_none_or(|old| !value.same(&**old))
+            })
+    }
+
+    /// Record the values provided here as the ones the children rendered
+    /// with
+    pub(super) fn commit(&self) {
+        *self.committed.borrow_mut() = self.values.borrow().clone();
+    }
+
+    /// The nearest `T`, starting here
+    pub(super) fn get<T: Clone + 'static>(&self) -> Option<T> {
+        let value = self.values.borrow().get(&TypeId::of::<T>()).cloned();
+        match value {
+            Some(value) => value.as_any().downcast_ref::<T>().cloned(),
+            None => self.parent.as_ref()?.get(),
+        }
+    }
+}
+
+/// Provide `value` to the component being rendered and everything below it
+///
+/// Replaces a `T` provided further up for this subtree. Values are provided
+/// anew on each render, so one the `view` stops providing goes away. The
+/// components below render again only when a value differs from the one
+/// they last rendered with, or stops being provided.
+pub fn provide_context<T: PartialEq + 'static>(value: T) {
+    match current_contexts() {
+        Some(contexts) => contexts.insert(value),
+        None => log::warn!(
+            "provide_context::<{}> called outside a component's view",
+            std::any::type_name::<T>()
+        ),
+    }
+}
+
+/// The nearest `T` provided to the component being rendered
+///
+/// `None` outside a component's `view`, or when no `T` was provided.
+pub fn use_context<T: Clone + 'static>() -> Option<T> {
+    current_contexts()?.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::{child, scope, Component, ComponentView, Runtime, Scope};
+    use crate::css::{use_theme, Theme};
+
+    /// Shows the string and theme color provided to it
+    struct Leaf;
+
+    impl Component for Leaf {
+        type Props = ();
+        type Msg = ();
+
+        fn create(_: ()) -> Self {
+            Self
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> ComponentView {
+            let provided = use_context::<String>().unwrap_or_else(|| "none".to_string());
+            ComponentView::text(format!("{}:{} ", provided, use_theme().colors.primary))
+        }
+    }
+
+    type Slot = Rc<RefCell<Option<Scope<bool>>>>;
+
+    /// Overrides the string for its leaf while `provide` is set, sharing its
+    /// scope through the slot
+    struct Middle {
+        provide: bool,
+        slot: Slot,
+    }
+
+    impl Component for Middle {
+        type Props = Slot;
+        type Msg = bool;
+
+        fn create(slot: Slot) -> Self {
+            Self {
+                provide: true,
+                slot,
+            }
+        }
+
+        fn update(&mut self, provide: bool) -> bool {
+            self.provide = provide;
+            true
+        }
+
+        fn view(&self) -> ComponentView {
+            *self.slot.borrow_mut() = scope::<Self>();
+            if self.provide {
+                provide_context("inner".to_string());
+            }
+            child::<Leaf>("leaf", ())
+        }
+    }
+
+    struct Root(Slot);
+
+    impl Component for Root {
+        type Props = Slot;
+        type Msg = ();
+
+        fn create(slot: Slot) -> Self {
+            Self(slot)
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> ComponentView {
+            ComponentView::Fragment(vec![
+                child::<Leaf>("leaf", ()),
+                child::<Middle>("middle", self.0.clone()),
+            ])
+        }
+    }
+
+    fn text(view: &ComponentView) -> String {
+        view.nodes()
+            .into_iter()
+            .map(|node| match node {
+                ComponentView::Text(text) => text.as_str(),
+                _ => "",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_contexts_reach_subtrees_and_nest() {
+        let slot = Slot::default();
+        let mut runtime = Runtime::<Root>::mount(slot.clone()).unwrap();
+        assert_eq!(text(runtime.view()), "none:#3b82f6 inner:#3b82f6 ");
+
+        let mut theme = Theme::default();
+        theme.colors.primary = "#000".to_string();
+        runtime.provide_context(theme).unwrap();
+        runtime.provide_context("outer".to_string()).unwrap();
+        assert_eq!(text(runtime.view()), "outer:#000 inner:#000 ");
+
+        // Once the middle stops providing, its leaf sees the outer value
+        slot.borrow().clone().unwrap().send(false);
+        runtime.process().unwrap();
+        assert_eq!(text(runtime.view()), "outer:#000 outer:#000 ");
+
+        assert!(use_context::<String>().is_none());
+    }
+
+    type Renders = Rc<std::cell::Cell<usize>>;
+
+    /// Shows the string provided to it, counting its renders
+    struct Counted(Renders);
+
+    impl Component for Counted {
+        type Props = Renders;
+        type Msg = ();
+
+        fn create(renders: Renders) -> Self {
+            Self(renders)
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> ComponentView {
+            self.0.set(self.0.get() + 1);
+            ComponentView::text(use_context::<String>().unwrap_or_default())
+        }
+    }
+
+    /// Provides the last string sent to it
+    struct Provider {
+        value: String,
+        renders: Renders,
+    }
+
+    impl Component for Provider {
+        type Props = Renders;
+        type Msg = String;
+
+        fn create(renders: Renders) -> Self {
+            Self {
+                value: "first".to_string(),
+                renders,
+            }
+        }
+
+        fn update(&mut self, value: String) -> bool {
+            self.value = value;
+            true
+        }
+
+        fn view(&self) -> ComponentView {
+            provide_context(self.value.clone());
+            child::<Counted>("counted", self.renders.clone())
+        }
+    }
+
+    #[test]
+    fn test_children_render_again_only_for_a_changed_context() {
+        let renders = Renders::default();
+        let mut runtime = Runtime::<Provider>::mount(renders.clone()).unwrap();
+        assert_eq!(renders.get(), 1);
+
+        runtime.dispatch("first".to_string()).unwrap();
+        assert_eq!(renders.get(), 1);
+
+        runtime.dispatch("second".to_string()).unwrap();
+        assert_eq!(renders.get(), 2);
+        assert_eq!(text(runtime.view()), "second");
+    }
+}
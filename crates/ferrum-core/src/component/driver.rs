@@ -0,0 +1,186 @@
+//! Keeping the page in step with a [`Runtime`]
+//!
+//! The driver renders the runtime's view into an element, patches it with
+//! [`vdom::diff`](crate::vdom::diff) after each re-render, and listens for
+//! events on that element. An event is traced back to the node it fired on
+//! and handed to [`Runtime::dispatch_event`], which calls the handlers in
+//! the current view.
+
+use super::{Component, ComponentView, Event, Runtime};
+use crate::vdom::{self, js_error};
+use crate::{FerrumError, Result};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::{Rc, Weak};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+impl<C> Runtime<C>
+where
+    C: Component,
+    C::Props: 'static,
+    C::Msg: 'static,
+{
+    /// Render into `root` and patch it whenever the tree changes
+    pub fn mount_to(self, root: web_sys::Element) -> Result<Rc<RefCell<Self>>> {
+        let document = root
+            .owner_document()
+            .ok_or_else(|| FerrumError::Component("element has no document".to_string()))?;
+        root.set_inner_html("");
+        for node in self.view().nodes() {
+            root.append_child(&vdom::create_node(&document, node)?)
+                .map_err(js_error)?;
+        }
+
+        let runtime = Rc::new(RefCell::new(self));
+        let driver = Rc::new(Driver {
+            runtime: Rc::downgrade(&runtime),
+            root,
+            listening: RefCell::new(HashSet::new()),
+        });
+        driver.listen(runtime.borrow().view())?;
+
+        let waker = driver.clone();
+        runtime
+            .borrow()
+            .set_wake(Rc::new(move || waker.update(Runtime::process)));
+        Ok(runtime)
+    }
+}
+
+struct Driver<C> {
+    runtime: Weak<RefCell<Runtime<C>>>,
+    root: web_sys::Element,
+    /// Events with a listener on `root`
+    listening: RefCell<HashSet<String>>,
+}
+
+impl<C> Driver<C>
+where
+    C: Component,
+    C::Props: 'static,
+    C::Msg: 'static,
+{
+    /// Run `f` on the runtime and patch the page if the tree changed
+    fn update(self: &Rc<Self>, f: impl FnOnce(&mut Runtime<C>) -> Result<bool>) {
+        let Some(runtime) = self.runtime.upgrade() else {
+            return;
+        };
+        // Already processing: the running loop delivers the message
+        let Ok(mut runtime) = runtime.try_borrow_mut() else {
+            return;
+        };
+
+        let old = runtime.view().clone();
+        let result = f(&mut runtime).and_then(|rendered| {
+            if !rendered {
+                return Ok(());
+            }
+            vdom::apply_to_dom(&self.root, &vdom::diff(&old, runtime.view()))?;
+            self.listen(runtime.view())
+        });
+        if let Err(e) = result {
+            log::error!("Re-rendering failed: {}", e);
+        }
+    }
+
+    /// Listen on the root for every event `view` has a handler for
+    ///
+    /// Listeners capture, so events that don't bubble arrive too.
+    fn listen(self: &Rc<Self>, view: &ComponentView) -> Result<()> {
+        let mut names = HashSet::new();
+        collect_events(view, &mut names);
+
+        for name in names {
+            if !self.listening.borrow_mut().insert(name.clone()) {
+                continue;
+            }
+            let driver = Rc::downgrade(self);
+            let listener = Closure::<dyn FnMut(_)>::new(move |event: web_sys::Event| {
+                let Some(driver) = driver.upgrade() else {
+                    return;
+                };
+                let Some(path) = path_to(&driver.root, event.target()) else {
+                    return;
+                };
+                // Forms with a handler are submitted by the app, not the browser
+                if event.type_() == "submit" {
+                    event.prevent_default();
+                }
+                let event = to_event(&event);
+                driver.update(|runtime| runtime.dispatch_event(&path, &event));
+            });
+            self.root
+                .add_event_listener_with_callback_and_bool(
+                    &name,
+                    listener.as_ref().unchecked_ref(),
+                    true,
+                )
+                .map_err(js_error)?;
+            listener.forget();
+        }
+        Ok(())
+    }
+}
+
+fn collect_events(view: &ComponentView, names: &mut HashSet<String>) {
+    let children = match view {
+        ComponentView::Element(element) => {
+            names.extend(element.handlers.keys().cloned());
+            &element.children
+        }
+        ComponentView::Fragment(children) => children,
+        _ => return,
+    };
+    for child in children {
+        collect_events(child, names);
+    }
+}
+
+/// Child indices from `root` down to `target`
+fn path_to(root: &web_sys::Element, target: Option<web_sys::EventTarget>) -> Option<Vec<usize>> {
+    let root: &web_sys::Node = root.as_ref();
+    let mut node = target?.dyn_into::<web_sys::Node>().ok()?;
+    let mut path = Vec::new();
+
+    while !node.is_same_node(Some(root)) {
+        let parent = node.parent_node()?;
+        let siblings = parent.child_nodes();
+        let index = (0..siblings.length()).find(|&index| {
+            siblings
+                .item(index)
+                .is_some_and(|n| n.is_same_node(Some(&node)))
+        })?;
+        path.push(index as usize);
+        node = parent;
+    }
+    path.reverse();
+    Some(path)
+}
+
+fn to_event(event: &web_sys::Event) -> Event {
+    let target = event.target();
+    let input = target
+        .as_ref()
+        .and_then(|target| target.dyn_ref::<web_sys::HtmlInputElement>());
+
+    let value = input
+        .map(|input| input.value())
+        .or_else(|| {
+            let select = target.as_ref()?.dyn_ref::<web_sys::HtmlSelectElement>()?;
+            Some(select.value())
+        })
+        .or_else(|| {
+            let textarea = target.as_ref()?.dyn_ref::<web_sys::HtmlTextAreaElement>()?;
+            Some(textarea.value())
+        });
+
+    Event {
+        name: event.type_(),
+        value,
+        checked: input.map(|input| input.checked()),
+        key: event
+            .dyn_ref::<web_sys::KeyboardEvent>()
+            .map(|event| event.key()),
+    }
+}
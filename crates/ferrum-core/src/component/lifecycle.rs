@@ -0,0 +1,139 @@
+//! Running code as components mount, re-render and unmount
+//!
+//! The hooks are called from a component's `view`, like
+//! [`provide_context`](super::provide_context). Mount and update hooks run
+//! once the runtime has finished rendering, children before their parents;
+//! cleanups run before the component renders again and when it unmounts.
+//! A render that fails keeps none of its hooks.
+
+use super::runtime::current_hooks;
+
+type Hook = Box<dyn FnOnce()>;
+
+/// Hooks registered by the `view` being rendered
+#[derive(Default)]
+pub(super) struct Hooks {
+    pub(super) mount: Vec<Hook>,
+    pub(super) update: Vec<Hook>,
+    pub(super) cleanup: Vec<Hook>,
+}
+
+/// Run `f` once the component being rendered has mounted
+///
+/// Only counts during the component's first render.
+pub fn on_mount(f: impl FnOnce() + 'static) {
+    register("on_mount", |hooks| hooks.mount.push(Box::new(f)));
+}
+
+/// Run `f` after the component being rendered renders again
+///
+/// Doesn't count during the component's first render.
+pub fn on_update(f: impl FnOnce() + 'static) {
+    register("on_update", |hooks| hooks.update.push(Box::new(f)));
+}
+
+/// Run `f` before the component being rendered renders again, or when it
+/// unmounts
+pub fn on_cleanup(f: impl FnOnce() + 'static) {
+    register("on_cleanup", |hooks| hooks.cleanup.push(Box::new(f)));
+}
+
+fn register(name: &str, f: impl FnOnce(&mut Hooks)) {
+    if current_hooks(f).is_none() {
+        log::warn!("{} called outside a component's view", name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::{child, Component, ComponentView, Runtime};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+
+    fn record(log: &Log, entry: &'static str) -> impl FnOnce() + 'static {
+        let log = log.clone();
+        move || log.borrow_mut().push(entry)
+    }
+
+    struct Child(Log);
+
+    impl Component for Child {
+        type Props = Log;
+        type Msg = ();
+
+        fn create(log: Log) -> Self {
+            Self(log)
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn changed(&mut self, _: Log) -> bool {
+            true
+        }
+
+        fn view(&self) -> ComponentView {
+            on_mount(record(&self.0, "child mount"));
+            on_update(record(&self.0, "child update"));
+            on_cleanup(record(&self.0, "child cleanup"));
+            ComponentView::text("child")
+        }
+    }
+
+    /// Shows its child while the last message was `true`
+    struct Parent {
+        log: Log,
+        show: bool,
+    }
+
+    impl Component for Parent {
+        type Props = Log;
+        type Msg = bool;
+
+        fn create(log: Log) -> Self {
+            Self { log, show: true }
+        }
+
+        fn update(&mut self, show: bool) -> bool {
+            self.show = show;
+            true
+        }
+
+        fn view(&self) -> ComponentView {
+            on_mount(record(&self.log, "parent mount"));
+            on_update(record(&self.log, "parent update"));
+            if self.show {
+                child::<Child>("child", self.log.clone())
+            } else {
+                ComponentView::text("")
+            }
+        }
+    }
+
+    #[test]
+    fn test_hooks_run_in_order() {
+        let log = Log::default();
+        let mut runtime = Runtime::<Parent>::mount(log.clone()).unwrap();
+        assert_eq!(*log.borrow(), ["child mount", "parent mount"]);
+
+        log.borrow_mut().clear();
+        runtime.scope().send(true);
+        runtime.process().unwrap();
+        assert_eq!(
+            *log.borrow(),
+            ["child cleanup", "child update", "parent update"]
+        );
+
+        log.borrow_mut().clear();
+        runtime.scope().send(false);
+        runtime.process().unwrap();
+        assert_eq!(*log.borrow(), ["child cleanup", "parent update"]);
+
+        // Outside a view the hooks are ignored
+        on_mount(|| panic!("not a component"));
+    }
+}
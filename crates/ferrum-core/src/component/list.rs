@@ -0,0 +1,339 @@
+//! Rows rendered from a [`SignalVec`], one child per item
+//!
+//! A [`For`] mounts a row component per item, keyed by the item's key, and
+//! applies the [`VecDiff`]s the list reports instead of rendering the list
+//! again. Only inserted and updated items run the row function, so an edit
+//! to one item re-renders one row, and the keyed rows it moves or keeps give
+//! [`crate::vdom::diff`] no patches.
+
+use super::{child, scope, Component, ComponentView, PropValue};
+use crate::state::{SignalVec, Subscription, VecDiff};
+use crate::vdom::KEY_PROP;
+use std::cell::RefCell;
+use std::fmt::{Debug, Display};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Tells a row apart from the same item rendered before
+static NEXT_VERSION: AtomicUsize = AtomicUsize::new(1);
+
+pub struct ForProps<T, K> {
+    pub each: SignalVec<T, K>,
+    pub row: Rc<dyn Fn(&T) -> ComponentView>,
+}
+
+impl<T, K> ForProps<T, K> {
+    pub fn new(each: SignalVec<T, K>, row: impl Fn(&T) -> ComponentView + 'static) -> Self {
+        Self {
+            each,
+            row: Rc::new(row),
+        }
+    }
+}
+
+/// Edits to the list a [`For`] shows
+pub struct ForMsg<T> {
+    /// Which subscription reported them, so edits to a list the `For` no
+    /// longer shows are dropped
+    generation: usize,
+    diffs: Vec<VecDiff<T>>,
+}
+
+/// Renders `row` for every item of `each`, keeping the list's order
+///
+/// Each row's root element gets the item's key as its `key` prop unless
+/// `row` sets one. New props re-render every row, since `row` may have
+/// changed with them.
+pub struct For<T, K> {
+    props: ForProps<T, K>,
+    rows: RefCell<Vec<Row<T>>>,
+    /// Set by the first render, which has the scope the diffs go to
+    subscription: RefCell<Option<Subscription>>,
+    generation: usize,
+}
+
+struct Row<T> {
+    key: String,
+    item: T,
+    version: usize,
+}
+
+impl<T, K> For<T, K>
+where
+    T: Clone + Send + Sync + 'static,
+    K: PartialEq + Debug + Display + 'static,
+{
+    fn row(&self, item: &T) -> Row<T> {
+        Row {
+            key: self.props.each.key_of(item).to_string(),
+            item: item.clone(),
+            version: NEXT_VERSION.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Take the current items and follow the list's edits from there
+    fn subscribe(&self) {
+        let Some(scope) = scope::<Self>() else {
+            *self.rows.borrow_mut() = self
+                .props
+                .each
+                .with(|items| items.iter().map(|item| self.row(item)).collect());
+            return;
+        };
+        let scope = scope.remote();
+        let generation = self.generation;
+
+        let (rows, subscription) = self.props.each.with(|items| {
+            let subscription = self.props.each.subscribe_diff(move |diffs| {
+                scope.send(ForMsg {
+                    generation,
+                    diffs: diffs.to_vec(),
+                })
+            });
+            (
+                items.iter().map(|item| self.row(item)).collect(),
+                subscription,
+            )
+        });
+        *self.rows.borrow_mut() = rows;
+        *self.subscription.borrow_mut() = Some(subscription);
+    }
+}
+
+impl<T, K> Component for For<T, K>
+where
+    T: Clone + Send + Sync + 'static,
+    K: PartialEq + Debug + Display + 'static,
+{
+    type Props = ForProps<T, K>;
+    type Msg = ForMsg<T>;
+
+    fn create(props: ForProps<T, K>) -> Self {
+        Self {
+            props,
+            rows: RefCell::default(),
+            subscription: RefCell::default(),
+            generation: 0,
+        }
+    }
+
+    fn update(&mut self, msg: ForMsg<T>) -> bool {
+        if msg.generation != self.generation || self.subscription.get_mut().is_none() {
+            return false;
+        }
+        let mut rows = std::mem::take(self.rows.get_mut());
+        for diff in &msg.diffs {
+            diff.apply(&mut rows, |item| self.row(item));
+        }
+        *self.rows.get_mut() = rows;
+        true
+    }
+
+    fn changed(&mut self, props: ForProps<T, K>) -> bool {
+        if !props.each.ptr_eq(&self.props.each) {
+            self.subscription.get_mut().take();
+            self.generation += 1;
+        }
+        self.props = props;
+        for row in self.rows.get_mut() {
+            row.version = NEXT_VERSION.fetch_add(1, Ordering::Relaxed);
+        }
+        true
+    }
+
+    fn view(&self) -> ComponentView {
+        if self.subscription.borrow().is_none() {
+            self.subscribe();
+        }
+
+        let rows = self.rows.borrow();
+        ComponentView::Fragment(
+            rows.iter()
+                .map(|row| {
+                    child::<ForRow<T>>(
+                        row.key.clone(),
+                        RowProps {
+                            key: row.key.clone(),
+                            item: row.item.clone(),
+                            version: row.version,
+                            render: self.props.row.clone(),
+                        },
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+/// One item of a [`For`], rendered again only when its version changes
+struct ForRow<T> {
+    props: RowProps<T>,
+}
+
+struct RowProps<T> {
+    key: String,
+    item: T,
+    version: usize,
+    render: Rc<dyn Fn(&T) -> ComponentView>,
+}
+
+impl<T: 'static> Component for ForRow<T> {
+    type Props = RowProps<T>;
+    type Msg = ();
+
+    fn create(props: RowProps<T>) -> Self {
+        Self { props }
+    }
+
+    fn update(&mut self, _: ()) -> bool {
+        false
+    }
+
+    fn changed(&mut self, props: RowProps<T>) -> bool {
+        let changed = props.version != self.props.version;
+        self.props = props;
+        changed
+    }
+
+    fn view(&self) -> ComponentView {
+        match (self.props.render)(&self.props.item) {
+            ComponentView::Element(mut element) => {
+                element
+                    .props
+                    .entry(KEY_PROP.to_string())
+                    .or_insert_with(|| PropValue::String(self.props.key.clone()));
+                ComponentView::Element(element)
+            }
+            view => view,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::{Element, Runtime};
+    use crate::vdom::{self, Patch};
+
+    #[derive(Debug, Clone)]
+    struct Task {
+        id: u32,
+        title: String,
+    }
+
+    fn task(id: u32, title: &str) -> Task {
+        Task {
+            id,
+            title: title.to_string(),
+        }
+    }
+
+    type Rendered = Rc<RefCell<Vec<u32>>>;
+
+    /// Shows a task list, logging which tasks it renders, until sent another
+    struct Tasks {
+        tasks: SignalVec<Task, u32>,
+        rendered: Rendered,
+    }
+
+    impl Component for Tasks {
+        type Props = (SignalVec<Task, u32>, Rendered);
+        type Msg = SignalVec<Task, u32>;
+
+        fn create((tasks, rendered): Self::Props) -> Self {
+            Self { tasks, rendered }
+        }
+
+        fn update(&mut self, tasks: SignalVec<Task, u32>) -> bool {
+            self.tasks = tasks;
+            true
+        }
+
+        fn view(&self) -> ComponentView {
+            let rendered = self.rendered.clone();
+            Element::new("ul")
+                .child(child::<For<Task, u32>>(
+                    "tasks",
+                    ForProps::new(self.tasks.clone(), move |task| {
+                        rendered.borrow_mut().push(task.id);
+                        Element::new("li")
+                            .child(ComponentView::text(task.title.as_str()))
+                            .into()
+                    }),
+                ))
+                .into()
+        }
+    }
+
+    fn titles(view: &ComponentView) -> Vec<String> {
+        vdom::flatten(view)[0]
+            .as_element()
+            .unwrap()
+            .children
+            .iter()
+            .map(|row| match &row.as_element().unwrap().children[0] {
+                ComponentView::Text(title) => title.clone(),
+                other => panic!("not a title: {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_edits_render_only_the_rows_they_touch() {
+        let tasks = SignalVec::new(|task: &Task| task.id);
+        tasks.push(task(1, "Write docs")).unwrap();
+        tasks.push(task(2, "Ship it")).unwrap();
+        let rendered = Rendered::default();
+        let mut runtime = Runtime::<Tasks>::mount((tasks.clone(), rendered.clone())).unwrap();
+        assert_eq!(*rendered.borrow(), [1, 2]);
+
+        let before = runtime.view().clone();
+        tasks.push(task(3, "Celebrate")).unwrap();
+        assert!(runtime.process().unwrap());
+        assert_eq!(*rendered.borrow(), [1, 2, 3]);
+        assert_eq!(
+            titles(runtime.view()),
+            ["Write docs", "Ship it", "Celebrate"]
+        );
+        let patches = vdom::diff(&before, runtime.view());
+        assert!(
+            matches!(&patches[..], [Patch::Insert { index: 2, .. }]),
+            "{:?}",
+            patches
+        );
+
+        tasks
+            .update_item(&1, |task| task.title = "Write more docs".to_string())
+            .unwrap();
+        tasks.move_item(&3, 0).unwrap();
+        tasks.remove_by_key(&2).unwrap();
+        runtime.process().unwrap();
+        assert_eq!(*rendered.borrow(), [1, 2, 3, 1]);
+        assert_eq!(titles(runtime.view()), ["Celebrate", "Write more docs"]);
+
+        tasks.set(vec![task(4, "Rest")]).unwrap();
+        runtime.process().unwrap();
+        assert_eq!(*rendered.borrow(), [1, 2, 3, 1, 4]);
+        assert_eq!(titles(runtime.view()), ["Rest"]);
+    }
+
+    #[test]
+    fn test_a_new_list_replaces_the_old_one() {
+        let first = SignalVec::new(|task: &Task| task.id);
+        first.push(task(1, "Old")).unwrap();
+        let mut runtime = Runtime::<Tasks>::mount((first.clone(), Rendered::default())).unwrap();
+
+        let second = SignalVec::new(|task: &Task| task.id);
+        second.push(task(2, "New")).unwrap();
+        // Queued behind the switch, so it arrives once the rows are the new
+        // list's
+        first.push(task(3, "Stale")).unwrap();
+        runtime.dispatch(second.clone()).unwrap();
+        assert_eq!(titles(runtime.view()), ["New"]);
+
+        first.push(task(4, "Ignored")).unwrap();
+        second.push(task(5, "Newer")).unwrap();
+        runtime.process().unwrap();
+        assert_eq!(titles(runtime.view()), ["New", "Newer"]);
+    }
+}
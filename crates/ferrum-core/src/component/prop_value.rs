@@ -0,0 +1,902 @@
+//! Converting between [`PropValue`] and Rust types
+//!
+//! A `PropValue` serializes like the JSON value it mirrors, and works as a
+//! serde `Serializer` target and `Deserializer` source the way
+//! `serde_json::Value` does, so props from a `.frr` file or a
+//! [`ComponentNode`](super::ComponentNode) can become a component's typed
+//! `Props` with [`from_prop_value`].
+
+use super::PropValue;
+use crate::FerrumError;
+use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fmt;
+
+type Error = serde_json::Error;
+
+/// Convert `value` to a [`PropValue`]
+pub fn to_prop_value<T: Serialize + ?Sized>(value: &T) -> crate::Result<PropValue> {
+    Ok(value.serialize(PropValueSerializer)?)
+}
+
+/// Build a `T` from `value`
+pub fn from_prop_value<T: DeserializeOwned>(value: PropValue) -> crate::Result<T> {
+    Ok(T::deserialize(value)?)
+}
+
+impl Serialize for PropValue {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            PropValue::String(value) => serializer.serialize_str(value),
+            PropValue::Number(value) if is_integer(*value) => {
+                serializer.serialize_i64(*value as i64)
+            }
+            PropValue::Number(value) => serializer.serialize_f64(*value),
+            PropValue::Boolean(value) => serializer.serialize_bool(*value),
+            PropValue::Array(values) => values.serialize(serializer),
+            PropValue::Object(values) => values.serialize(serializer),
+            PropValue::Null => serializer.serialize_unit(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PropValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PropValueVisitor)
+    }
+}
+
+struct PropValueVisitor;
+
+impl<'de> Visitor<'de> for PropValueVisitor {
+    type Value = PropValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a prop value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<PropValue, E> {
+        Ok(PropValue::Boolean(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<PropValue, E> {
+        Ok(PropValue::Number(value as f64))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<PropValue, E> {
+        Ok(PropValue::Number(value as f64))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<PropValue, E> {
+        Ok(PropValue::Number(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<PropValue, E> {
+        Ok(PropValue::String(value.to_string()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<PropValue, E> {
+        Ok(PropValue::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<PropValue, E> {
+        Ok(PropValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<PropValue, E> {
+        Ok(PropValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<PropValue, D::Error> {
+        PropValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<PropValue, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(PropValue::Array(values))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<PropValue, A::Error> {
+        let mut values = HashMap::new();
+        while let Some((key, value)) = map.next_entry()? {
+            values.insert(key, value);
+        }
+        Ok(PropValue::Object(values))
+    }
+}
+
+impl<'de> Deserializer<'de> for PropValue {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            PropValue::String(value) => visitor.visit_string(value),
+            // Integer fields only accept whole numbers as integers
+            PropValue::Number(value) if is_integer(value) => visitor.visit_i64(value as i64),
+            PropValue::Number(value) => visitor.visit_f64(value),
+            PropValue::Boolean(value) => visitor.visit_bool(value),
+            PropValue::Array(values) => {
+                let mut seq = SeqDeserializer::new(values.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            PropValue::Object(values) => {
+                let mut map = MapDeserializer::new(values.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            PropValue::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            PropValue::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// A unit variant is its name; any other variant is an object with the
+    /// name as its only key
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            PropValue::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            PropValue::Object(values) if values.len() == 1 => visitor.visit_enum(
+                MapAccessDeserializer::new(MapDeserializer::new(values.into_iter())),
+            ),
+            _ => Err(de::Error::invalid_type(
+                de::Unexpected::Other("prop value"),
+                &"a variant name or an object with one key",
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Every integer up to this one, and none just past it, is an exact `f64`
+const MAX_EXACT_INTEGER: u64 = 1 << 53;
+
+/// `value` as a [`PropValue::Number`], if an `f64` holds it exactly
+fn exact_integer(value: u64, negative: bool) -> Result<PropValue, Error> {
+    if value > MAX_EXACT_INTEGER {
+        let sign = if negative { "-" } else { "" };
+        return Err(ser::Error::custom(format!(
+            "{}{} is too large for a prop number",
+            sign, value
+        )));
+    }
+    let value = value as f64;
+    Ok(PropValue::Number(if negative { -value } else { value }))
+}
+
+/// Whether `value` is a whole number that fits an `i64`
+fn is_integer(value: f64) -> bool {
+    value.fract() == 0.0 && value.abs() < i64::MAX as f64
+}
+
+impl<'de> IntoDeserializer<'de, Error> for PropValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Serializes Rust values into [`PropValue`]s; see [`to_prop_value`]
+pub struct PropValueSerializer;
+
+impl ser::Serializer for PropValueSerializer {
+    type Ok = PropValue;
+    type Error = Error;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeVariant<SerializeArray>;
+    type SerializeMap = SerializeObject;
+    type SerializeStruct = SerializeObject;
+    type SerializeStructVariant = SerializeVariant<SerializeObject>;
+
+    fn serialize_bool(self, value: bool) -> Result<PropValue, Error> {
+        Ok(PropValue::Boolean(value))
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<PropValue, Error> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<PropValue, Error> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<PropValue, Error> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<PropValue, Error> {
+        exact_integer(value.unsigned_abs(), value < 0)
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<PropValue, Error> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<PropValue, Error> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<PropValue, Error> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<PropValue, Error> {
+        exact_integer(value, false)
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<PropValue, Error> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<PropValue, Error> {
+        Ok(PropValue::Number(value))
+    }
+
+    fn serialize_char(self, value: char) -> Result<PropValue, Error> {
+        Ok(PropValue::String(value.to_string()))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<PropValue, Error> {
+        Ok(PropValue::String(value.to_string()))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<PropValue, Error> {
+        Ok(PropValue::Array(value.iter().map(|&b| b.into()).collect()))
+    }
+
+    fn serialize_none(self) -> Result<PropValue, Error> {
+        Ok(PropValue::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<PropValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<PropValue, Error> {
+        Ok(PropValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<PropValue, Error> {
+        Ok(PropValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<PropValue, Error> {
+        Ok(PropValue::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<PropValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<PropValue, Error> {
+        let value = value.serialize(PropValueSerializer)?;
+        Ok(PropValue::Object(HashMap::from([(
+            variant.to_string(),
+            value,
+        )])))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, Error> {
+        Ok(SerializeArray(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Ok(SerializeVariant {
+            variant,
+            fields: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeObject, Error> {
+        Ok(SerializeObject::default())
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeObject, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Ok(SerializeVariant {
+            variant,
+            fields: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+/// Serializes a sequence or tuple into a [`PropValue::Array`]
+pub struct SerializeArray(Vec<PropValue>);
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = PropValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.0.push(value.serialize(PropValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<PropValue, Error> {
+        Ok(PropValue::Array(self.0))
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = PropValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<PropValue, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = PropValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<PropValue, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Serializes a map or struct into a [`PropValue::Object`]
+#[derive(Default)]
+pub struct SerializeObject {
+    values: HashMap<String, PropValue>,
+    next_key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeObject {
+    type Ok = PropValue;
+    type Error = Error;
+
+    /// Keys are strings; numbers and booleans are written out as text
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        let key = match key.serialize(PropValueSerializer)? {
+            PropValue::String(key) => key,
+            PropValue::Number(key) => key.to_string(),
+            PropValue::Boolean(key) => key.to_string(),
+            _ => return Err(ser::Error::custom("prop object keys must be strings")),
+        };
+        self.next_key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| ser::Error::custom("value serialized before its key"))?;
+        self.values
+            .insert(key, value.serialize(PropValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<PropValue, Error> {
+        Ok(PropValue::Object(self.values))
+    }
+}
+
+impl ser::SerializeStruct for SerializeObject {
+    type Ok = PropValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.values
+            .insert(key.to_string(), value.serialize(PropValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<PropValue, Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// Fields of a tuple or struct variant, wrapped in an object keyed by the
+/// variant's name when done
+pub struct SerializeVariant<F> {
+    variant: &'static str,
+    fields: F,
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeArray> {
+    type Ok = PropValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(&mut self.fields, value)
+    }
+
+    fn end(self) -> Result<PropValue, Error> {
+        let value = PropValue::Array(self.fields.0);
+        Ok(PropValue::Object(HashMap::from([(
+            self.variant.to_string(),
+            value,
+        )])))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeObject> {
+    type Ok = PropValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(&mut self.fields, key, value)
+    }
+
+    fn end(self) -> Result<PropValue, Error> {
+        let value = PropValue::Object(self.fields.values);
+        Ok(PropValue::Object(HashMap::from([(
+            self.variant.to_string(),
+            value,
+        )])))
+    }
+}
+
+macro_rules! number_conversions {
+    ($($ty:ty),*) => {$(
+        /// Fails unless the value is a whole number in range
+        impl TryFrom<PropValue> for $ty {
+            type Error = FerrumError;
+
+            fn try_from(value: PropValue) -> Result<Self, FerrumError> {
+                match value {
+                    // `MAX + 1` is a power of two, so unlike `MAX` it is
+                    // an exact `f64` for every type
+                    PropValue::Number(n)
+                        if n.fract() == 0.0
+                            && n >= <$ty>::MIN as f64
+                            && n < <$ty>::MAX as f64 + 1.0 =>
+                    {
+                        Ok(n as $ty)
+                    }
+                    other => Err(mismatch(stringify!($ty), &other)),
+                }
+            }
+        }
+    )*};
+}
+
+number_conversions!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// Integers every value of which an `f64` holds exactly
+macro_rules! exact_number_conversions {
+    ($($ty:ty),*) => {$(
+        impl From<$ty> for PropValue {
+            fn from(value: $ty) -> Self {
+                PropValue::Number(value.into())
+            }
+        }
+    )*};
+}
+
+exact_number_conversions!(i8, i16, i32, u8, u16, u32);
+
+/// Integers past 2^53 would round, so converting them fails instead
+macro_rules! wide_number_conversions {
+    ($($ty:ty),*) => {$(
+        impl TryFrom<$ty> for PropValue {
+            type Error = FerrumError;
+
+            fn try_from(value: $ty) -> Result<Self, FerrumError> {
+                Ok(exact_integer(value.unsigned_abs() as u64, value < 0)?)
+            }
+        }
+    )*};
+}
+
+wide_number_conversions!(i64, isize);
+
+impl TryFrom<u64> for PropValue {
+    type Error = FerrumError;
+
+    fn try_from(value: u64) -> Result<Self, FerrumError> {
+        Ok(exact_integer(value, false)?)
+    }
+}
+
+impl TryFrom<usize> for PropValue {
+    type Error = FerrumError;
+
+    fn try_from(value: usize) -> Result<Self, FerrumError> {
+        Ok(exact_integer(value as u64, false)?)
+    }
+}
+
+impl From<f64> for PropValue {
+    fn from(value: f64) -> Self {
+        PropValue::Number(value)
+    }
+}
+
+impl From<f32> for PropValue {
+    fn from(value: f32) -> Self {
+        PropValue::Number(value.into())
+    }
+}
+
+impl From<bool> for PropValue {
+    fn from(value: bool) -> Self {
+        PropValue::Boolean(value)
+    }
+}
+
+impl From<String> for PropValue {
+    fn from(value: String) -> Self {
+        PropValue::String(value)
+    }
+}
+
+impl From<&str> for PropValue {
+    fn from(value: &str) -> Self {
+        PropValue::String(value.to_string())
+    }
+}
+
+impl<T: Into<PropValue>> From<Option<T>> for PropValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(PropValue::Null, Into::into)
+    }
+}
+
+impl<T: Into<PropValue>> From<Vec<T>> for PropValue {
+    fn from(values: Vec<T>) -> Self {
+        PropValue::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<PropValue>> From<HashMap<String, T>> for PropValue {
+    fn from(values: HashMap<String, T>) -> Self {
+        PropValue::Object(
+            values
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect(),
+        )
+    }
+}
+
+impl TryFrom<PropValue> for f64 {
+    type Error = FerrumError;
+
+    fn try_from(value: PropValue) -> Result<Self, FerrumError> {
+        match value {
+            PropValue::Number(n) => Ok(n),
+            other => Err(mismatch("f64", &other)),
+        }
+    }
+}
+
+impl TryFrom<PropValue> for f32 {
+    type Error = FerrumError;
+
+    fn try_from(value: PropValue) -> Result<Self, FerrumError> {
+        f64::try_from(value).map(|n| n as f32)
+    }
+}
+
+impl TryFrom<PropValue> for bool {
+    type Error = FerrumError;
+
+    fn try_from(value: PropValue) -> Result<Self, FerrumError> {
+        match value {
+            PropValue::Boolean(value) => Ok(value),
+            other => Err(mismatch("bool", &other)),
+        }
+    }
+}
+
+impl TryFrom<PropValue> for String {
+    type Error = FerrumError;
+
+    fn try_from(value: PropValue) -> Result<Self, FerrumError> {
+        match value {
+            PropValue::String(value) => Ok(value),
+            other => Err(mismatch("String", &other)),
+        }
+    }
+}
+
+impl<T> TryFrom<PropValue> for Vec<T>
+where
+    T: TryFrom<PropValue, Error = FerrumError>,
+{
+    type Error = FerrumError;
+
+    fn try_from(value: PropValue) -> Result<Self, FerrumError> {
+        match value {
+            PropValue::Array(values) => values.into_iter().map(T::try_from).collect(),
+            other => Err(mismatch("Vec", &other)),
+        }
+    }
+}
+
+impl<T> TryFrom<PropValue> for HashMap<String, T>
+where
+    T: TryFrom<PropValue, Error = FerrumError>,
+{
+    type Error = FerrumError;
+
+    fn try_from(value: PropValue) -> Result<Self, FerrumError> {
+        match value {
+            PropValue::Object(values) => values
+                .into_iter()
+                .map(|(key, value)| Ok((key, T::try_from(value)?)))
+                .collect(),
+            other => Err(mismatch("HashMap", &other)),
+        }
+    }
+}
+
+fn mismatch(expected: &str, found: &PropValue) -> FerrumError {
+    FerrumError::Component(format!("expected {} prop, found {:?}", expected, found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde::Serialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Size {
+        Small,
+        Custom(u32),
+        Scaled { width: u32, height: u32 },
+        Pair(u8, u8),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Props {
+        title: String,
+        count: i32,
+        ratio: f64,
+        visible: bool,
+        tags: Vec<String>,
+        limits: HashMap<String, u16>,
+        subtitle: Option<String>,
+        sizes: Vec<Size>,
+    }
+
+    #[test]
+    fn test_props_round_trip_through_prop_values() {
+        let props = Props {
+            title: "Hello".to_string(),
+            count: -3,
+            ratio: 0.5,
+            visible: true,
+            tags: vec!["a".to_string(), "b".to_string()],
+            limits: HashMap::from([("max".to_string(), 10)]),
+            subtitle: None,
+            sizes: vec![
+                Size::Small,
+                Size::Custom(4),
+                Size::Scaled {
+                    width: 2,
+                    height: 3,
+                },
+                Size::Pair(1, 2),
+            ],
+        };
+
+        let value = to_prop_value(&props).unwrap();
+        let PropValue::Object(fields) = &value else {
+            panic!("expected an object, got {:?}", value);
+        };
+        assert_eq!(fields["count"], PropValue::Number(-3.0));
+        assert_eq!(fields["subtitle"], PropValue::Null);
+        assert_eq!(
+            fields["sizes"],
+            PropValue::Array(vec![
+                "Small".into(),
+                PropValue::from(HashMap::from([("Custom".to_string(), 4)])),
+                to_prop_value(&Size::Scaled {
+                    width: 2,
+                    height: 3
+                })
+                .unwrap(),
+                PropValue::from(HashMap::from([("Pair".to_string(), vec![1, 2])])),
+            ])
+        );
+        assert_eq!(from_prop_value::<Props>(value).unwrap(), props);
+
+        // Serializes like the JSON it mirrors
+        let json = serde_json::to_value(to_prop_value(&props).unwrap()).unwrap();
+        assert_eq!(json, serde_json::to_value(&props).unwrap());
+        assert_eq!(
+            serde_json::from_value::<PropValue>(json).unwrap(),
+            to_prop_value(&props).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_prop_value_conversions() {
+        assert_eq!(PropValue::from(3u8), PropValue::Number(3.0));
+        assert_eq!(
+            PropValue::from(Some("x")),
+            PropValue::String("x".to_string())
+        );
+        assert_eq!(PropValue::from(None::<bool>), PropValue::Null);
+        assert_eq!(
+            PropValue::from(vec![true, false]),
+            PropValue::Array(vec![true.into(), false.into()])
+        );
+
+        assert_eq!(i32::try_from(PropValue::Number(-7.0)).unwrap(), -7);
+        assert!(u8::try_from(PropValue::Number(256.0)).is_err());
+        assert!(u32::try_from(PropValue::Number(-1.0)).is_err());
+        assert!(i64::try_from(PropValue::Number(1.5)).is_err());
+        assert_eq!(f64::try_from(PropValue::Number(1.5)).unwrap(), 1.5);
+        assert!(String::try_from(PropValue::Boolean(true)).is_err());
+        assert_eq!(
+            Vec::<u16>::try_from(PropValue::from(vec![1, 2])).unwrap(),
+            [1, 2]
+        );
+        assert_eq!(
+            HashMap::<String, bool>::try_from(PropValue::from(HashMap::from([(
+                "on".to_string(),
+                true
+            )])))
+            .unwrap(),
+            HashMap::from([("on".to_string(), true)])
+        );
+
+        assert!(from_prop_value::<u8>(PropValue::Number(-1.0)).is_err());
+        assert!(from_prop_value::<Props>(PropValue::Null).is_err());
+        assert!(from_prop_value::<Size>("Large".into()).is_err());
+    }
+
+    #[test]
+    fn test_integers_an_f64_cannot_hold_are_rejected() {
+        let max = 1i64 << 53;
+        assert_eq!(to_prop_value(&max).unwrap(), PropValue::Number(max as f64));
+        assert_eq!(
+            from_prop_value::<i64>(to_prop_value(&-max).unwrap()).unwrap(),
+            -max
+        );
+        assert_eq!(
+            from_prop_value::<u64>(to_prop_value(&(max as u64)).unwrap()).unwrap(),
+            max as u64
+        );
+
+        assert!(to_prop_value(&(max + 1)).is_err());
+        assert!(to_prop_value(&(-max - 1)).is_err());
+        assert!(to_prop_value(&i64::MIN).is_err());
+        assert!(to_prop_value(&u64::MAX).is_err());
+        assert!(to_prop_value(&vec![1, u64::MAX]).is_err());
+
+        assert_eq!(
+            PropValue::try_from(max).unwrap(),
+            PropValue::Number(max as f64)
+        );
+        assert_eq!(
+            PropValue::try_from(-max).unwrap(),
+            PropValue::Number(-max as f64)
+        );
+        assert!(PropValue::try_from(max + 1).is_err());
+        assert!(PropValue::try_from(i64::MIN).is_err());
+        assert!(PropValue::try_from(u64::MAX).is_err());
+        assert!(PropValue::try_from(usize::MAX).is_err());
+        assert_eq!(PropValue::try_from(3usize).unwrap(), PropValue::Number(3.0));
+    }
+
+    #[test]
+    fn test_numbers_past_the_integer_range_are_rejected() {
+        let two_to_63 = 2f64.powi(63);
+        let two_to_64 = 2f64.powi(64);
+        assert!(i64::try_from(PropValue::Number(two_to_63)).is_err());
+        assert!(isize::try_from(PropValue::Number(two_to_63)).is_err());
+        assert!(u64::try_from(PropValue::Number(two_to_64)).is_err());
+        assert!(usize::try_from(PropValue::Number(two_to_64)).is_err());
+        assert!(i32::try_from(PropValue::Number(2f64.powi(31))).is_err());
+
+        assert_eq!(
+            i64::try_from(PropValue::Number(-two_to_63)).unwrap(),
+            i64::MIN
+        );
+        assert_eq!(
+            u64::try_from(PropValue::Number(2f64.powi(63))).unwrap(),
+            1 << 63
+        );
+        assert_eq!(
+            i32::try_from(PropValue::Number(i32::MAX.into())).unwrap(),
+            i32::MAX
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_numbers_and_strings_round_trip(n in any::<i32>(), s in ".*") {
+            let value = to_prop_value(&(n, s.clone())).unwrap();
+            prop_assert_eq!(from_prop_value::<(i32, String)>(value).unwrap(), (n, s));
+        }
+    }
+}
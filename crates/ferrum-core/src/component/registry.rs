@@ -0,0 +1,231 @@
+//! Creating components by name
+//!
+//! A view can name a child instead of mounting it with
+//! [`child`](super::child): a [`ComponentView::Component`](super::ComponentView)
+//! node, or an element whose tag is a registered name. The runtime looks the
+//! name up in its [`ComponentRegistry`] and builds the props from the node's.
+
+use super::runtime::{create, Pending};
+use super::{from_prop_value, AnyComponent, Component, PropValue};
+use crate::{FerrumError, Result};
+use serde::de::DeserializeOwned;
+use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
+
+/// Separates a namespace from the names registered in it
+pub const NAMESPACE_SEPARATOR: &str = "::";
+
+/// Turns a node's props into a component's `Props`, boxed
+type PropsFactory = Box<dyn Fn(PropValue) -> Result<Box<dyn Any>>>;
+
+struct Registered {
+    type_id: TypeId,
+    props: PropsFactory,
+    create: fn(Box<dyn Any>) -> Box<dyn AnyComponent>,
+}
+
+/// Components that can be created by name
+#[derive(Default)]
+pub struct ComponentRegistry {
+    components: BTreeMap<String, Registered>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create `C` for nodes named `name`, deserializing its props from theirs
+    ///
+    /// Fails if the name is taken.
+    pub fn register<C>(&mut self, name: impl Into<String>) -> Result<()>
+    where
+        C: Component,
+        C::Props: DeserializeOwned + 'static,
+        C::Msg: 'static,
+    {
+        self.register_with::<C>(name, from_prop_value)
+    }
+
+    /// Like [`register`](Self::register), with props made by `props`
+    pub fn register_with<C>(
+        &mut self,
+        name: impl Into<String>,
+        props: impl Fn(PropValue) -> Result<C::Props> + 'static,
+    ) -> Result<()>
+    where
+        C: Component,
+        C::Props: 'static,
+        C::Msg: 'static,
+    {
+        let name = name.into();
+        if name.is_empty() || name.split(NAMESPACE_SEPARATOR).any(str::is_empty) {
+            return Err(FerrumError::Component(format!(
+                "invalid component name {:?}",
+                name
+            )));
+        }
+        if self.components.contains_key(&name) {
+            return Err(FerrumError::Component(format!(
+                "component '{}' is already registered",
+                name
+            )));
+        }
+
+        self.components.insert(
+            name.clone(),
+            Registered {
+                type_id: TypeId::of::<C>(),
+                props: Box::new(move |value| Ok(Box::new(props(value)?))),
+                create: create::<C>,
+            },
+        );
+        log::debug!("Registered component: {}", name);
+        Ok(())
+    }
+
+    /// Register components as `<namespace>::<name>`
+    pub fn namespace(&mut self, namespace: impl Into<String>) -> Namespace<'_> {
+        Namespace {
+            registry: self,
+            namespace: namespace.into(),
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.components.contains_key(name)
+    }
+
+    /// Every registered name, sorted
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.components.keys().map(String::as_str)
+    }
+
+    /// Names registered under `namespace`, without the prefix
+    pub fn names_in<'a>(&'a self, namespace: &str) -> impl Iterator<Item = &'a str> {
+        let prefix = format!("{}{}", namespace, NAMESPACE_SEPARATOR);
+        self.components
+            .keys()
+            .filter_map(move |name| name.strip_prefix(prefix.as_str()))
+    }
+
+    /// Create the component registered as `name`
+    pub fn create(&self, name: &str, props: PropValue) -> Result<Box<dyn AnyComponent>> {
+        let pending = self.pending(name, props)?;
+        Ok((pending.create)(pending.props))
+    }
+
+    pub(super) fn pending(&self, name: &str, props: PropValue) -> Result<Pending> {
+        let registered = self.components.get(name).ok_or_else(|| {
+            FerrumError::Component(format!("no component registered as '{}'", name))
+        })?;
+        Ok(Pending {
+            type_id: registered.type_id,
+            props: (registered.props)(props)?,
+            create: registered.create,
+        })
+    }
+}
+
+/// Registers components under a namespace; see [`ComponentRegistry::namespace`]
+pub struct Namespace<'a> {
+    registry: &'a mut ComponentRegistry,
+    namespace: String,
+}
+
+impl Namespace<'_> {
+    pub fn register<C>(&mut self, name: &str) -> Result<()>
+    where
+        C: Component,
+        C::Props: DeserializeOwned + 'static,
+        C::Msg: 'static,
+    {
+        self.register_with::<C>(name, from_prop_value)
+    }
+
+    pub fn register_with<C>(
+        &mut self,
+        name: &str,
+        props: impl Fn(PropValue) -> Result<C::Props> + 'static,
+    ) -> Result<()>
+    where
+        C: Component,
+        C::Props: 'static,
+        C::Msg: 'static,
+    {
+        let name = format!("{}{}{}", self.namespace, NAMESPACE_SEPARATOR, name);
+        self.registry.register_with::<C>(name, props)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::{ComponentView, Element};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    struct Label(String);
+
+    #[derive(Deserialize)]
+    struct LabelProps {
+        text: String,
+    }
+
+    impl Component for Label {
+        type Props = LabelProps;
+        type Msg = ();
+
+        fn create(props: LabelProps) -> Self {
+            Self(props.text)
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            false
+        }
+
+        fn view(&self) -> ComponentView {
+            Element::new("span")
+                .child(ComponentView::text(&self.0))
+                .into()
+        }
+    }
+
+    #[test]
+    fn test_registry_creates_by_name() {
+        let mut registry = ComponentRegistry::new();
+        registry.register::<Label>("Label").unwrap();
+        registry.namespace("ui").register::<Label>("Badge").unwrap();
+        registry
+            .namespace("ui")
+            .register_with::<Label>("Tag", |_| {
+                Ok(LabelProps {
+                    text: "tag".to_string(),
+                })
+            })
+            .unwrap();
+
+        assert!(registry.register::<Label>("Label").is_err());
+        assert!(registry.namespace("ui").register::<Label>("Tag").is_err());
+        assert!(registry.register::<Label>("ui::").is_err());
+
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["Label", "ui::Badge", "ui::Tag"]
+        );
+        assert_eq!(
+            registry.names_in("ui").collect::<Vec<_>>(),
+            ["Badge", "Tag"]
+        );
+        assert!(registry.contains("ui::Badge") && !registry.contains("Badge"));
+
+        let props = PropValue::Object(HashMap::from([("text".to_string(), "hi".into())]));
+        let label = registry.create("ui::Badge", props).unwrap();
+        assert_eq!(label.as_any().downcast_ref::<Label>().unwrap().0, "hi");
+        let tag = registry.create("ui::Tag", PropValue::Null).unwrap();
+        assert_eq!(tag.as_any().downcast_ref::<Label>().unwrap().0, "tag");
+
+        assert!(registry.create("Label", PropValue::Null).is_err());
+        assert!(registry.create("Missing", PropValue::Null).is_err());
+    }
+}
@@ -0,0 +1,1155 @@
+//! Mounting components and re-rendering them as messages arrive
+//!
+//! A [`Runtime`] owns the root component and every component mounted below
+//! it with [`child`]. A child keeps its instance, and so its state, for as
+//! long as its parent renders it under the same key. Messages go to a single
+//! instance; when its `update` returns true only that instance's `view` runs
+//! again, and [`Runtime::view`] holds the new tree with every child filled in.
+//! Children a view names rather than mounts come from the runtime's
+//! [`ComponentRegistry`].
+
+use super::context::Contexts;
+use super::lifecycle::Hooks;
+use super::{
+    Component, ComponentNode, ComponentRegistry, ComponentView, Event, EventHandler, PropValue,
+};
+use crate::vdom::KEY_PROP;
+use crate::{FerrumError, Result};
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+static NEXT_INSTANCE: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    /// The instances whose `view` is running on this thread, innermost last
+    static RENDERING: RefCell<Vec<Frame>> = const { RefCell::new(Vec::new()) };
+}
+
+#[cfg(feature = "client")]
+thread_local! {
+    /// Wake functions of the runtimes driven on this thread, by mailbox
+    static WAKES: RefCell<HashMap<usize, Rc<dyn Fn()>>> = RefCell::new(HashMap::new());
+}
+
+/// A component behind a type-erased interface
+pub trait AnyComponent {
+    /// Handle a message; fails if it isn't the component's `Msg`
+    fn update_any(&mut self, msg: Box<dyn Any>) -> Result<bool>;
+    /// Take new props; fails if they aren't the component's `Props`
+    fn changed_any(&mut self, props: Box<dyn Any>) -> Result<bool>;
+    fn view(&self) -> ComponentView;
+    /// Handle an error from below; see [`Component::catch`]
+    fn catch(&mut self, error: FerrumError) -> Result<()>;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<C> AnyComponent for C
+where
+    C: Component,
+    C::Props: 'static,
+    C::Msg: 'static,
+{
+    fn update_any(&mut self, msg: Box<dyn Any>) -> Result<bool> {
+        let msg = msg
+            .downcast::<C::Msg>()
+            .map_err(|_| mismatch::<C>("message"))?;
+        Ok(self.update(*msg))
+    }
+
+    fn changed_any(&mut self, props: Box<dyn Any>) -> Result<bool> {
+        let props = props
+            .downcast::<C::Props>()
+            .map_err(|_| mismatch::<C>("props"))?;
+        Ok(self.changed(*props))
+    }
+
+    fn view(&self) -> ComponentView {
+        Component::view(self)
+    }
+
+    fn catch(&mut self, error: FerrumError) -> Result<()> {
+        Component::catch(self, error)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn mismatch<C>(what: &str) -> FerrumError {
+    FerrumError::Component(format!(
+        "wrong {} type for {}",
+        what,
+        std::any::type_name::<C>()
+    ))
+}
+
+/// Mount `C` as a child of the component being rendered
+///
+/// Returns the placeholder the child's view replaces. The key must be
+/// unique among the children of one `view`.
+pub fn child<C>(key: impl Into<String>, props: C::Props) -> ComponentView
+where
+    C: Component,
+    C::Props: 'static,
+    C::Msg: 'static,
+{
+    let key = key.into();
+    let pending = Pending {
+        type_id: TypeId::of::<C>(),
+        props: Box::new(props),
+        create: create::<C>,
+    };
+
+    let mounted = RENDERING.with(|frames| match frames.borrow_mut().last_mut() {
+        Some(frame) => {
+            frame.children.push((key.clone(), pending));
+            true
+        }
+        None => false,
+    });
+    if !mounted {
+        log::warn!("child({}) called outside a component's view", key);
+    }
+
+    ComponentView::Component(ComponentNode {
+        name: component_name::<C>(),
+        key,
+        props: HashMap::new(),
+    })
+}
+
+/// Create a `C` from its boxed props
+pub(super) fn create<C>(props: Box<dyn Any>) -> Box<dyn AnyComponent>
+where
+    C: Component,
+    C::Props: 'static,
+    C::Msg: 'static,
+{
+    let props = props
+        .downcast::<C::Props>()
+        .expect("props are stored with their component");
+    Box::new(C::create(*props))
+}
+
+/// `C`'s type name without its path or generics
+fn component_name<C>() -> String {
+    let name = std::any::type_name::<C>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name).to_string()
+}
+
+/// A handler that sends the `C` being rendered whatever `f` makes of the
+/// event
+pub fn handler<C>(f: impl Fn(&Event) -> C::Msg + 'static) -> EventHandler
+where
+    C: Component,
+    C::Msg: 'static,
+{
+    match scope::<C>() {
+        Some(scope) => scope.handler(f),
+        None => {
+            log::warn!(
+                "handler::<{}> called outside its view",
+                component_name::<C>()
+            );
+            EventHandler::new(|_| {})
+        }
+    }
+}
+
+/// A handle for sending messages to the `C` whose `view` is running
+pub fn scope<C>() -> Option<Scope<C::Msg>>
+where
+    C: Component,
+{
+    RENDERING.with(|frames| {
+        let frames = frames.borrow();
+        let frame = frames
+            .last()
+            .filter(|frame| frame.type_id == TypeId::of::<C>())?;
+        Some(Scope {
+            mailbox: frame.mailbox.clone(),
+            path: frame.path.clone(),
+            instance: frame.instance,
+            _msg: PhantomData,
+        })
+    })
+}
+
+/// Sends messages to one mounted component
+///
+/// Messages to an instance that has since been unmounted are dropped.
+pub struct Scope<M> {
+    mailbox: Rc<Mailbox>,
+    path: Rc<[String]>,
+    instance: usize,
+    _msg: PhantomData<fn(M)>,
+}
+
+impl<M> Clone for Scope<M> {
+    fn clone(&self) -> Self {
+        Self {
+            mailbox: self.mailbox.clone(),
+            path: self.path.clone(),
+            instance: self.instance,
+            _msg: PhantomData,
+        }
+    }
+}
+
+impl<M: 'static> Scope<M> {
+    pub fn send(&self, msg: M) {
+        self.mailbox.queue.borrow_mut().push_back(Envelope {
+            path: self.path.clone(),
+            instance: self.instance,
+            msg: Box::new(msg),
+        });
+
+        let wake = self.mailbox.wake.borrow().clone();
+        if let Some(wake) = wake {
+            wake();
+        }
+    }
+
+    /// A function that sends whatever `f` makes of its argument
+    pub fn callback<E>(&self, f: impl Fn(E) -> M) -> impl Fn(E) {
+        let scope = self.clone();
+        move |event| scope.send(f(event))
+    }
+
+    /// An event handler that sends whatever `f` makes of the event
+    pub fn handler(&self, f: impl Fn(&Event) -> M + 'static) -> EventHandler {
+        let scope = self.clone();
+        EventHandler::new(move |event| scope.send(f(event)))
+    }
+
+    /// A handle for sending the same component messages from any thread
+    pub fn remote(&self) -> RemoteScope<M> {
+        RemoteScope {
+            inbox: self.mailbox.remote.clone(),
+            mailbox: self.mailbox.id,
+            path: Arc::from(&*self.path),
+            instance: self.instance,
+            _msg: PhantomData,
+        }
+    }
+}
+
+/// Sends messages to one mounted component from any thread
+///
+/// They are delivered the next time its runtime processes messages. On the
+/// client, sending from the runtime's own thread also wakes its driver.
+pub struct RemoteScope<M> {
+    inbox: Arc<Mutex<VecDeque<RemoteEnvelope>>>,
+    mailbox: usize,
+    path: Arc<[String]>,
+    instance: usize,
+    _msg: PhantomData<fn(M)>,
+}
+
+impl<M> Clone for RemoteScope<M> {
+    fn clone(&self) -> Self {
+        Self {
+            inbox: self.inbox.clone(),
+            mailbox: self.mailbox,
+            path: self.path.clone(),
+            instance: self.instance,
+            _msg: PhantomData,
+        }
+    }
+}
+
+impl<M: Send + 'static> RemoteScope<M> {
+    pub fn send(&self, msg: M) {
+        self.inbox
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push_back(RemoteEnvelope {
+                path: self.path.clone(),
+                instance: self.instance,
+                msg: Box::new(msg),
+            });
+
+        #[cfg(feature = "client")]
+        {
+            let wake = WAKES
+                .try_with(|wakes| wakes.borrow().get(&self.mailbox).cloned())
+                .ok()
+                .flatten();
+            if let Some(wake) = wake {
+                wake();
+            }
+        }
+    }
+}
+
+/// Mount or update hooks from a render, children's before their parent's
+type Effects = Vec<Box<dyn FnOnce()>>;
+
+/// Messages waiting for [`Runtime::process`]
+struct Mailbox {
+    id: usize,
+    queue: RefCell<VecDeque<Envelope>>,
+    /// Messages sent through a [`RemoteScope`]
+    remote: Arc<Mutex<VecDeque<RemoteEnvelope>>>,
+    /// Mount and update hooks waiting for the render to finish
+    effects: RefCell<Effects>,
+    /// Called after each send, so a driver can process it
+    wake: RefCell<Option<Rc<dyn Fn()>>>,
+}
+
+impl Mailbox {
+    fn new() -> Rc<Self> {
+        Rc::new(Self {
+            id: NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed),
+            queue: RefCell::default(),
+            remote: Arc::default(),
+            effects: RefCell::default(),
+            wake: RefCell::default(),
+        })
+    }
+}
+
+#[cfg(feature = "client")]
+impl Drop for Mailbox {
+    fn drop(&mut self) {
+        let _ = WAKES.try_with(|wakes| wakes.borrow_mut().remove(&self.id));
+    }
+}
+
+struct Envelope {
+    path: Rc<[String]>,
+    instance: usize,
+    msg: Box<dyn Any>,
+}
+
+struct RemoteEnvelope {
+    path: Arc<[String]>,
+    instance: usize,
+    msg: Box<dyn Any + Send>,
+}
+
+/// A child asked for by the `view` being rendered
+pub(super) struct Pending {
+    pub(super) type_id: TypeId,
+    pub(super) props: Box<dyn Any>,
+    pub(super) create: fn(Box<dyn Any>) -> Box<dyn AnyComponent>,
+}
+
+struct Frame {
+    mailbox: Rc<Mailbox>,
+    path: Rc<[String]>,
+    instance: usize,
+    type_id: TypeId,
+    contexts: Rc<Contexts>,
+    children: Vec<(String, Pending)>,
+    hooks: Hooks,
+}
+
+/// Contexts of the instance being rendered, for
+/// [`provide_context`](super::provide_context) and
+/// [`use_context`](super::use_context)
+pub(super) fn current_contexts() -> Option<Rc<Contexts>> {
+    RENDERING.with(|frames| Some(frames.borrow().last()?.contexts.clone()))
+}
+
+/// Run `f` on the hooks of the instance being rendered
+pub(super) fn current_hooks<R>(f: impl FnOnce(&mut Hooks) -> R) -> Option<R> {
+    RENDERING.with(|frames| Some(f(&mut frames.borrow_mut().last_mut()?.hooks)))
+}
+
+/// Run `f`, turning a panic into an error from the component at `path`
+fn catch_panic<R>(path: &[String], f: impl FnOnce() -> R) -> Result<R> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        FerrumError::Component(format!(
+            "component at /{} panicked: {}",
+            path.join("/"),
+            panic_message(&*payload)
+        ))
+    })
+}
+
+/// The message a panic was started with, if it was a string
+pub(super) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
+}
+
+/// What rendering an instance needs besides the instance
+#[derive(Clone, Copy)]
+struct Pass<'a> {
+    mailbox: &'a Rc<Mailbox>,
+    registry: &'a ComponentRegistry,
+    /// Re-render every child that is kept, not just those with new props
+    deep: bool,
+}
+
+/// Why a render failed
+enum Failure {
+    /// The instance's own `view`, or its children's keys or props
+    Own(FerrumError),
+    /// A child's render
+    Child(FerrumError),
+}
+
+impl Failure {
+    fn into_error(self) -> FerrumError {
+        match self {
+            Failure::Own(error) | Failure::Child(error) => error,
+        }
+    }
+}
+
+/// A component instance and the children from its last render
+struct Mounted {
+    instance: usize,
+    type_id: TypeId,
+    path: Rc<[String]>,
+    component: Box<dyn AnyComponent>,
+    /// What this instance provides, over what its ancestors provide
+    contexts: Rc<Contexts>,
+    /// Whether the first render has happened
+    mounted: bool,
+    /// From [`on_cleanup`](super::on_cleanup) during the last render that
+    /// committed
+    cleanups: Vec<Box<dyn FnOnce()>>,
+    rendered: ComponentView,
+    children: HashMap<String, Mounted>,
+}
+
+impl Drop for Mounted {
+    fn drop(&mut self) {
+        self.cleanup();
+    }
+}
+
+impl Mounted {
+    fn new(
+        type_id: TypeId,
+        path: Rc<[String]>,
+        component: Box<dyn AnyComponent>,
+        parent: &Rc<Contexts>,
+    ) -> Self {
+        Self {
+            instance: NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed),
+            type_id,
+            path,
+            component,
+            contexts: Contexts::below(parent),
+            mounted: false,
+            cleanups: Vec::new(),
+            rendered: ComponentView::text(""),
+            children: HashMap::new(),
+        }
+    }
+
+    /// Run `view` and mount, update or drop children to match it
+    ///
+    /// The hooks of what rendered are queued only if the whole render
+    /// succeeds.
+    fn render(&mut self, pass: Pass) -> Result<()> {
+        let effects = self.render_tree(pass)?;
+        pass.mailbox.effects.borrow_mut().extend(effects);
+        Ok(())
+    }
+
+    /// Like [`render`](Self::render), returning the hooks instead
+    ///
+    /// An error from a child goes to this instance's `catch`; if that
+    /// handles it, the instance renders again and the hooks from the failed
+    /// attempt are dropped.
+    fn render_tree(&mut self, pass: Pass) -> Result<Effects> {
+        match self.render_once(pass) {
+            Err(Failure::Child(error)) => {
+                self.component.catch(error)?;
+                self.render_once(pass).map_err(Failure::into_error)
+            }
+            result => result.map_err(Failure::into_error),
+        }
+    }
+
+    fn render_once(&mut self, pass: Pass) -> std::result::Result<Effects, Failure> {
+        self.cleanup();
+        self.contexts.clear();
+
+        RENDERING.with(|frames| {
+            frames.borrow_mut().push(Frame {
+                mailbox: pass.mailbox.clone(),
+                path: self.path.clone(),
+                instance: self.instance,
+                type_id: self.type_id,
+                contexts: self.contexts.clone(),
+                children: Vec::new(),
+                hooks: Hooks::default(),
+            })
+        });
+        let view = catch_panic(&self.path, || self.component.view());
+        let frame = RENDERING
+            .with(|frames| frames.borrow_mut().pop())
+            .expect("the frame pushed for this render");
+        let mut view = view.map_err(Failure::Own)?;
+
+        let Frame {
+            children: mut pending,
+            hooks,
+            ..
+        } = frame;
+        let effects = if self.mounted {
+            hooks.update
+        } else {
+            hooks.mount
+        };
+
+        // Once what this instance provides changes, everything below it
+        // reads its context again
+        let pass = Pass {
+            deep: pass.deep || self.contexts.changed(),
+            ..pass
+        };
+
+        let mut named = Named {
+            registry: pass.registry,
+            claimed: pending.iter().map(|(key, _)| key.clone()).collect(),
+            counts: HashMap::new(),
+            children: Vec::new(),
+        };
+        named.resolve(&mut view).map_err(Failure::Own)?;
+        pending.append(&mut named.children);
+
+        // Children are built aside, so a failure leaves the last ones mounted
+        let mut keys = HashSet::new();
+        let mut created = Created::default();
+        let mut queued = Effects::new();
+        for (key, pending) in pending {
+            if !keys.insert(key.clone()) {
+                return Err(Failure::Own(FerrumError::Component(format!(
+                    "two children keyed {:?} under {}",
+                    key,
+                    self.path.join("/")
+                ))));
+            }
+
+            match self.children.get_mut(&key) {
+                Some(child) if child.type_id == pending.type_id => {
+                    let changed = child
+                        .component
+                        .changed_any(pending.props)
+                        .map_err(Failure::Own)?;
+                    if changed || pass.deep {
+                        queued.extend(child.render_tree(pass).map_err(Failure::Child)?);
+                    }
+                }
+                _ => {
+                    let path: Rc<[String]> = self.path.iter().chain([&key]).cloned().collect();
+                    let mut child = Mounted::new(
+                        pending.type_id,
+                        path,
+                        (pending.create)(pending.props),
+                        &self.contexts,
+                    );
+                    queued.extend(child.render_tree(pass).map_err(Failure::Child)?);
+                    created.0.insert(key, child);
+                }
+            }
+        }
+
+        // The children that weren't kept unmount as `previous` drops
+        let mut previous = std::mem::take(&mut self.children);
+        self.children = keys
+            .into_iter()
+            .map(|key| {
+                let child = created
+                    .0
+                    .remove(&key)
+                    .or_else(|| previous.remove(&key))
+                    .expect("each key was kept or created");
+                (key, child)
+            })
+            .collect();
+
+        self.contexts.commit();
+        self.cleanups = hooks.cleanup;
+        self.rendered = view;
+        self.mounted = true;
+        queued.extend(effects);
+        Ok(queued)
+    }
+
+    /// Run the cleanups from the last render
+    fn cleanup(&mut self) {
+        for cleanup in self.cleanups.drain(..) {
+            cleanup();
+        }
+    }
+
+    /// Drop the cleanups here and below without running them, for a tree
+    /// that never mounted
+    fn forget(&mut self) {
+        self.cleanups.clear();
+        for child in self.children.values_mut() {
+            child.forget();
+        }
+    }
+
+    fn find_mut(&mut self, path: &[String]) -> Option<&mut Mounted> {
+        match path.split_first() {
+            Some((key, rest)) => self.children.get_mut(key)?.find_mut(rest),
+            None => Some(self),
+        }
+    }
+
+    /// The last render with each child's placeholder replaced by its view
+    fn expand(&self) -> ComponentView {
+        let mut view = self.rendered.clone();
+        self.fill(&mut view);
+        view
+    }
+
+    fn fill(&self, view: &mut ComponentView) {
+        let children = match view {
+            ComponentView::Component(node) => {
+                if let Some(child) = self.children.get(&node.key) {
+                    *view = child.expand();
+                }
+                return;
+            }
+            ComponentView::Text(_) => return,
+            ComponentView::Element(element) => &mut element.children,
+            ComponentView::Fragment(children) => children,
+        };
+        for node in children {
+            self.fill(node);
+        }
+    }
+}
+
+/// Children created by a render, by key
+///
+/// Those still here when it drops were never mounted, because the render
+/// failed, so they unmount without running their cleanups.
+#[derive(Default)]
+struct Created(HashMap<String, Mounted>);
+
+impl Drop for Created {
+    fn drop(&mut self) {
+        for child in self.0.values_mut() {
+            child.forget();
+        }
+    }
+}
+
+/// Finds the children a view names instead of mounting with [`child`]
+struct Named<'a> {
+    registry: &'a ComponentRegistry,
+    /// Keys of the children mounted with [`child`]
+    claimed: HashSet<String>,
+    /// Unkeyed elements seen so far, by tag
+    counts: HashMap<String, usize>,
+    children: Vec<(String, Pending)>,
+}
+
+impl Named<'_> {
+    /// Create registered components for the unclaimed component nodes in
+    /// `view`, and for elements with a registered tag
+    ///
+    /// Such an element becomes a component node with its props, keyed by
+    /// its `key` prop or its position among elements with that tag; its
+    /// children are dropped.
+    fn resolve(&mut self, view: &mut ComponentView) -> Result<()> {
+        let children = match view {
+            ComponentView::Element(element) if self.registry.contains(&element.tag) => {
+                let mut props = std::mem::take(&mut element.props);
+                let key = match props.remove(KEY_PROP) {
+                    Some(PropValue::String(key)) => key,
+                    _ => {
+                        let count = self.counts.entry(element.tag.clone()).or_default();
+                        *count += 1;
+                        format!("{}#{}", element.tag, count)
+                    }
+                };
+                let node = ComponentNode {
+                    name: std::mem::take(&mut element.tag),
+                    key,
+                    props,
+                };
+                *view = ComponentView::Component(node);
+                return self.resolve(view);
+            }
+            ComponentView::Component(node) => {
+                if !self.claimed.contains(&node.key) {
+                    let props = PropValue::Object(node.props.clone());
+                    let pending = self.registry.pending(&node.name, props)?;
+                    self.children.push((node.key.clone(), pending));
+                }
+                return Ok(());
+            }
+            ComponentView::Text(_) => return Ok(()),
+            ComponentView::Element(element) => &mut element.children,
+            ComponentView::Fragment(children) => children,
+        };
+        for child in children {
+            self.resolve(child)?;
+        }
+        Ok(())
+    }
+}
+
+/// Drives a tree of components from a root `C`
+pub struct Runtime<C> {
+    root: Mounted,
+    mailbox: Rc<Mailbox>,
+    registry: ComponentRegistry,
+    /// Provided to the whole tree
+    contexts: Rc<Contexts>,
+    view: ComponentView,
+    _component: PhantomData<C>,
+}
+
+#[cfg(feature = "client")]
+impl<C> Runtime<C> {
+    /// Have each send call `wake`, so a driver can process the message
+    pub(super) fn set_wake(&self, wake: Rc<dyn Fn()>) {
+        WAKES.with(|wakes| wakes.borrow_mut().insert(self.mailbox.id, wake.clone()));
+        *self.mailbox.wake.borrow_mut() = Some(wake);
+    }
+}
+
+impl<C> Runtime<C>
+where
+    C: Component,
+    C::Props: 'static,
+    C::Msg: 'static,
+{
+    /// Create the root and render the whole tree once
+    pub fn mount(props: C::Props) -> Result<Self> {
+        Self::mount_with(props, ComponentRegistry::new())
+    }
+
+    /// Like [`mount`](Self::mount), creating the components views name
+    /// from `registry`
+    pub fn mount_with(props: C::Props, registry: ComponentRegistry) -> Result<Self> {
+        let mailbox = Mailbox::new();
+        let contexts = Rc::new(Contexts::default());
+        let mut root = Mounted::new(
+            TypeId::of::<C>(),
+            Rc::from([]),
+            Box::new(C::create(props)),
+            &contexts,
+        );
+        root.render(Pass {
+            mailbox: &mailbox,
+            registry: &registry,
+            deep: false,
+        })?;
+
+        let mut runtime = Self {
+            view: root.expand(),
+            root,
+            mailbox,
+            registry,
+            contexts,
+            _component: PhantomData,
+        };
+        // Runs the mount hooks
+        runtime.process()?;
+        Ok(runtime)
+    }
+
+    /// Provide `value` to every component, unless one provides its own
+    ///
+    /// The whole tree renders again to pick it up.
+    pub fn provide_context<T: PartialEq + 'static>(&mut self, value: T) -> Result<()> {
+        self.contexts.insert(value);
+        self.root.render(Pass {
+            mailbox: &self.mailbox,
+            registry: &self.registry,
+            deep: true,
+        })?;
+        self.view = self.root.expand();
+        self.process()?;
+        Ok(())
+    }
+
+    /// The current tree
+    pub fn view(&self) -> &ComponentView {
+        &self.view
+    }
+
+    pub fn component(&self) -> &C {
+        self.root
+            .component
+            .as_any()
+            .downcast_ref()
+            .expect("the root is a C")
+    }
+
+    pub fn scope(&self) -> Scope<C::Msg> {
+        Scope {
+            mailbox: self.mailbox.clone(),
+            path: self.root.path.clone(),
+            instance: self.root.instance,
+            _msg: PhantomData,
+        }
+    }
+
+    /// Send `msg` to the root and process it; returns whether anything
+    /// re-rendered
+    pub fn dispatch(&mut self, msg: C::Msg) -> Result<bool> {
+        self.mailbox.queue.borrow_mut().push_back(Envelope {
+            path: self.root.path.clone(),
+            instance: self.root.instance,
+            msg: Box::new(msg),
+        });
+        self.process()
+    }
+
+    /// Fire `event` at the node at `path` and process what its handlers sent
+    ///
+    /// `path` holds child indices from the root as in [`crate::vdom`]. The
+    /// event bubbles: handlers for it on the node and then on each of its
+    /// ancestors are called.
+    pub fn dispatch_event(&mut self, path: &[usize], event: &Event) -> Result<bool> {
+        for handler in crate::vdom::handlers_at(&self.view, path, &event.name) {
+            handler.call(event);
+        }
+        self.process()
+    }
+
+    /// Deliver queued messages, including those sent while handling them,
+    /// then run the hooks of what rendered
+    pub fn process(&mut self) -> Result<bool> {
+        let mut rendered = false;
+        loop {
+            self.receive_remote();
+            let Some(envelope) = self.mailbox.queue.borrow_mut().pop_front() else {
+                let effects = std::mem::take(&mut *self.mailbox.effects.borrow_mut());
+                if effects.is_empty() {
+                    break;
+                }
+                for effect in effects {
+                    effect();
+                }
+                continue;
+            };
+            rendered |= self.deliver(envelope)?;
+        }
+
+        if rendered {
+            self.view = self.root.expand();
+        }
+        Ok(rendered)
+    }
+
+    /// Queue the messages sent through a [`RemoteScope`]
+    fn receive_remote(&self) {
+        let mut remote = self
+            .mailbox
+            .remote
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.mailbox
+            .queue
+            .borrow_mut()
+            .extend(remote.drain(..).map(|envelope| Envelope {
+                path: Rc::from(&*envelope.path),
+                instance: envelope.instance,
+                msg: envelope.msg,
+            }));
+    }
+
+    /// Hand `envelope` to its instance; returns whether anything re-rendered
+    ///
+    /// If the instance fails, the nearest ancestor that catches the error
+    /// renders again.
+    fn deliver(&mut self, envelope: Envelope) -> Result<bool> {
+        let pass = Pass {
+            mailbox: &self.mailbox,
+            registry: &self.registry,
+            deep: false,
+        };
+        let target = self
+            .root
+            .find_mut(&envelope.path)
+            .filter(|target| target.instance == envelope.instance);
+        let Some(target) = target else {
+            log::debug!("Dropped a message for an unmounted component");
+            return Ok(false);
+        };
+
+        let path = target.path.clone();
+        let result = catch_panic(&path, || target.component.update_any(envelope.msg))
+            .and_then(|changed| changed)
+            .and_then(|changed| {
+                if changed {
+                    target.render(pass)?;
+                }
+                Ok(changed)
+            });
+
+        match result {
+            Ok(changed) => Ok(changed),
+            Err(error) => {
+                self.recover(&path, error)?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Give `error` from the instance at `path` to its nearest ancestor
+    /// that catches it, and render that ancestor again
+    fn recover(&mut self, path: &[String], mut error: FerrumError) -> Result<()> {
+        let pass = Pass {
+            mailbox: &self.mailbox,
+            registry: &self.registry,
+            deep: false,
+        };
+        for depth in (0..path.len()).rev() {
+            let Some(ancestor) = self.root.find_mut(&path[..depth]) else {
+                continue;
+            };
+            match ancestor.component.catch(error) {
+                Ok(()) => return ancestor.render(pass),
+                Err(uncaught) => error = uncaught,
+            }
+        }
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Element;
+
+    fn text(view: &ComponentView) -> String {
+        match view {
+            ComponentView::Text(text) => text.clone(),
+            ComponentView::Element(element) => element.children.iter().map(text).collect(),
+            ComponentView::Fragment(children) => children.iter().map(text).collect(),
+            ComponentView::Component(_) => String::new(),
+        }
+    }
+
+    type Slot = Rc<RefCell<Option<Scope<()>>>>;
+
+    /// Counts clicks and shares its scope with the test
+    struct Counter {
+        count: u32,
+        slot: Slot,
+    }
+
+    impl Component for Counter {
+        type Props = Slot;
+        type Msg = ();
+
+        fn create(slot: Slot) -> Self {
+            Self { count: 0, slot }
+        }
+
+        fn update(&mut self, _: ()) -> bool {
+            self.count += 1;
+            true
+        }
+
+        fn view(&self) -> ComponentView {
+            *self.slot.borrow_mut() = scope::<Self>();
+            Element::new("button")
+                .on("click", handler::<Self>(|_| ()))
+                .child(ComponentView::text(self.count.to_string()))
+                .into()
+        }
+    }
+
+    enum ListMsg {
+        Show(Vec<&'static str>),
+        Rename(&'static str),
+        Unchanged,
+    }
+
+    /// Renders a counter for each key, sharing each one's scope through `slots`
+    struct List {
+        keys: Vec<&'static str>,
+        title: &'static str,
+        slots: HashMap<&'static str, Slot>,
+    }
+
+    impl Component for List {
+        type Props = (Vec<&'static str>, HashMap<&'static str, Slot>);
+        type Msg = ListMsg;
+
+        fn create((keys, slots): Self::Props) -> Self {
+            Self {
+                keys,
+                title: "list",
+                slots,
+            }
+        }
+
+        fn update(&mut self, msg: ListMsg) -> bool {
+            match msg {
+                ListMsg::Show(keys) => self.keys = keys,
+                ListMsg::Rename(title) => self.title = title,
+                ListMsg::Unchanged => return false,
+            }
+            true
+        }
+
+        fn view(&self) -> ComponentView {
+            let mut children = vec![ComponentView::text(format!("{}:", self.title))];
+            for key in &self.keys {
+                children.push(child::<Counter>(*key, self.slots[key].clone()));
+            }
+            Element::new("ul").children(children).into()
+        }
+    }
+
+    #[test]
+    fn test_runtime_rerenders_and_keeps_keyed_children() {
+        let slots: HashMap<_, Slot> = ["a", "b", "c"]
+            .into_iter()
+            .map(|key| (key, Slot::default()))
+            .collect();
+        let counter = |key: &str| slots[key].borrow().clone().unwrap();
+
+        let mut runtime = Runtime::<List>::mount((vec!["a", "b"], slots.clone())).unwrap();
+        assert_eq!(text(runtime.view()), "list:00");
+        assert!(scope::<Counter>().is_none());
+
+        counter("b").send(());
+        counter("b").send(());
+        assert!(runtime.process().unwrap());
+        assert_eq!(text(runtime.view()), "list:02");
+        assert!(!runtime.dispatch(ListMsg::Unchanged).unwrap());
+
+        // Re-rendering the parent keeps each counter's state
+        runtime.dispatch(ListMsg::Rename("counts")).unwrap();
+        assert_eq!(text(runtime.view()), "counts:02");
+        runtime.dispatch(ListMsg::Show(vec!["b", "a"])).unwrap();
+        assert_eq!(text(runtime.view()), "counts:20");
+
+        // A dropped child is unmounted; its old scope reaches nothing
+        let stale = counter("b");
+        runtime.dispatch(ListMsg::Show(vec!["a"])).unwrap();
+        runtime.dispatch(ListMsg::Show(vec!["a", "b"])).unwrap();
+        stale.send(());
+        assert!(!runtime.process().unwrap());
+        assert_eq!(text(runtime.view()), "counts:00");
+
+        assert!(runtime.dispatch(ListMsg::Show(vec!["c", "c"])).is_err());
+    }
+
+    #[test]
+    fn test_failed_render_keeps_the_mounted_children() {
+        let slots: HashMap<_, Slot> = ["a", "b", "c"]
+            .into_iter()
+            .map(|key| (key, Slot::default()))
+            .collect();
+        let counter = |key: &str| slots[key].borrow().clone().unwrap();
+
+        let mut runtime = Runtime::<List>::mount((vec!["a", "b"], slots.clone())).unwrap();
+        counter("b").send(());
+        runtime.process().unwrap();
+
+        // c mounts before the duplicate fails the render
+        assert!(runtime
+            .dispatch(ListMsg::Show(vec!["b", "c", "c"]))
+            .is_err());
+        assert_eq!(text(runtime.view()), "list:01");
+
+        // a and b are still mounted, with their state
+        counter("b").send(());
+        counter("a").send(());
+        assert!(runtime.process().unwrap());
+        assert_eq!(text(runtime.view()), "list:12");
+    }
+
+    #[test]
+    fn test_events_bubble_to_component_handlers() {
+        let slots: HashMap<_, Slot> = ["a", "b"]
+            .into_iter()
+            .map(|key| (key, Slot::default()))
+            .collect();
+        let mut runtime = Runtime::<List>::mount((vec!["a", "b"], slots)).unwrap();
+
+        // The text inside b's button
+        assert!(runtime
+            .dispatch_event(&[0, 2, 0], &Event::new("click"))
+            .unwrap());
+        assert_eq!(text(runtime.view()), "list:01");
+
+        assert!(!runtime
+            .dispatch_event(&[0, 1], &Event::new("input"))
+            .unwrap());
+        assert!(!runtime
+            .dispatch_event(&[0, 0], &Event::new("click"))
+            .unwrap());
+    }
+
+    /// Shows whatever nodes it was last sent
+    struct Page(Vec<ComponentView>);
+
+    impl Component for Page {
+        type Props = Vec<ComponentView>;
+        type Msg = Vec<ComponentView>;
+
+        fn create(nodes: Self::Props) -> Self {
+            Self(nodes)
+        }
+
+        fn update(&mut self, nodes: Self::Msg) -> bool {
+            self.0 = nodes;
+            true
+        }
+
+        fn view(&self) -> ComponentView {
+            ComponentView::Fragment(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_runtime_creates_named_children_from_the_registry() {
+        let slot = Slot::default();
+        let mut registry = ComponentRegistry::new();
+        let counter = slot.clone();
+        registry
+            .register_with::<Counter>("Counter", move |_| Ok(counter.clone()))
+            .unwrap();
+
+        let nodes = vec![
+            Element::new("Counter")
+                .prop(KEY_PROP, PropValue::String("a".to_string()))
+                .into(),
+            ComponentView::Component(ComponentNode {
+                name: "Counter".to_string(),
+                key: "b".to_string(),
+                props: HashMap::new(),
+            }),
+        ];
+        let mut runtime = Runtime::<Page>::mount_with(nodes.clone(), registry).unwrap();
+        assert_eq!(text(runtime.view()), "00");
+
+        // The slot holds the scope of the last counter rendered
+        slot.borrow().clone().unwrap().send(());
+        assert!(runtime.process().unwrap());
+        assert_eq!(text(runtime.view()), "01");
+        runtime.dispatch(nodes).unwrap();
+        assert_eq!(text(runtime.view()), "01");
+
+        let missing = ComponentView::Component(ComponentNode {
+            name: "Missing".to_string(),
+            key: "c".to_string(),
+            props: HashMap::new(),
+        });
+        assert!(runtime.dispatch(vec![missing]).is_err());
+    }
+}
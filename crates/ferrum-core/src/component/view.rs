@@ -0,0 +1,158 @@
+//! The tree a component's `view` returns
+
+use super::PropValue;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// Represents a rendered component view
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ComponentView {
+    Element(Element),
+    Text(String),
+    /// Nodes rendered in place of the fragment, without a wrapper
+    Fragment(Vec<ComponentView>),
+    /// A child component, replaced by its own view once mounted
+    Component(ComponentNode),
+}
+
+impl ComponentView {
+    /// A text node
+    pub fn text(content: impl Into<String>) -> Self {
+        Self::Text(content.into())
+    }
+
+    pub fn as_element(&self) -> Option<&Element> {
+        match self {
+            Self::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// The nodes this view renders to, with fragments flattened away
+    pub fn nodes(&self) -> Vec<&ComponentView> {
+        let mut nodes = Vec::new();
+        self.collect_nodes(&mut nodes);
+        nodes
+    }
+
+    fn collect_nodes<'a>(&'a self, nodes: &mut Vec<&'a ComponentView>) {
+        match self {
+            Self::Fragment(children) => {
+                for child in children {
+                    child.collect_nodes(nodes);
+                }
+            }
+            node => nodes.push(node),
+        }
+    }
+}
+
+impl From<Element> for ComponentView {
+    fn from(element: Element) -> Self {
+        Self::Element(element)
+    }
+}
+
+/// An HTML element
+///
+/// Handlers take no part in comparisons: every render makes new closures,
+/// and they are looked up in the current view when an event fires.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Element {
+    pub tag: String,
+    pub props: HashMap<String, PropValue>,
+    pub children: Vec<ComponentView>,
+    #[serde(skip)]
+    pub handlers: HashMap<String, EventHandler>,
+}
+
+impl PartialEq for Element {
+    fn eq(&self, other: &Self) -> bool {
+        self.tag == other.tag && self.props == other.props && self.children == other.children
+    }
+}
+
+impl Element {
+    pub fn new(tag: impl Into<String>) -> Self {
+        Self {
+            tag: tag.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn prop(mut self, name: impl Into<String>, value: impl Into<PropValue>) -> Self {
+        self.props.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn child(mut self, child: impl Into<ComponentView>) -> Self {
+        self.children.push(child.into());
+        self
+    }
+
+    pub fn children(mut self, children: impl IntoIterator<Item = ComponentView>) -> Self {
+        self.children.extend(children);
+        self
+    }
+
+    /// Call `handler` when `event` fires on this element or below it
+    pub fn on(mut self, event: impl Into<String>, handler: EventHandler) -> Self {
+        self.handlers.insert(event.into(), handler);
+        self
+    }
+}
+
+/// A child component by name, with the key that identifies it among its
+/// siblings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentNode {
+    pub name: String,
+    pub key: String,
+    #[serde(default)]
+    pub props: HashMap<String, PropValue>,
+}
+
+/// What a handler learns about the event it handles
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Event {
+    /// The DOM event type, like `click` or `input`
+    pub name: String,
+    /// Value of the input, select or textarea the event came from
+    pub value: Option<String>,
+    /// Whether the checkbox or radio button the event came from is checked
+    pub checked: Option<bool>,
+    /// Key of a keyboard event
+    pub key: Option<String>,
+}
+
+impl Event {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Runs when an event fires; see [`Scope::handler`](super::Scope::handler)
+/// for one that sends a component a message
+#[derive(Clone)]
+pub struct EventHandler(Rc<dyn Fn(&Event)>);
+
+impl EventHandler {
+    pub fn new(f: impl Fn(&Event) + 'static) -> Self {
+        Self(Rc::new(f))
+    }
+
+    pub fn call(&self, event: &Event) {
+        (self.0)(event)
+    }
+}
+
+impl fmt::Debug for EventHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventHandler")
+    }
+}
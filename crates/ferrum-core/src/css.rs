@@ -242,14 +242,14 @@ pub fn use_style() -> CssBuilder {
 }
 
 /// Theme system for consistent design
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Theme {
     pub colors: Colors,
     pub spacing: Spacing,
     pub typography: Typography,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Colors {
     pub primary: String,
     pub secondary: String,
@@ -260,7 +260,7 @@ pub struct Colors {
     pub text_secondary: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Spacing {
     pub xs: String,
     pub sm: String,
@@ -269,7 +269,7 @@ pub struct Spacing {
     pub xl: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Typography {
     pub font_family: String,
     pub sizes: HashMap<String, String>,
@@ -318,3 +318,11 @@ impl Default for Theme {
         }
     }
 }
+
+/// The theme provided to the component being rendered, or the default one
+///
+/// Provide a theme with [`provide_context`](crate::component::provide_context)
+/// or [`Runtime::provide_context`](crate::component::Runtime::provide_context).
+pub fn use_theme() -> Theme {
+    crate::component::use_context().unwrap_or_default()
+}
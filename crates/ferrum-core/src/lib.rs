@@ -11,6 +11,10 @@ pub mod formatter;
 pub mod parser;
 pub mod routing;
 pub mod state;
+pub mod vdom;
+
+#[doc(hidden)]
+pub use paste;
 
 /// Core error types for the framework
 #[derive(Error, Debug)]
@@ -1,137 +1,304 @@
-use crate::component::{ComponentView, PropValue};
+use crate::{FerrumError, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+mod context;
+mod link;
+mod loader;
+mod navigation;
+mod nested;
+mod pattern;
+mod query;
+mod typed;
+
+pub use context::{set_query, use_navigate, use_params, use_query, use_route, use_router};
+pub use link::{Link, LinkProps};
+pub use loader::{loader_script, LoaderFuture, LOADER_DATA_ELEMENT_ID};
+pub use navigation::{Navigation, Resolution, MAX_REDIRECTS};
+pub use nested::{render_route, Outlet, RouteScope, RouterView, OUTLET_TAG};
+pub use pattern::RoutePattern;
+pub use query::{percent_decode, percent_encode, Query};
+pub use typed::{generate_route_enum, TypedRoute};
+
+use loader::LoaderFn;
+use navigation::{is_external, Guard};
 
 /// Routing system for Ferrum applications
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Route {
     pub path: String,
     pub component: String,
     pub params: HashMap<String, String>,
+    /// First value of each query key; see [`Query`] for repeated keys
     pub query: HashMap<String, String>,
+    /// The query string as given, without its `?`
+    #[serde(default)]
+    pub search: String,
+    /// The path that matched, with its query string
+    #[serde(default)]
+    pub location: String,
+    /// Layout components wrapping this route, outermost first
+    #[serde(default)]
+    pub layouts: Vec<String>,
 }
 
 /// Router for managing application routes
+///
+/// Routes are kept ordered by [`RoutePattern::cmp_specificity`], so the
+/// order they are added in doesn't matter.
 pub struct Router {
-    routes: Vec<Route>,
+    routes: Vec<Entry>,
+    fallback: Option<Route>,
     current_route: Signal<Route>,
+    /// Loader data fetched ahead of navigation, keyed by location
+    prefetched: Arc<Mutex<HashMap<String, Value>>>,
+}
+
+impl fmt::Debug for Router {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let routes: Vec<_> = self.routes.iter().map(|e| e.pattern.as_str()).collect();
+        f.debug_struct("Router")
+            .field("routes", &routes)
+            .field("fallback", &self.fallback.as_ref().map(|r| &r.component))
+            .finish_non_exhaustive()
+    }
+}
+
+/// What a navigation does to the browser's history
+#[derive(Clone, Copy)]
+enum HistoryUpdate {
+    Push,
+    Replace,
+    /// The browser already moved, e.g. on the back button
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    Keep,
+}
+
+/// Record a navigation in the browser's history (only available on client)
+///
+/// Native builds with the `client` feature, like tests, have no browser to
+/// call into, so they skip this as the server does.
+#[cfg(all(feature = "client", target_arch = "wasm32"))]
+fn update_history(update: HistoryUpdate, path: &str) {
+    let Some(history) = web_sys::window().and_then(|window| window.history().ok()) else {
+        return;
+    };
+    let state = &wasm_bindgen::JsValue::NULL;
+    let _ = match update {
+        HistoryUpdate::Push => history.push_state_with_url(state, "", Some(path)),
+        HistoryUpdate::Replace => history.replace_state_with_url(state, "", Some(path)),
+        HistoryUpdate::Keep => Ok(()),
+    };
+}
+
+#[cfg(not(all(feature = "client", target_arch = "wasm32")))]
+fn update_history(_: HistoryUpdate, _: &str) {
+    // No browser history on server
+}
+
+/// A route pattern and what happens when it matches
+struct Entry {
+    pattern: RoutePattern,
+    route: Route,
+    /// Target of a declarative redirect, which has no component
+    redirect: Option<String>,
+    guards: Vec<Guard>,
+    loader: Option<LoaderFn>,
 }
 
 impl Router {
     pub fn new() -> Self {
         Self {
             routes: Vec::new(),
+            fallback: None,
             current_route: Signal::new(Route {
                 path: "/".to_string(),
                 component: "Home".to_string(),
                 params: HashMap::new(),
                 query: HashMap::new(),
+                search: String::new(),
+                location: "/".to_string(),
+                layouts: Vec::new(),
             }),
+            prefetched: Arc::default(),
         }
     }
 
-    pub fn add_route(&mut self, path: &str, component: &str) {
+    /// Add a route; see [`RoutePattern`] for the pattern syntax
+    pub fn add_route(&mut self, path: &str, component: &str) -> Result<()> {
+        self.insert_route(path, component, Vec::new())
+    }
+
+    fn insert_route(&mut self, path: &str, component: &str, layouts: Vec<String>) -> Result<()> {
+        self.insert_entry(path, component, layouts, None)?;
+        log::debug!("Added route: {} -> {}", path, component);
+        Ok(())
+    }
+
+    fn insert_entry(
+        &mut self,
+        path: &str,
+        component: &str,
+        layouts: Vec<String>,
+        redirect: Option<String>,
+    ) -> Result<()> {
+        let pattern = RoutePattern::parse(path)?;
         let route = Route {
             path: path.to_string(),
             component: component.to_string(),
             params: HashMap::new(),
             query: HashMap::new(),
+            search: String::new(),
+            location: String::new(),
+            layouts,
         };
 
-        self.routes.push(route);
-        log::debug!("Added route: {} -> {}", path, component);
+        // Insert after every route at least as specific, keeping ties in
+        // the order they were added
+        let index = self
+            .routes
+            .partition_point(|entry| entry.pattern.cmp_specificity(&pattern).is_le());
+        self.routes.insert(
+            index,
+            Entry {
+                pattern,
+                route,
+                redirect,
+                guards: Vec::new(),
+                loader: None,
+            },
+        );
+        Ok(())
     }
 
-    pub fn navigate(&self, path: &str) {
-        // Parse the path and find matching route
-        if let Some(route) = self.find_route(path) {
-            self.current_route.set(route);
+    /// The route added with exactly this pattern
+    fn entry_mut(&mut self, path: &str) -> Result<&mut Entry> {
+        self.routes
+            .iter_mut()
+            .find(|entry| entry.pattern.as_str() == path)
+            .ok_or_else(|| FerrumError::Routing(format!("no route '{}' has been added", path)))
+    }
+
+    /// Go to `path`, following redirects and running guards
+    ///
+    /// A blocked navigation leaves the current route as it is. Unmatched
+    /// paths go to the fallback route, or fail if there is none.
+    pub fn navigate(&self, path: &str) -> Result<()> {
+        self.go(path, HistoryUpdate::Push)
+    }
+
+    /// Like [`Router::navigate`], replacing the current history entry
+    pub fn replace(&self, path: &str) -> Result<()> {
+        self.go(path, HistoryUpdate::Replace)
+    }
+
+    fn go(&self, path: &str, update: HistoryUpdate) -> Result<()> {
+        let mut path = path.to_string();
 
-            // Update browser history (only available on client)
-            #[cfg(feature = "client")]
-            {
-                if let Some(window) = web_sys::window() {
-                    if let Ok(history) = window.history() {
-                        let _ = history.push_state_with_url(
-                            &wasm_bindgen::JsValue::NULL,
-                            "",
-                            Some(path),
-                        );
+        for _ in 0..=MAX_REDIRECTS {
+            let route = match self.resolve(&path) {
+                Resolution::Render(route) | Resolution::NotFound(Some(route)) => route,
+                Resolution::Redirect(to) if is_external(&to) => {
+                    #[cfg(all(feature = "client", target_arch = "wasm32"))]
+                    {
+                        if let Some(window) = web_sys::window() {
+                            let _ = window.location().assign(&to);
+                        }
                     }
+                    return Ok(());
+                }
+                Resolution::Redirect(to) => {
+                    path = to;
+                    continue;
+                }
+                Resolution::Blocked => {
+                    log::debug!("Navigation to {} was blocked", path);
+                    return Ok(());
                 }
-            }
+                Resolution::NotFound(None) => {
+                    return Err(FerrumError::Routing(format!("no route matches {}", path)))
+                }
+            };
+            self.current_route.set(route);
+
+            update_history(update, &path);
+            return Ok(());
         }
-    }
 
-    fn find_route(&self, path: &str) -> Option<Route> {
-        // Simple exact match for now
-        // TODO: Implement parameter matching and wildcards
-        self.routes.iter().find(|route| route.path == path).cloned()
+        Err(FerrumError::Routing(format!(
+            "more than {} redirects navigating to {}",
+            MAX_REDIRECTS, path
+        )))
     }
 
-    pub fn current_route(&self) -> Signal<Route> {
-        self.current_route.clone()
+    /// The most specific route matching `path`, with its params and query
+    ///
+    /// Params and query values are percent-decoded. Redirects and guards
+    /// are not applied; see [`Router::resolve`] for that.
+    pub fn find_route(&self, path: &str) -> Option<Route> {
+        self.match_path(path)
+            .find(|(entry, _)| entry.redirect.is_none())
+            .map(|(_, route)| route)
     }
-}
 
-/// Navigation hooks
-#[cfg(feature = "client")]
-pub fn use_navigate() -> impl Fn(&str) {
-    |path: &str| {
-        if let Some(window) = web_sys::window() {
-            let _ = window.location().assign(path);
-        }
+    /// Entries matching `path`, most specific first, with the route filled in
+    fn match_path<'a>(
+        &'a self,
+        location: &'a str,
+    ) -> impl Iterator<Item = (&'a Entry, Route)> + 'a {
+        let (path, search) = location.split_once('?').unwrap_or((location, ""));
+
+        self.routes.iter().filter_map(move |entry| {
+            let params = entry
+                .pattern
+                .matches(path)?
+                .into_iter()
+                .map(|(name, value)| (name, percent_decode(&value, false)))
+                .collect();
+            let route = Route {
+                params,
+                query: Query::parse(search).to_map(),
+                search: search.to_string(),
+                location: location.to_string(),
+                ..entry.route.clone()
+            };
+            Some((entry, route))
+        })
     }
-}
 
-#[cfg(not(feature = "client"))]
-pub fn use_navigate() -> impl Fn(&str) {
-    |_: &str| {
-        // No-op on server
+    pub fn current_route(&self) -> Signal<Route> {
+        self.current_route.clone()
     }
 }
 
-/// Hook for accessing current route parameters
-pub fn use_params() -> HashMap<String, String> {
-    // TODO: Implement parameter extraction from current route
-    HashMap::new()
-}
+// Re-export Signal from state module
+use crate::state::Signal;
 
-/// Hook for accessing query parameters
-pub fn use_query() -> HashMap<String, String> {
-    // TODO: Implement query parameter extraction
-    HashMap::new()
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-/// Link component for navigation
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LinkProps {
-    pub to: String,
-    pub class: Option<String>,
-    pub children: String,
-}
+    #[test]
+    fn test_find_route_ranks_and_fills_route() {
+        let mut router = Router::new();
+        router.add_route("/*rest", "NotFound").unwrap();
+        router.add_route("/users/:id", "UserProfile").unwrap();
+        router.add_route("/users/new", "NewUser").unwrap();
+        assert!(router.add_route("users", "Invalid").is_err());
+
+        let route = router.find_route("/users/42?tab=posts&page=2").unwrap();
+        assert_eq!(route.component, "UserProfile");
+        assert_eq!(route.params["id"], "42");
+        assert_eq!(route.query["tab"], "posts");
+        assert_eq!(route.query["page"], "2");
 
-pub fn Link(props: LinkProps) -> ComponentView {
-    let onclick = format!("ferrum.navigate('{}')", props.to);
-
-    ComponentView {
-        tag: "a".to_string(),
-        props: {
-            let mut map = HashMap::new();
-            map.insert("href".to_string(), PropValue::String(props.to.clone()));
-            map.insert("onclick".to_string(), PropValue::String(onclick));
-            if let Some(class) = props.class {
-                map.insert("class".to_string(), PropValue::String(class));
-            }
-            map
-        },
-        children: vec![ComponentView {
-            tag: "span".to_string(),
-            props: HashMap::new(),
-            children: vec![],
-        }],
+        assert_eq!(
+            router.find_route("/users/new").unwrap().component,
+            "NewUser"
+        );
+        assert_eq!(router.find_route("/nope").unwrap().component, "NotFound");
     }
 }
-
-// Re-export Signal from state module
-use crate::state::Signal;
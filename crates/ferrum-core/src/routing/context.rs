@@ -0,0 +1,173 @@
+//! Route hooks
+//!
+//! Inside a component's view the hooks use the `Arc<Router>` provided with
+//! [`provide_context`](crate::component::provide_context); elsewhere, or
+//! when none was, the one [`Router::provide`] made available to this
+//! thread. They read its current route through a signal, so effects and
+//! memos using them re-run when the route changes.
+
+use super::query::Query;
+use super::{Route, Router};
+use crate::component::use_context;
+use crate::state::Signal;
+use crate::{FerrumError, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+thread_local! {
+    static ROUTER: RefCell<Option<Arc<Router>>> = const { RefCell::new(None) };
+}
+
+impl Router {
+    /// Serve this router to the route hooks on this thread
+    ///
+    /// A router provided to a component tree takes precedence inside it.
+    pub fn provide(self: &Arc<Self>) {
+        ROUTER.with(|router| *router.borrow_mut() = Some(self.clone()));
+    }
+}
+
+/// The provided router, if any
+pub fn use_router() -> Option<Arc<Router>> {
+    use_context::<Arc<Router>>().or_else(|| ROUTER.with(|router| router.borrow().clone()))
+}
+
+/// The provided router's current route, if any
+pub fn use_route() -> Option<Signal<Route>> {
+    use_router().map(|router| router.current_route())
+}
+
+/// Navigate with the provided router
+///
+/// Without one, the client falls back to a full page load.
+pub fn use_navigate() -> impl Fn(&str) -> Result<()> {
+    let router = use_router();
+    move |path: &str| match &router {
+        Some(router) => router.navigate(path),
+        None => {
+            #[cfg(all(feature = "client", target_arch = "wasm32"))]
+            {
+                if let Some(window) = web_sys::window() {
+                    let _ = window.location().assign(path);
+                    return Ok(());
+                }
+            }
+            Err(no_router())
+        }
+    }
+}
+
+fn no_router() -> FerrumError {
+    FerrumError::Routing("no router provided; call Router::provide first".to_string())
+}
+
+fn provided_route() -> Result<Signal<Route>> {
+    use_route().ok_or_else(no_router)
+}
+
+/// Params of the current route, empty if no router is provided
+pub fn use_params() -> HashMap<String, String> {
+    use_route()
+        .map(|route| route.with(|route| route.params.clone()))
+        .unwrap_or_default()
+}
+
+/// The current query string read into `T`
+///
+/// `use_query::<HashMap<String, String>>()` gives the raw values; see
+/// [`Query::deserialize`] for how fields are read.
+pub fn use_query<T: DeserializeOwned>() -> Result<T> {
+    provided_route()?.with(|route| Query::parse(&route.search).deserialize())
+}
+
+/// Replace the query string without navigating
+///
+/// The route's params and component stay as they are; only its query
+/// changes, and on the client the URL is updated in place.
+pub fn set_query<T: Serialize>(query: &T) -> Result<()> {
+    let route = provided_route()?;
+    let query = Query::from_serialize(query)?;
+    let search = query.to_string();
+
+    route.update(|route| {
+        let path = route.location.split('?').next().unwrap_or_default();
+        route.location = if search.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}?{}", path, search)
+        };
+        route.query = query.to_map();
+        route.search = search;
+    });
+
+    #[cfg(all(feature = "client", target_arch = "wasm32"))]
+    {
+        if let Some(window) = web_sys::window() {
+            if let Ok(history) = window.history() {
+                let location = route.get_untracked().location;
+                let _ = history.replace_state_with_url(
+                    &wasm_bindgen::JsValue::NULL,
+                    "",
+                    Some(&location),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::create_effect;
+    use serde::Deserialize;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Filters {
+        #[serde(default)]
+        tag: Vec<String>,
+        page: Option<u32>,
+    }
+
+    #[test]
+    fn test_hooks_follow_current_route() {
+        let mut router = Router::new();
+        router.add_route("/posts/:slug", "Post").unwrap();
+        let router = Arc::new(router);
+        router.provide();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let _effect = {
+            let seen = seen.clone();
+            create_effect(move || {
+                let filters: Filters = use_query().unwrap();
+                seen.lock().unwrap().push(filters.tag);
+            })
+        };
+
+        router
+            .navigate("/posts/hello%20world?tag=rust&tag=web")
+            .unwrap();
+        assert_eq!(use_params()["slug"], "hello world");
+
+        set_query(&Filters {
+            tag: vec!["a b".to_string()],
+            page: Some(2),
+        })
+        .unwrap();
+        let route = router.current_route().get();
+        assert_eq!(route.search, "page=2&tag=a%20b");
+        assert_eq!(route.location, "/posts/hello%20world?page=2&tag=a%20b");
+        assert_eq!(route.params["slug"], "hello world");
+        assert_eq!(use_query::<HashMap<String, String>>().unwrap()["page"], "2");
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [vec![], vec!["rust", "web"], vec!["a b"]]
+        );
+    }
+}
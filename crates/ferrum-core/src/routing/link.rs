@@ -0,0 +1,317 @@
+//! Links and client-side navigation
+//!
+//! [`Link`] renders a plain `<a href>`, so links work before the app has
+//! loaded. In the browser, [`Router::listen`] intercepts clicks on the links
+//! it rendered, navigates through the router instead of reloading, and
+//! follows the back and forward buttons.
+
+use super::context::use_route;
+use super::navigation::is_external;
+use super::TypedRoute;
+use crate::component::{ComponentView, Element, PropValue};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[cfg(feature = "client")]
+use super::{HistoryUpdate, Router};
+#[cfg(feature = "client")]
+use crate::{FerrumError, Result};
+#[cfg(feature = "client")]
+use std::sync::Arc;
+
+/// Marks the links [`Router::listen`] handles
+pub const LINK_ATTR: &str = "data-ferrum-link";
+const REPLACE_ATTR: &str = "data-replace";
+const PREFETCH_ATTR: &str = "data-prefetch";
+
+/// Class given to active links without an `active_class`
+pub const DEFAULT_ACTIVE_CLASS: &str = "active";
+
+/// Link component for navigation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkProps {
+    pub to: String,
+    pub class: Option<String>,
+    /// Class added while the link is active
+    #[serde(default)]
+    pub active_class: Option<String>,
+    /// Only active on `to` itself, not on the paths below it
+    #[serde(default)]
+    pub exact: bool,
+    /// Replace the current history entry instead of adding one
+    #[serde(default)]
+    pub replace: bool,
+    /// Run the target's loader when the link is hovered or focused
+    #[serde(default)]
+    pub prefetch: bool,
+    #[serde(default)]
+    pub children: Vec<ComponentView>,
+}
+
+impl LinkProps {
+    /// Props linking to a typed route
+    pub fn route(route: &impl TypedRoute) -> Self {
+        Self {
+            to: route.to_path(),
+            ..Default::default()
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn Link(props: LinkProps) -> ComponentView {
+    let mut attrs = HashMap::new();
+    let mut classes: Vec<String> = props.class.into_iter().collect();
+
+    if is_external(&props.to) {
+        attrs.insert(
+            "rel".to_string(),
+            PropValue::String("external noopener".to_string()),
+        );
+    } else {
+        attrs.insert(LINK_ATTR.to_string(), PropValue::Boolean(true));
+        if props.replace {
+            attrs.insert(REPLACE_ATTR.to_string(), PropValue::Boolean(true));
+        }
+        if props.prefetch {
+            attrs.insert(PREFETCH_ATTR.to_string(), PropValue::Boolean(true));
+        }
+
+        let location = use_route().map(|route| route.with(|route| route.location.clone()));
+        let (active, current) = location
+            .map(|location| link_state(&props.to, &location))
+            .unwrap_or_default();
+        if current {
+            attrs.insert(
+                "aria-current".to_string(),
+                PropValue::String("page".to_string()),
+            );
+        }
+        if current || (active && !props.exact) {
+            classes.push(
+                props
+                    .active_class
+                    .unwrap_or_else(|| DEFAULT_ACTIVE_CLASS.to_string()),
+            );
+        }
+    }
+
+    attrs.insert("href".to_string(), PropValue::String(props.to));
+    if !classes.is_empty() {
+        attrs.insert("class".to_string(), PropValue::String(classes.join(" ")));
+    }
+
+    Element {
+        tag: "a".to_string(),
+        props: attrs,
+        children: props.children,
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Whether `location` is at or below `to`, and whether it is exactly `to`
+fn link_state(to: &str, location: &str) -> (bool, bool) {
+    let normalize = |path: &str| {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        match path.trim_end_matches('/') {
+            "" => "/".to_string(),
+            trimmed => trimmed.to_string(),
+        }
+    };
+    let (to, location) = (normalize(to), normalize(location));
+
+    let current = to == location;
+    let below = to != "/" && location.starts_with(&format!("{}/", to));
+    (current || below, current)
+}
+
+#[cfg(feature = "client")]
+impl Router {
+    /// Take over link clicks and the back and forward buttons
+    ///
+    /// The listeners stay installed for the life of the page.
+    pub fn listen(self: &Arc<Self>) -> Result<()> {
+        use wasm_bindgen::closure::Closure;
+        use wasm_bindgen::JsCast;
+
+        let js_error = |e: wasm_bindgen::JsValue| FerrumError::Routing(format!("{:?}", e));
+        let window =
+            web_sys::window().ok_or_else(|| FerrumError::Routing("no window".to_string()))?;
+        let document = window
+            .document()
+            .ok_or_else(|| FerrumError::Routing("no document".to_string()))?;
+
+        let router = self.clone();
+        let on_click = Closure::<dyn FnMut(_)>::new(move |event: web_sys::MouseEvent| {
+            // Leave new-tab clicks and the like to the browser
+            let modified =
+                event.meta_key() || event.ctrl_key() || event.shift_key() || event.alt_key();
+            if event.default_prevented() || event.button() != 0 || modified {
+                return;
+            }
+            let Some(link) = closest_link(event.target()) else {
+                return;
+            };
+            let Some(href) = link.get_attribute("href") else {
+                return;
+            };
+            if link.has_attribute("target") || link.has_attribute("download") {
+                return;
+            }
+
+            event.prevent_default();
+            let result = if link.has_attribute(REPLACE_ATTR) {
+                router.replace(&href)
+            } else {
+                router.navigate(&href)
+            };
+            if let Err(e) = result {
+                log::warn!("Navigation to {} failed: {}", href, e);
+            }
+        });
+        document
+            .add_event_listener_with_callback("click", on_click.as_ref().unchecked_ref())
+            .map_err(js_error)?;
+        on_click.forget();
+
+        let router = self.clone();
+        let on_intent = Closure::<dyn FnMut(_)>::new(move |event: web_sys::Event| {
+            let Some(link) = closest_link(event.target()) else {
+                return;
+            };
+            let Some(href) = link.get_attribute("href") else {
+                return;
+            };
+            if !link.has_attribute(PREFETCH_ATTR) {
+                return;
+            }
+            // Prefetch each link once
+            let _ = link.remove_attribute(PREFETCH_ATTR);
+
+            let router = router.clone();
+            let prefetch = async move {
+                if let Err(e) = router.prefetch(&href).await {
+                    log::warn!("Prefetching {} failed: {}", href, e);
+                }
+            };
+            // The browser has no Tokio runtime; its event loop drives the future
+            #[cfg(target_arch = "wasm32")]
+            wasm_bindgen_futures::spawn_local(prefetch);
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                handle.spawn(prefetch);
+            }
+        });
+        for event in ["mouseover", "focusin"] {
+            document
+                .add_event_listener_with_callback(event, on_intent.as_ref().unchecked_ref())
+                .map_err(js_error)?;
+        }
+        on_intent.forget();
+
+        let router = self.clone();
+        let on_popstate = Closure::<dyn FnMut(_)>::new(move |_: web_sys::Event| {
+            let Some(location) = web_sys::window().map(|window| window.location()) else {
+                return;
+            };
+            let path = format!(
+                "{}{}",
+                location.pathname().unwrap_or_default(),
+                location.search().unwrap_or_default()
+            );
+            if let Err(e) = router.go(&path, HistoryUpdate::Keep) {
+                log::warn!("Navigation to {} failed: {}", path, e);
+            }
+        });
+        window
+            .add_event_listener_with_callback("popstate", on_popstate.as_ref().unchecked_ref())
+            .map_err(js_error)?;
+        on_popstate.forget();
+
+        Ok(())
+    }
+}
+
+/// The router link an event happened in, if any
+#[cfg(feature = "client")]
+fn closest_link(target: Option<web_sys::EventTarget>) -> Option<web_sys::Element> {
+    use wasm_bindgen::JsCast;
+
+    target?
+        .dyn_into::<web_sys::Element>()
+        .ok()?
+        .closest(&format!("a[{}]", LINK_ATTR))
+        .ok()?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routing::Router;
+    use std::sync::Arc;
+
+    fn prop<'a>(view: &'a ComponentView, name: &str) -> Option<&'a PropValue> {
+        view.as_element()?.props.get(name)
+    }
+
+    fn class(view: &ComponentView) -> Option<&str> {
+        match prop(view, "class") {
+            Some(PropValue::String(class)) => Some(class),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_link_renders_children_and_active_state() {
+        let mut router = Router::new();
+        router.add_route("/docs", "Docs").unwrap();
+        router.add_route("/docs/:page", "DocsPage").unwrap();
+        let router = Arc::new(router);
+        router.provide();
+        router.navigate("/docs/intro?v=2").unwrap();
+
+        let link = |to: &str, exact: bool| {
+            Link(LinkProps {
+                to: to.to_string(),
+                class: Some("nav".to_string()),
+                exact,
+                children: vec![ComponentView::text("Docs")],
+                ..Default::default()
+            })
+        };
+
+        let current = link("/docs/intro", false);
+        assert_eq!(
+            current.as_element().unwrap().children,
+            [ComponentView::text("Docs")]
+        );
+        assert_eq!(class(&current), Some("nav active"));
+        assert!(prop(&current, "aria-current").is_some());
+        assert!(prop(&current, LINK_ATTR).is_some());
+
+        let parent = link("/docs/", false);
+        assert_eq!(class(&parent), Some("nav active"));
+        assert!(prop(&parent, "aria-current").is_none());
+        assert_eq!(class(&link("/docs", true)), Some("nav"));
+        assert_eq!(class(&link("/", false)), Some("nav"));
+
+        let external = Link(LinkProps {
+            to: "https://example.com".to_string(),
+            replace: true,
+            ..Default::default()
+        });
+        assert!(prop(&external, LINK_ATTR).is_none());
+        assert!(prop(&external, REPLACE_ATTR).is_none());
+        assert!(class(&external).is_none());
+
+        let options = Link(LinkProps {
+            to: "/docs".to_string(),
+            replace: true,
+            prefetch: true,
+            ..Default::default()
+        });
+        assert!(prop(&options, REPLACE_ATTR).is_some());
+        assert!(prop(&options, PREFETCH_ATTR).is_some());
+    }
+}
@@ -0,0 +1,187 @@
+//! Route data loaders
+//!
+//! A loader fetches what a route needs before it renders. The dev server
+//! awaits it with [`Router::load`] and embeds the result in the page with
+//! [`loader_script`]; in the app, [`Router::loader_data`] reloads it as a
+//! [`Resource`] whenever the current route changes.
+
+use super::{Resolution, Route, Router};
+use crate::state::runtime::lock;
+use crate::state::{create_resource, Resource};
+use crate::{FerrumError, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Id of the `<script>` tag holding a server-run loader's data
+pub const LOADER_DATA_ELEMENT_ID: &str = "ferrum-loader-data";
+
+/// A running loader, with its data as JSON
+pub type LoaderFuture = Pin<Box<dyn Future<Output = std::result::Result<Value, String>> + Send>>;
+
+pub(super) type LoaderFn = Arc<dyn Fn(Route) -> LoaderFuture + Send + Sync>;
+
+impl Router {
+    /// Load data for the route added as `path` before it renders
+    pub fn loader<T, F, Fut>(&mut self, path: &str, loader: F) -> Result<()>
+    where
+        T: Serialize,
+        F: Fn(Route) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<T, String>> + Send + 'static,
+    {
+        self.entry_mut(path)?.loader = Some(Arc::new(move |route| {
+            let data = loader(route);
+            Box::pin(async move { serde_json::to_value(data.await?).map_err(|e| e.to_string()) })
+        }));
+        Ok(())
+    }
+
+    /// Run the loader of `route`, if it has one
+    pub fn load(&self, route: &Route) -> Option<LoaderFuture> {
+        let entry = self
+            .routes
+            .iter()
+            .find(|entry| entry.pattern.as_str() == route.path)?;
+        Some((entry.loader.as_ref()?)(route.clone()))
+    }
+
+    /// Run the loader for `path` ahead of navigating there
+    ///
+    /// [`Router::loader_data`] uses the result instead of loading again
+    /// when the app navigates to exactly `path`.
+    pub async fn prefetch(&self, path: &str) -> Result<()> {
+        let Resolution::Render(route) = self.resolve(path) else {
+            return Ok(());
+        };
+        let Some(load) = self.load(&route) else {
+            return Ok(());
+        };
+
+        let data = load.await.map_err(FerrumError::Routing)?;
+        lock(&self.prefetched).insert(route.location, data);
+        Ok(())
+    }
+
+    /// The current route's loader data, reloaded whenever the route changes
+    ///
+    /// Loaders added after this is called are not used. Routes without a
+    /// loader leave the data empty and set an error.
+    pub fn loader_data<T>(&self) -> Resource<T>
+    where
+        T: DeserializeOwned + Clone + Send + Sync + 'static,
+    {
+        let loaders: HashMap<String, LoaderFn> = self
+            .routes
+            .iter()
+            .filter_map(|entry| Some((entry.pattern.as_str().to_string(), entry.loader.clone()?)))
+            .collect();
+        let prefetched = self.prefetched.clone();
+        let current = self.current_route();
+        let route = current.clone();
+
+        create_resource(
+            move || current.with(route_key),
+            move |_| {
+                let route = route.get_untracked();
+                let loader = loaders.get(&route.path).cloned();
+                let ready = lock(&prefetched).remove(&route.location);
+                async move {
+                    let data = match (ready, loader) {
+                        (Some(data), _) => data,
+                        (None, Some(loader)) => loader(route).await?,
+                        (None, None) => return Err(format!("route {} has no loader", route.path)),
+                    };
+                    serde_json::from_value(data).map_err(|e| e.to_string())
+                }
+            },
+        )
+    }
+}
+
+/// What a loader's result depends on
+fn route_key(route: &Route) -> (String, Vec<(String, String)>, String) {
+    let mut params: Vec<_> = route
+        .params
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    params.sort();
+    (route.path.clone(), params, route.search.clone())
+}
+
+/// `<script>` tag embedding loader data in a server-rendered page
+pub fn loader_script(data: &Value) -> Result<String> {
+    // `</script>` inside a string value must not end the element early
+    let json = serde_json::to_string(data)?.replace("</", "<\\/");
+    Ok(format!(
+        r#"<script type="application/json" id="{}">{}</script>"#,
+        LOADER_DATA_ELEMENT_ID, json
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Post {
+        slug: String,
+        title: String,
+    }
+
+    #[tokio::test]
+    async fn test_loaders_run_for_the_current_route() {
+        let mut router = Router::new();
+        router.add_route("/", "Home").unwrap();
+        router.add_route("/posts/:slug", "Post").unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        router
+            .loader("/posts/:slug", move |route: Route| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let slug = route.params["slug"].clone();
+                    Ok(Post {
+                        title: slug.to_uppercase(),
+                        slug,
+                    })
+                }
+            })
+            .unwrap();
+
+        let route = router.find_route("/posts/hi").unwrap();
+        let data = router.load(&route).unwrap().await.unwrap();
+        assert_eq!(data["title"], "HI");
+        assert!(router.load(&router.find_route("/").unwrap()).is_none());
+        assert!(loader_script(&data)
+            .unwrap()
+            .contains(LOADER_DATA_ELEMENT_ID));
+
+        router.navigate("/posts/first").unwrap();
+        let post = router.loader_data::<Post>();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(post.data().get().unwrap().title, "FIRST");
+
+        router.navigate("/posts/second").unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(post.data().get().unwrap().slug, "second");
+
+        router.navigate("/").unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(post.error().get().unwrap().contains("no loader"));
+
+        router.prefetch("/posts/third").await.unwrap();
+        let prefetched = calls.load(Ordering::SeqCst);
+        router.navigate("/posts/third").unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(post.data().get().unwrap().title, "THIRD");
+        assert_eq!(calls.load(Ordering::SeqCst), prefetched);
+    }
+}
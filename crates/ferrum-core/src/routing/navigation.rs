@@ -0,0 +1,199 @@
+//! Guards, redirects and the fallback route
+//!
+//! [`Router::resolve`] decides what a path leads to without changing the
+//! current route, so the dev server can answer with an HTTP redirect or 404
+//! where the client would navigate.
+
+use super::query::percent_encode;
+use super::{Route, Router};
+use crate::Result;
+
+/// Redirects followed by [`Router::navigate`] before it gives up
+pub const MAX_REDIRECTS: usize = 10;
+
+/// What a `before_enter` guard decides
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Navigation {
+    Allow,
+    Redirect(String),
+    Block,
+}
+
+/// Where a path leads
+#[derive(Debug, Clone)]
+pub enum Resolution {
+    Render(Route),
+    Redirect(String),
+    Blocked,
+    /// No route matched; holds the fallback route if one is set
+    NotFound(Option<Route>),
+}
+
+pub(super) type Guard = Box<dyn Fn(&Route) -> Navigation + Send + Sync>;
+
+impl Router {
+    /// Run `guard` before entering the route added as `path`
+    ///
+    /// Guards run in the order they were added; the first that doesn't
+    /// allow the navigation decides it.
+    pub fn before_enter<F>(&mut self, path: &str, guard: F) -> Result<()>
+    where
+        F: Fn(&Route) -> Navigation + Send + Sync + 'static,
+    {
+        self.entry_mut(path)?.guards.push(Box::new(guard));
+        Ok(())
+    }
+
+    /// Send `from` to `to`
+    ///
+    /// `:name` and `*name` segments of `to` are filled from the params of
+    /// `from`, and the query string is kept unless `to` has its own.
+    pub fn redirect(&mut self, from: &str, to: &str) -> Result<()> {
+        self.insert_entry(from, "", Vec::new(), Some(to.to_string()))?;
+        log::debug!("Added redirect: {} -> {}", from, to);
+        Ok(())
+    }
+
+    /// Render `component` for paths no route matches
+    pub fn fallback(&mut self, component: &str) {
+        self.fallback = Some(Route {
+            path: "*".to_string(),
+            component: component.to_string(),
+            params: Default::default(),
+            query: Default::default(),
+            search: String::new(),
+            location: String::new(),
+            layouts: Vec::new(),
+        });
+    }
+
+    /// Where `path` leads, after redirects and guards of the matched route
+    ///
+    /// Only one step is taken: a redirect is returned, not followed.
+    pub fn resolve(&self, path: &str) -> Resolution {
+        let Some((entry, route)) = self.match_path(path).next() else {
+            let (_, search) = path.split_once('?').unwrap_or((path, ""));
+            return Resolution::NotFound(self.fallback.as_ref().map(|fallback| Route {
+                query: super::Query::parse(search).to_map(),
+                search: search.to_string(),
+                location: path.to_string(),
+                ..fallback.clone()
+            }));
+        };
+
+        if let Some(target) = &entry.redirect {
+            return Resolution::Redirect(fill_target(target, &route));
+        }
+
+        for guard in &entry.guards {
+            match guard(&route) {
+                Navigation::Allow => {}
+                Navigation::Redirect(to) => return Resolution::Redirect(to),
+                Navigation::Block => return Resolution::Blocked,
+            }
+        }
+        Resolution::Render(route)
+    }
+}
+
+/// Absolute URLs leave the app
+pub(super) fn is_external(target: &str) -> bool {
+    target.contains("://") || target.starts_with("//") || target.starts_with("mailto:")
+}
+
+fn fill_target(target: &str, route: &Route) -> String {
+    let (path, search) = match target.split_once('?') {
+        Some((path, search)) => (path, search),
+        None => (target, route.search.as_str()),
+    };
+
+    let path = path
+        .split('/')
+        .map(|segment| {
+            let name = segment
+                .strip_prefix(':')
+                .or_else(|| segment.strip_prefix('*'));
+            match name.and_then(|name| route.params.get(name)) {
+                Some(value) => value
+                    .split('/')
+                    .map(percent_encode)
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                None => segment.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if search.is_empty() {
+        path
+    } else {
+        format!("{}?{}", path, search)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_redirects_guards_and_fallback() {
+        let signed_in = Arc::new(AtomicBool::new(false));
+
+        let mut router = Router::new();
+        router.add_route("/", "Home").unwrap();
+        router.add_route("/login", "Login").unwrap();
+        router.add_route("/users/:id", "User").unwrap();
+        router.add_route("/admin", "Admin").unwrap();
+        router.add_route("/locked", "Locked").unwrap();
+        router.redirect("/u/:id", "/users/:id").unwrap();
+        router.redirect("/loop", "/loop").unwrap();
+        router.fallback("NotFound");
+
+        let guard = signed_in.clone();
+        router
+            .before_enter("/admin", move |_| {
+                if guard.load(Ordering::SeqCst) {
+                    Navigation::Allow
+                } else {
+                    Navigation::Redirect("/login".to_string())
+                }
+            })
+            .unwrap();
+        router
+            .before_enter("/locked", |_| Navigation::Block)
+            .unwrap();
+        assert!(router
+            .before_enter("/missing", |_| Navigation::Allow)
+            .is_err());
+
+        assert!(matches!(
+            router.resolve("/u/a%20b?tab=posts"),
+            Resolution::Redirect(to) if to == "/users/a%20b?tab=posts"
+        ));
+        assert!(router.find_route("/u/1").is_none());
+
+        router.navigate("/u/7").unwrap();
+        assert_eq!(router.current_route().get().params["id"], "7");
+
+        router.navigate("/admin").unwrap();
+        assert_eq!(router.current_route().get().component, "Login");
+        signed_in.store(true, Ordering::SeqCst);
+        router.navigate("/admin").unwrap();
+        assert_eq!(router.current_route().get().component, "Admin");
+
+        router.navigate("/locked").unwrap();
+        assert_eq!(router.current_route().get().component, "Admin");
+
+        router.navigate("/nowhere?q=1").unwrap();
+        let route = router.current_route().get();
+        assert_eq!(
+            (route.component.as_str(), route.search.as_str()),
+            ("NotFound", "q=1")
+        );
+
+        assert!(router.navigate("/loop").is_err());
+    }
+}
@@ -0,0 +1,353 @@
+//! Nested routes rendered inside layout components
+//!
+//! A layout wraps every route nested below it and marks where the matched
+//! child goes with an [`Outlet`]. [`render_route`] renders the matched
+//! component, then each layout from the innermost out. In a component
+//! runtime, [`RouterView`] mounts the chain instead: each layout is created
+//! by name, and its `Outlet` mounts the next component down.
+
+use super::{use_route, Route, Router};
+use crate::component::{
+    provide_context, scope, use_context, Component, ComponentNode, ComponentView, Element,
+};
+use crate::state::Subscription;
+use crate::Result;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Tag of the placeholder element produced by [`Outlet`]
+pub const OUTLET_TAG: &str = "ferrum-outlet";
+
+/// Routes added below a layout; see [`Router::nest`]
+pub struct RouteScope<'a> {
+    router: &'a mut Router,
+    prefix: String,
+    layouts: Vec<String>,
+}
+
+impl RouteScope<'_> {
+    /// Add a route relative to the scope's prefix
+    pub fn add_route(&mut self, path: &str, component: &str) -> Result<()> {
+        let path = join_paths(&self.prefix, path);
+        self.router
+            .insert_route(&path, component, self.layouts.clone())
+    }
+
+    /// Nest another layout inside this one
+    pub fn nest<F>(&mut self, prefix: &str, layout: &str, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut RouteScope) -> Result<()>,
+    {
+        let mut layouts = self.layouts.clone();
+        layouts.push(layout.to_string());
+
+        f(&mut RouteScope {
+            router: &mut *self.router,
+            prefix: join_paths(&self.prefix, prefix),
+            layouts,
+        })
+    }
+}
+
+impl Router {
+    /// Add routes below `prefix`, all rendered inside the `layout` component
+    pub fn nest<F>(&mut self, prefix: &str, layout: &str, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut RouteScope) -> Result<()>,
+    {
+        RouteScope {
+            router: self,
+            prefix: "/".to_string(),
+            layouts: Vec::new(),
+        }
+        .nest(prefix, layout, f)
+    }
+}
+
+fn join_paths(prefix: &str, path: &str) -> String {
+    let joined = format!(
+        "{}/{}",
+        prefix.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    );
+    match joined.trim_end_matches('/') {
+        "" => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// Where a layout renders its matched child
+///
+/// Below a [`RouterView`] this is the next component of the route's chain;
+/// elsewhere it is a placeholder for [`render_route`] to fill.
+#[allow(non_snake_case)]
+pub fn Outlet() -> ComponentView {
+    match use_context::<Chain>() {
+        Some(chain) => chain.mount(),
+        None => Element::new(OUTLET_TAG).into(),
+    }
+}
+
+/// The current route's layouts and component, and how far down the
+/// component being rendered is
+///
+/// It differs after any navigation, so everything below the layouts
+/// renders again and reads the new route.
+#[derive(Clone, PartialEq)]
+struct Chain {
+    route: Rc<Route>,
+    components: Rc<[String]>,
+    depth: usize,
+}
+
+impl Chain {
+    /// A node for the component at `depth`, with the rest of the chain
+    /// provided to it
+    fn mount(&self) -> ComponentView {
+        let Some(name) = self.components.get(self.depth) else {
+            log::warn!("Outlet below the route's own component renders nothing");
+            return ComponentView::Fragment(Vec::new());
+        };
+        provide_context(Chain {
+            route: self.route.clone(),
+            components: self.components.clone(),
+            depth: self.depth + 1,
+        });
+        ComponentView::Component(ComponentNode {
+            name: name.clone(),
+            key: name.clone(),
+            props: Default::default(),
+        })
+    }
+}
+
+/// Renders the provided router's current route inside its layouts
+///
+/// The layouts and the route's component are created from the runtime's
+/// registry with empty props, so each must accept those. A layout kept
+/// across navigations keeps its state; everything below it renders again.
+#[derive(Default)]
+pub struct RouterView {
+    subscription: RefCell<Option<Subscription>>,
+}
+
+impl Component for RouterView {
+    type Props = ();
+    /// The route changed
+    type Msg = ();
+
+    fn create(_: ()) -> Self {
+        Self::default()
+    }
+
+    fn update(&mut self, _: ()) -> bool {
+        true
+    }
+
+    fn view(&self) -> ComponentView {
+        let Some(route) = use_route() else {
+            log::warn!("RouterView rendered without a router");
+            return ComponentView::Fragment(Vec::new());
+        };
+
+        let mut subscription = self.subscription.borrow_mut();
+        if subscription.is_none() {
+            if let Some(scope) = scope::<Self>() {
+                let scope = scope.remote();
+                *subscription = Some(route.subscribe(move |_| scope.send(())));
+            }
+        }
+
+        let route = route.get_untracked();
+        Chain {
+            components: route
+                .layouts
+                .iter()
+                .chain([&route.component])
+                .cloned()
+                .collect(),
+            route: Rc::new(route),
+            depth: 0,
+        }
+        .mount()
+    }
+}
+
+/// Render `route` inside its layouts
+///
+/// `render` is called with each component name, innermost first; the
+/// route's params are on `route` for it to read.
+pub fn render_route(route: &Route, mut render: impl FnMut(&str) -> ComponentView) -> ComponentView {
+    let mut view = render(&route.component);
+
+    for layout in route.layouts.iter().rev() {
+        let mut child = Some(view);
+        let mut layout_view = render(layout);
+        fill_outlet(&mut layout_view, &mut child);
+
+        if child.is_some() {
+            log::warn!("Layout {} has no Outlet; its child is not rendered", layout);
+        }
+        view = layout_view;
+    }
+    view
+}
+
+/// Replace the first outlet in `view` with `child`
+fn fill_outlet(view: &mut ComponentView, child: &mut Option<ComponentView>) {
+    let children = match view {
+        ComponentView::Element(element) if element.tag == OUTLET_TAG => {
+            if let Some(child) = child.take() {
+                *view = child;
+            }
+            return;
+        }
+        ComponentView::Element(element) => &mut element.children,
+        ComponentView::Fragment(children) => children,
+        _ => return,
+    };
+    for node in children {
+        if child.is_none() {
+            return;
+        }
+        fill_outlet(node, child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(tag: &str, children: Vec<ComponentView>) -> ComponentView {
+        Element::new(tag).children(children).into()
+    }
+
+    fn element(view: &ComponentView) -> &Element {
+        view.as_element().expect("an element")
+    }
+
+    #[test]
+    fn test_nested_routes_carry_layouts_and_params() {
+        let mut router = Router::new();
+        router.add_route("/", "Home").unwrap();
+        router
+            .nest("/orgs/:org", "OrgLayout", |org| {
+                org.add_route("/", "OrgOverview")?;
+                org.nest("/projects", "ProjectsLayout", |projects| {
+                    projects.add_route("/:id", "Project")
+                })
+            })
+            .unwrap();
+
+        let route = router.find_route("/orgs/acme/projects/7").unwrap();
+        assert_eq!(route.path, "/orgs/:org/projects/:id");
+        assert_eq!(route.layouts, ["OrgLayout", "ProjectsLayout"]);
+        assert_eq!(route.params["org"], "acme");
+        assert_eq!(route.params["id"], "7");
+
+        let route = router.find_route("/orgs/acme").unwrap();
+        assert_eq!(route.component, "OrgOverview");
+        assert_eq!(route.layouts, ["OrgLayout"]);
+        assert!(router.find_route("/").unwrap().layouts.is_empty());
+    }
+
+    /// Registers `name` as a component rendering `view`
+    fn register(
+        registry: &mut crate::component::ComponentRegistry,
+        name: &str,
+        view: fn() -> ComponentView,
+    ) {
+        struct Static(fn() -> ComponentView);
+
+        impl Component for Static {
+            type Props = fn() -> ComponentView;
+            type Msg = ();
+
+            fn create(view: fn() -> ComponentView) -> Self {
+                Self(view)
+            }
+
+            fn update(&mut self, _: ()) -> bool {
+                false
+            }
+
+            fn view(&self) -> ComponentView {
+                (self.0)()
+            }
+        }
+
+        registry
+            .register_with::<Static>(name, move |_| Ok(view))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_router_view_mounts_the_layout_chain() {
+        use crate::component::{ComponentRegistry, Runtime};
+        use crate::routing::use_params;
+        use std::sync::Arc;
+
+        let mut router = Router::new();
+        router
+            .nest("/app", "Shell", |app| {
+                app.add_route("/users/:id", "User")?;
+                app.add_route("/about", "About")
+            })
+            .unwrap();
+        let router = Arc::new(router);
+        router.provide();
+        router.navigate("/app/users/1").unwrap();
+
+        let mut registry = ComponentRegistry::new();
+        register(&mut registry, "Shell", || {
+            view("main", vec![view("nav", vec![]), Outlet()])
+        });
+        register(&mut registry, "User", || {
+            ComponentView::text(format!("user {}", use_params()["id"]))
+        });
+        register(&mut registry, "About", || ComponentView::text("about"));
+
+        let mut runtime = Runtime::<RouterView>::mount_with((), registry).unwrap();
+        let text = |runtime: &Runtime<RouterView>| {
+            let main = runtime.view().nodes()[0].as_element().unwrap().clone();
+            assert_eq!(main.tag, "main");
+            match &main.children[1] {
+                ComponentView::Text(text) => text.clone(),
+                other => panic!("expected text, got {:?}", other),
+            }
+        };
+        assert_eq!(text(&runtime), "user 1");
+
+        router.navigate("/app/users/2").unwrap();
+        assert!(runtime.process().unwrap());
+        assert_eq!(text(&runtime), "user 2");
+
+        router.navigate("/app/about").unwrap();
+        runtime.process().unwrap();
+        assert_eq!(text(&runtime), "about");
+    }
+
+    #[test]
+    fn test_render_route_fills_outlets() {
+        let mut router = Router::new();
+        router
+            .nest("/app", "Shell", |app| {
+                app.nest("/settings", "SettingsLayout", |settings| {
+                    settings.add_route("/profile", "Profile")
+                })
+            })
+            .unwrap();
+        let route = router.find_route("/app/settings/profile").unwrap();
+
+        let rendered = render_route(&route, |component| match component {
+            "Shell" => view("main", vec![view("nav", vec![]), Outlet()]),
+            "SettingsLayout" => view("section", vec![Outlet()]),
+            _ => view("form", vec![]),
+        });
+
+        let section = element(&element(&rendered).children[1]);
+        assert_eq!(element(&rendered).tag, "main");
+        assert_eq!(section.tag, "section");
+        assert_eq!(element(&section.children[0]).tag, "form");
+    }
+}
@@ -0,0 +1,270 @@
+//! Route path patterns
+//!
+//! | Pattern          | Matches                         | Params               |
+//! |------------------|---------------------------------|----------------------|
+//! | `/users/:id`     | `/users/42`                     | `id = 42`            |
+//! | `/users/:id(\d+)`| `/users/42`, not `/users/me`    | `id = 42`            |
+//! | `/:lang?/about`  | `/about`, `/en/about`           | `lang = en` if given |
+//! | `/files/*rest`   | `/files/a/b.txt`                | `rest = a/b.txt`     |
+
+use crate::{FerrumError, Result};
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// A parsed route pattern
+#[derive(Debug, Clone)]
+pub struct RoutePattern {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Segment {
+    Static(String),
+    Param {
+        name: String,
+        constraint: Option<Regex>,
+        optional: bool,
+    },
+    CatchAll(String),
+}
+
+impl Segment {
+    /// How strongly the segment pins down a path, highest first
+    fn rank(&self) -> u8 {
+        match self {
+            Segment::Static(_) => 5,
+            Segment::Param {
+                constraint: Some(_),
+                optional: false,
+                ..
+            } => 4,
+            Segment::Param {
+                optional: false, ..
+            } => 3,
+            Segment::Param { .. } => 1,
+            Segment::CatchAll(_) => 0,
+        }
+    }
+}
+
+impl RoutePattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let invalid =
+            |reason: &str| FerrumError::Routing(format!("invalid route '{}': {}", pattern, reason));
+
+        if !pattern.starts_with('/') {
+            return Err(invalid("patterns must start with '/'"));
+        }
+
+        let parts: Vec<&str> = pattern.split('/').filter(|p| !p.is_empty()).collect();
+        let mut segments = Vec::with_capacity(parts.len());
+
+        for (i, part) in parts.iter().enumerate() {
+            let segment = if let Some(name) = part.strip_prefix('*') {
+                if i != parts.len() - 1 {
+                    return Err(invalid("a catch-all must be the last segment"));
+                }
+                Segment::CatchAll(parse_name(name).ok_or_else(|| invalid("missing name"))?)
+            } else if let Some(param) = part.strip_prefix(':') {
+                let (param, optional) = match param.strip_suffix('?') {
+                    Some(param) => (param, true),
+                    None => (param, false),
+                };
+                let (name, constraint) = match param.split_once('(') {
+                    Some((name, rest)) => {
+                        let regex = rest
+                            .strip_suffix(')')
+                            .ok_or_else(|| invalid("unclosed constraint"))?;
+                        let regex = Regex::new(&format!("^(?:{})$", regex))
+                            .map_err(|e| invalid(&e.to_string()))?;
+                        (name, Some(regex))
+                    }
+                    None => (param, None),
+                };
+                Segment::Param {
+                    name: parse_name(name).ok_or_else(|| invalid("missing name"))?,
+                    constraint,
+                    optional,
+                }
+            } else {
+                Segment::Static(part.to_string())
+            };
+            segments.push(segment);
+        }
+
+        Ok(Self {
+            source: pattern.to_string(),
+            segments,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub(super) fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Params extracted from `path`, or `None` if it doesn't match
+    ///
+    /// `path` must not include a query string.
+    pub fn matches(&self, path: &str) -> Option<HashMap<String, String>> {
+        let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+        let mut params = HashMap::new();
+        match_segments(&self.segments, &parts, &mut params).then_some(params)
+    }
+
+    /// Order patterns so the most specific comes first
+    ///
+    /// Segments are compared left to right: static beats constrained param,
+    /// which beats param, then optional param, then catch-all. Where one
+    /// pattern ends, it beats optional and catch-all segments of the other,
+    /// so `/users` wins over `/users/:id?` but loses to `/users/:id`.
+    pub fn cmp_specificity(&self, other: &Self) -> Ordering {
+        const END: u8 = 2;
+        let ranks = |pattern: &Self| {
+            let mut ranks: Vec<u8> = pattern.segments.iter().map(Segment::rank).collect();
+            ranks.push(END);
+            ranks
+        };
+        ranks(other).cmp(&ranks(self))
+    }
+}
+
+fn parse_name(name: &str) -> Option<String> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    valid.then(|| name.to_string())
+}
+
+fn match_segments(
+    segments: &[Segment],
+    parts: &[&str],
+    params: &mut HashMap<String, String>,
+) -> bool {
+    let Some((segment, rest)) = segments.split_first() else {
+        return parts.is_empty();
+    };
+
+    match segment {
+        Segment::Static(value) => {
+            parts.first() == Some(&value.as_str()) && match_segments(rest, &parts[1..], params)
+        }
+        Segment::Param {
+            name,
+            constraint,
+            optional,
+        } => {
+            if let Some(part) = parts.first() {
+                let allowed = constraint.as_ref().is_none_or(|re| re.is_match(part));
+                if allowed && match_segments(rest, &parts[1..], params) {
+                    params.insert(name.clone(), part.to_string());
+                    return true;
+                }
+            }
+            // An optional param that can't take this part is simply absent
+            *optional && match_segments(rest, parts, params)
+        }
+        Segment::CatchAll(name) => {
+            if parts.is_empty() {
+                return false;
+            }
+            params.insert(name.clone(), parts.join("/"));
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pattern, path, and the params expected if it matches
+    type Case = (
+        &'static str,
+        &'static str,
+        Option<&'static [(&'static str, &'static str)]>,
+    );
+
+    #[test]
+    fn test_matching_table() {
+        #[rustfmt::skip]
+        let cases: &[Case] = &[
+            ("/", "/", Some(&[])),
+            ("/about", "/about", Some(&[])),
+            ("/about", "/about/", Some(&[])),
+            ("/about", "/contact", None),
+            ("/users/:id", "/users/42", Some(&[("id", "42")])),
+            ("/users/:id", "/users", None),
+            ("/users/:id", "/users/42/posts", None),
+            ("/users/:id(\\d+)", "/users/42", Some(&[("id", "42")])),
+            ("/users/:id(\\d+)", "/users/me", None),
+            ("/posts/:year(\\d{4})/:slug", "/posts/2024/hello", Some(&[("year", "2024"), ("slug", "hello")])),
+            ("/:lang?/about", "/about", Some(&[])),
+            ("/:lang?/about", "/en/about", Some(&[("lang", "en")])),
+            ("/:lang(en|fr)?/docs/:page", "/docs/intro", Some(&[("page", "intro")])),
+            ("/:lang(en|fr)?/docs/:page", "/fr/docs/intro", Some(&[("lang", "fr"), ("page", "intro")])),
+            ("/:lang(en|fr)?/docs/:page", "/de/docs/intro", None),
+            ("/files/*rest", "/files/a/b/c.txt", Some(&[("rest", "a/b/c.txt")])),
+            ("/files/*rest", "/files", None),
+            ("/*path", "/anything/at/all", Some(&[("path", "anything/at/all")])),
+        ];
+
+        for (pattern, path, expected) in cases {
+            let params = RoutePattern::parse(pattern).unwrap().matches(path);
+            let expected = expected.map(|pairs| {
+                pairs
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect::<HashMap<_, _>>()
+            });
+            assert_eq!(params, expected, "{} against {}", pattern, path);
+        }
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        for pattern in [
+            "users",
+            "/files/*rest/more",
+            "/users/:",
+            "/users/:id(\\d+",
+            "/x/:id([)",
+        ] {
+            assert!(RoutePattern::parse(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_ranking() {
+        let mut patterns: Vec<_> = [
+            "/*rest",
+            "/users/:id",
+            "/users/:id?",
+            "/users/new",
+            "/users/:id(\\d+)",
+            "/users",
+            "/users/:id/posts",
+        ]
+        .iter()
+        .map(|p| RoutePattern::parse(p).unwrap())
+        .collect();
+        patterns.sort_by(RoutePattern::cmp_specificity);
+
+        let order: Vec<_> = patterns.iter().map(RoutePattern::as_str).collect();
+        assert_eq!(
+            order,
+            [
+                "/users/new",
+                "/users/:id(\\d+)",
+                "/users/:id/posts",
+                "/users/:id",
+                "/users",
+                "/users/:id?",
+                "/*rest",
+            ]
+        );
+    }
+}
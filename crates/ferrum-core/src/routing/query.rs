@@ -0,0 +1,410 @@
+//! Query strings
+//!
+//! [`Query`] keeps every `key=value` pair in order, percent-decoded, so
+//! repeated keys like `?tag=a&tag=b` survive. It converts to and from serde
+//! types: a field becomes one pair, and a sequence field one pair per item.
+
+use crate::{FerrumError, Result};
+use serde::de::value::{Error as DeError, MapDeserializer, SeqDeserializer, StringDeserializer};
+use serde::de::{self, DeserializeOwned, Deserializer, IntoDeserializer, Visitor};
+use serde::{forward_to_deserialize_any, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Decoded `key=value` pairs from a query string
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    pairs: Vec<(String, String)>,
+}
+
+impl Query {
+    /// Parse a query string, with or without its leading `?`
+    pub fn parse(search: &str) -> Self {
+        let pairs = search
+            .trim_start_matches('?')
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (percent_decode(key, true), percent_decode(value, true))
+            })
+            .collect();
+        Self { pairs }
+    }
+
+    /// Encode the fields of `value`, skipping `None`s
+    ///
+    /// Fields are written in key order.
+    pub fn from_serialize<T: Serialize>(value: &T) -> Result<Self> {
+        let Value::Object(fields) = serde_json::to_value(value)? else {
+            return Err(FerrumError::Routing(
+                "query values must serialize to a struct or map".to_string(),
+            ));
+        };
+
+        let mut pairs = Vec::new();
+        for (key, value) in fields {
+            let values = match value {
+                Value::Array(items) => items,
+                value => vec![value],
+            };
+            for value in values {
+                let value = match value {
+                    Value::Null => continue,
+                    Value::String(s) => s,
+                    Value::Bool(b) => b.to_string(),
+                    Value::Number(n) => n.to_string(),
+                    Value::Array(_) | Value::Object(_) => {
+                        return Err(FerrumError::Routing(format!(
+                            "query field '{}' can't hold nested values",
+                            key
+                        )))
+                    }
+                };
+                pairs.push((key.clone(), value));
+            }
+        }
+        Ok(Self { pairs })
+    }
+
+    /// First value for `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Every value for `key`, in order
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.pairs
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// First value of every key
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        for (key, value) in &self.pairs {
+            map.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        map
+    }
+
+    /// Read the query into a serde type
+    ///
+    /// Values are parsed as the field's type asks, so `page: u32` reads
+    /// `?page=2`, and `tag: Vec<String>` collects every `tag`.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        let mut grouped: Vec<(String, Vec<String>)> = Vec::new();
+        for (key, value) in &self.pairs {
+            match grouped.iter_mut().find(|(k, _)| k == key) {
+                Some((_, values)) => values.push(value.clone()),
+                None => grouped.push((key.clone(), vec![value.clone()])),
+            }
+        }
+
+        let map = MapDeserializer::new(grouped.into_iter().map(|(k, v)| (k, Values(v))));
+        T::deserialize(map).map_err(|e: DeError| FerrumError::Routing(e.to_string()))
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.pairs.iter().enumerate() {
+            if i > 0 {
+                f.write_str("&")?;
+            }
+            write!(f, "{}={}", percent_encode(key), percent_encode(value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode `%XX` escapes, and `+` as a space if `plus_as_space`
+///
+/// Malformed escapes are kept as written.
+pub fn percent_decode(input: &str, plus_as_space: bool) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        let escaped = (byte == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escaped {
+            Some(value) => {
+                decoded.push(value);
+                i += 3;
+            }
+            None => {
+                decoded.push(if plus_as_space && byte == b'+' {
+                    b' '
+                } else {
+                    byte
+                });
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Percent-encode everything but unreserved characters
+pub fn percent_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// All the values given for one key
+struct Values(Vec<String>);
+
+impl Values {
+    fn first(self) -> Part {
+        Part(self.0.into_iter().next().unwrap_or_default())
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for Values {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! forward_to_first {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> std::result::Result<V::Value, DeError> {
+                self.first().$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Values {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        if self.0.len() == 1 {
+            self.first().deserialize_any(visitor)
+        } else {
+            self.deserialize_seq(visitor)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        visitor.visit_seq(SeqDeserializer::new(self.0.into_iter().map(Part)))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        self.first().deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_first! {
+        deserialize_bool deserialize_char deserialize_str deserialize_string
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf unit unit_struct tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// One value, parsed on demand as whatever type the visitor wants
+struct Part(String);
+
+impl<'de> IntoDeserializer<'de, DeError> for Part {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! parse_part {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> std::result::Result<V::Value, DeError> {
+                match self.0.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(e) => Err(de::Error::custom(format_args!("'{}': {}", self.0, e))),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Part {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, DeError> {
+        let value: StringDeserializer<DeError> = self.0.into_deserializer();
+        value.deserialize_enum(name, variants, visitor)
+    }
+
+    parse_part! {
+        deserialize_bool => visit_bool,
+        deserialize_char => visit_char,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Sort {
+        Newest,
+        Oldest,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Filters {
+        q: Option<String>,
+        page: Option<u32>,
+        #[serde(default)]
+        tag: Vec<String>,
+        sort: Option<Sort>,
+        archived: Option<bool>,
+    }
+
+    #[test]
+    fn test_decoding_and_repeated_keys() {
+        let query = Query::parse("?q=caf%C3%A9+bar&tag=a&tag=b%26c&empty&bad=%zz");
+
+        assert_eq!(query.get("q"), Some("café bar"));
+        assert_eq!(query.get_all("tag"), ["a", "b&c"]);
+        assert_eq!(query.get("empty"), Some(""));
+        assert_eq!(query.get("bad"), Some("%zz"));
+        assert_eq!(query.to_map()["tag"], "a");
+        assert_eq!(percent_decode("a+b%2Fc", false), "a+b/c");
+    }
+
+    #[test]
+    fn test_typed_round_trip() {
+        let query = Query::parse("q=rust+web&page=3&tag=a&tag=b&sort=oldest");
+        let filters: Filters = query.deserialize().unwrap();
+        assert_eq!(
+            filters,
+            Filters {
+                q: Some("rust web".to_string()),
+                page: Some(3),
+                tag: vec!["a".to_string(), "b".to_string()],
+                sort: Some(Sort::Oldest),
+                archived: None,
+            }
+        );
+
+        let encoded = Query::from_serialize(&filters).unwrap();
+        assert_eq!(
+            encoded.to_string(),
+            "page=3&q=rust%20web&sort=oldest&tag=a&tag=b"
+        );
+        assert_eq!(Query::parse(&encoded.to_string()), encoded);
+
+        let single: Filters = Query::parse("tag=only").deserialize().unwrap();
+        assert_eq!(single.tag, ["only"]);
+
+        let err = Query::parse("page=two")
+            .deserialize::<Filters>()
+            .unwrap_err();
+        assert!(err.to_string().contains("two"));
+    }
+}
@@ -0,0 +1,403 @@
+//! Typed route tables
+//!
+//! [`generate_route_enum`] writes Rust source for an enum with a variant
+//! per route, so paths are built from typed values and a link to a route
+//! that doesn't exist fails to compile. Params constrained to digits, like
+//! `:id(\d+)`, become `u64`, and optional params become `Option`s.
+
+use super::pattern::{RoutePattern, Segment};
+use super::Router;
+use crate::{FerrumError, Result};
+use std::fmt::Write;
+
+/// A route enum written by [`generate_route_enum`]
+pub trait TypedRoute: Sized {
+    /// The path this route is served at
+    fn to_path(&self) -> String;
+    /// The route serving `path`, ignoring any query string
+    fn from_path(path: &str) -> Option<Self>;
+}
+
+impl Router {
+    /// Each route's component and pattern, most specific first
+    ///
+    /// Redirects are left out.
+    pub fn route_table(&self) -> Vec<(String, String)> {
+        self.routes
+            .iter()
+            .filter(|entry| entry.redirect.is_none())
+            .map(|entry| (entry.route.component.clone(), entry.route.path.clone()))
+            .collect()
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+];
+
+struct Variant {
+    name: String,
+    pattern: RoutePattern,
+}
+
+enum FieldKind {
+    Required,
+    Optional,
+    CatchAll,
+}
+
+struct Field<'a> {
+    param: &'a str,
+    ident: String,
+    kind: FieldKind,
+    numeric: bool,
+}
+
+impl Variant {
+    fn fields(&self) -> Result<Vec<Field<'_>>> {
+        self.pattern
+            .segments()
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Static(_) => None,
+                Segment::Param {
+                    name,
+                    constraint,
+                    optional,
+                } => Some((
+                    name,
+                    if *optional {
+                        FieldKind::Optional
+                    } else {
+                        FieldKind::Required
+                    },
+                    constraint
+                        .as_ref()
+                        .is_some_and(|re| matches!(re.as_str(), r"^(?:\d+)$" | r"^(?:[0-9]+)$")),
+                )),
+                Segment::CatchAll(name) => Some((name, FieldKind::CatchAll, false)),
+            })
+            .map(|(param, kind, numeric)| {
+                Ok(Field {
+                    param,
+                    ident: field_ident(param)?,
+                    kind,
+                    numeric,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Field<'_> {
+    fn rust_type(&self) -> &'static str {
+        match (&self.kind, self.numeric) {
+            (FieldKind::Optional, true) => "Option<u64>",
+            (FieldKind::Optional, false) => "Option<String>",
+            (_, true) => "u64",
+            (_, false) => "String",
+        }
+    }
+
+    /// Expression reading the field from `params`, `None`ing out on failure
+    fn parse_expr(&self) -> String {
+        let param = format!("params.get({:?})", self.param);
+        let parse = if self.numeric { ".parse().ok()?" } else { "" };
+        match self.kind {
+            FieldKind::Optional if !self.numeric => format!("{}.map(decode)", param),
+            FieldKind::Optional => format!(
+                "match {} {{ Some(value) => Some(decode(value){}), None => None }}",
+                param, parse
+            ),
+            _ => format!("decode({}?){}", param, parse),
+        }
+    }
+}
+
+fn field_ident(param: &str) -> Result<String> {
+    if param.starts_with(|c: char| c.is_ascii_digit())
+        || matches!(param, "self" | "Self" | "super" | "crate")
+    {
+        return Err(FerrumError::Routing(format!(
+            "param '{}' can't be used as a field name",
+            param
+        )));
+    }
+    Ok(if KEYWORDS.contains(&param) {
+        format!("r#{}", param)
+    } else {
+        param.to_string()
+    })
+}
+
+/// Rust source for an enum `name` with a variant per `(variant, pattern)`
+///
+/// The enum implements [`TypedRoute`]; `from_path` tries the patterns from
+/// the most specific down, like [`Router::find_route`].
+pub fn generate_route_enum(name: &str, routes: &[(String, String)]) -> Result<String> {
+    let mut variants: Vec<Variant> = Vec::with_capacity(routes.len());
+    for (variant, pattern) in routes {
+        let valid = variant.starts_with(|c: char| c.is_ascii_alphabetic())
+            && variant
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(FerrumError::Routing(format!(
+                "'{}' is not a valid variant name",
+                variant
+            )));
+        }
+        if variants.iter().any(|v| &v.name == variant) {
+            return Err(FerrumError::Routing(format!(
+                "two routes would both be named {}",
+                variant
+            )));
+        }
+        variants.push(Variant {
+            name: variant.clone(),
+            pattern: RoutePattern::parse(pattern)?,
+        });
+    }
+    variants.sort_by(|a, b| a.pattern.cmp_specificity(&b.pattern));
+
+    let fields = variants
+        .iter()
+        .map(Variant::fields)
+        .collect::<Result<Vec<_>>>()?;
+    let has_params = fields.iter().any(|fields| !fields.is_empty());
+
+    // Writing to a String can't fail
+    let mut out = String::new();
+    let _ = write_enum(&mut out, name, &variants, &fields, has_params);
+    Ok(out)
+}
+
+fn write_enum(
+    out: &mut String,
+    name: &str,
+    variants: &[Variant],
+    fields: &[Vec<Field>],
+    has_params: bool,
+) -> std::fmt::Result {
+    writeln!(out, "// Generated by `ferrum routes`; do not edit.")?;
+    writeln!(out)?;
+    writeln!(out, "#[derive(Debug, Clone, PartialEq, Eq)]")?;
+    writeln!(out, "pub enum {} {{", name)?;
+    for (variant, fields) in variants.iter().zip(fields) {
+        if fields.is_empty() {
+            writeln!(out, "    {},", variant.name)?;
+        } else {
+            let fields: Vec<_> = fields
+                .iter()
+                .map(|f| format!("{}: {}", f.ident, f.rust_type()))
+                .collect();
+            writeln!(out, "    {} {{ {} }},", variant.name, fields.join(", "))?;
+        }
+    }
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+
+    writeln!(out, "impl ferrum_core::routing::TypedRoute for {} {{", name)?;
+    writeln!(out, "    fn to_path(&self) -> String {{")?;
+    if has_params {
+        writeln!(out, "        use ferrum_core::routing::percent_encode;")?;
+        writeln!(out)?;
+    }
+    writeln!(out, "        let mut path = String::new();")?;
+    writeln!(out, "        match self {{")?;
+    for (variant, fields) in variants.iter().zip(fields) {
+        let bindings: Vec<_> = fields.iter().map(|f| f.ident.as_str()).collect();
+        if variant.pattern.segments().is_empty() {
+            writeln!(out, "            Self::{} => {{}}", variant.name)?;
+            continue;
+        }
+        if bindings.is_empty() {
+            writeln!(out, "            Self::{} => {{", variant.name)?;
+        } else {
+            writeln!(
+                out,
+                "            Self::{} {{ {} }} => {{",
+                variant.name,
+                bindings.join(", ")
+            )?;
+        }
+
+        let mut fields = fields.iter();
+        for segment in variant.pattern.segments() {
+            if let Segment::Static(part) = segment {
+                writeln!(
+                    out,
+                    "                path.push_str({:?});",
+                    format!("/{}", part)
+                )?;
+                continue;
+            }
+            let Some(field) = fields.next() else { break };
+            let value = if field.numeric {
+                format!("&{}.to_string()", field.ident)
+            } else {
+                field.ident.clone()
+            };
+            match field.kind {
+                FieldKind::Required => {
+                    writeln!(out, "                path.push('/');")?;
+                    writeln!(
+                        out,
+                        "                path.push_str(&percent_encode({}));",
+                        value
+                    )?;
+                }
+                FieldKind::Optional => {
+                    writeln!(
+                        out,
+                        "                if let Some({0}) = {0} {{",
+                        field.ident
+                    )?;
+                    writeln!(out, "                    path.push('/');")?;
+                    writeln!(
+                        out,
+                        "                    path.push_str(&percent_encode({}));",
+                        value
+                    )?;
+                    writeln!(out, "                }}")?;
+                }
+                FieldKind::CatchAll => {
+                    writeln!(
+                        out,
+                        "                for part in {}.split('/') {{",
+                        field.ident
+                    )?;
+                    writeln!(out, "                    path.push('/');")?;
+                    writeln!(
+                        out,
+                        "                    path.push_str(&percent_encode(part));"
+                    )?;
+                    writeln!(out, "                }}")?;
+                }
+            }
+        }
+        writeln!(out, "            }}")?;
+    }
+    writeln!(out, "        }}")?;
+    writeln!(out, "        if path.is_empty() {{")?;
+    writeln!(out, "            path.push('/');")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "        path")?;
+    writeln!(out, "    }}")?;
+    writeln!(out)?;
+
+    writeln!(out, "    fn from_path(path: &str) -> Option<Self> {{")?;
+    if has_params {
+        writeln!(
+            out,
+            "        use ferrum_core::routing::{{percent_decode, RoutePattern}};"
+        )?;
+    } else {
+        writeln!(out, "        use ferrum_core::routing::RoutePattern;")?;
+    }
+    writeln!(out)?;
+    writeln!(
+        out,
+        "        static PATTERNS: std::sync::OnceLock<Vec<RoutePattern>> = std::sync::OnceLock::new();"
+    )?;
+    writeln!(out, "        let patterns = PATTERNS.get_or_init(|| {{")?;
+    writeln!(out, "            [")?;
+    for variant in variants {
+        writeln!(out, "                {:?},", variant.pattern.as_str())?;
+    }
+    writeln!(out, "            ]")?;
+    writeln!(out, "            .iter()")?;
+    writeln!(
+        out,
+        "            .map(|pattern| RoutePattern::parse(pattern).expect(\"generated from a valid pattern\"))"
+    )?;
+    writeln!(out, "            .collect()")?;
+    writeln!(out, "        }});")?;
+    writeln!(
+        out,
+        "        let path = path.split(['?', '#']).next().unwrap_or_default();"
+    )?;
+    if has_params {
+        writeln!(
+            out,
+            "        let decode = |value: &String| percent_decode(value, false);"
+        )?;
+    }
+    writeln!(out)?;
+
+    for (i, (variant, fields)) in variants.iter().zip(fields).enumerate() {
+        if fields.is_empty() {
+            writeln!(out, "        if patterns[{}].matches(path).is_some() {{", i)?;
+            writeln!(out, "            return Some(Self::{});", variant.name)?;
+            writeln!(out, "        }}")?;
+            continue;
+        }
+        writeln!(
+            out,
+            "        if let Some(route) = patterns[{}].matches(path).and_then(|params| {{",
+            i
+        )?;
+        writeln!(out, "            Some(Self::{} {{", variant.name)?;
+        for field in fields {
+            writeln!(
+                out,
+                "                {}: {},",
+                field.ident,
+                field.parse_expr()
+            )?;
+        }
+        writeln!(out, "            }})")?;
+        writeln!(out, "        }}) {{")?;
+        writeln!(out, "            return Some(route);")?;
+        writeln!(out, "        }}")?;
+    }
+    writeln!(out, "        None")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(routes: &[(&str, &str)]) -> Vec<(String, String)> {
+        routes
+            .iter()
+            .map(|(v, p)| (v.to_string(), p.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_generated_enum_shape() {
+        let mut router = Router::new();
+        router.add_route("/", "Home").unwrap();
+        router.add_route("/users/:id(\\d+)", "UserProfile").unwrap();
+        router.add_route("/:lang?/docs/*rest", "Docs").unwrap();
+        router.redirect("/u/:id", "/users/:id").unwrap();
+
+        let code = generate_route_enum("AppRoute", &router.route_table()).unwrap();
+        assert!(code.contains("pub enum AppRoute {"));
+        assert!(code.contains("    Home,\n"));
+        assert!(code.contains("    UserProfile { id: u64 },\n"));
+        assert!(code.contains("    Docs { lang: Option<String>, rest: String },\n"));
+        assert!(code.contains("id: decode(params.get(\"id\")?).parse().ok()?,"));
+        assert!(!code.contains("\"/u/:id\""));
+    }
+
+    #[test]
+    fn test_invalid_tables() {
+        for routes in [
+            table(&[("Home", "/"), ("Home", "/home")]),
+            table(&[("not valid", "/")]),
+            table(&[("Item", "/items/:1st")]),
+            table(&[("Item", "items")]),
+        ] {
+            assert!(generate_route_enum("AppRoute", &routes).is_err());
+        }
+        let code = generate_route_enum("AppRoute", &table(&[("Item", "/items/:type")])).unwrap();
+        assert!(code.contains("Item { r#type: String }"));
+    }
+}
@@ -1,25 +1,63 @@
-use serde::{Deserialize, Serialize};
+use crate::{FerrumError, Result};
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+mod action;
+mod history;
+mod list;
+mod persist;
+mod resource;
+pub(crate) mod runtime;
+mod task;
+
+pub use action::{AsyncAction, DispatchPolicy};
+pub use history::{Change, History, Timeline, Transaction};
+pub use list::{SignalVec, VecDiff};
+pub use persist::{FileAdapter, MemoryAdapter, PersistAdapter, Persistence, HYDRATION_ELEMENT_ID};
+pub use resource::{
+    create_resource, create_resource_with, Resource, ResourceCache, ResourceOptions, RetryPolicy,
+};
+pub use runtime::{batch, untrack};
+use runtime::{EffectNode, Notify, Observer, Source};
+
+/// Callback invoked with a signal's new value
+pub type Callback<T> = Box<dyn Fn(&T) + Send + Sync>;
 
 /// State management system for Ferrum applications
 pub trait State: Send + Sync {
     type Value: Clone + Send + Sync;
 
-    fn get(&self) -> Self::Value;
-    fn set(&mut self, value: Self::Value);
-    fn subscribe(&mut self, callback: Box<dyn Fn(Self::Value) + Send + Sync>);
+    fn try_get(&self) -> Result<Self::Value>;
+    fn try_set(&self, value: Self::Value) -> Result<()>;
+    fn subscribe(&self, callback: Callback<Self::Value>) -> Subscription;
+}
+
+/// The only way a state lock fails: an `update` closure panicked while
+/// holding it, possibly leaving the value half-written
+fn poisoned() -> FerrumError {
+    FerrumError::State("state lock poisoned by a panicking update".to_string())
+}
+
+fn read<T>(lock: &RwLock<T>) -> Result<RwLockReadGuard<'_, T>> {
+    lock.read().map_err(|_| poisoned())
+}
+
+fn write<T>(lock: &RwLock<T>) -> Result<RwLockWriteGuard<'_, T>> {
+    lock.write().map_err(|_| poisoned())
 }
 
 /// Signal-based state management (inspired by Leptos but simplified)
+///
+/// Reading a signal inside [`create_memo`] or [`create_effect`] subscribes
+/// the memo or effect to it.
+///
+/// `try_*` methods report a poisoned lock as [`FerrumError::State`]; the
+/// plain methods panic with the same message instead.
 #[derive(Clone)]
 pub struct Signal<T> {
-    inner: Arc<RwLock<SignalInner<T>>>,
-}
-
-struct SignalInner<T> {
-    value: T,
-    subscribers: Vec<Box<dyn Fn(&T) + Send + Sync>>,
+    inner: Arc<RwLock<T>>,
+    source: Arc<Source>,
 }
 
 impl<T> Signal<T>
@@ -27,97 +65,177 @@ where
     T: Clone + Send + Sync + 'static,
 {
     pub fn new(initial_value: T) -> Self {
-        let inner = SignalInner {
-            value: initial_value,
-            subscribers: Vec::new(),
-        };
-
         Self {
-            inner: Arc::new(RwLock::new(inner)),
+            inner: Arc::new(RwLock::new(initial_value)),
+            source: Source::new(),
         }
     }
 
+    /// Whether both are handles to the same signal
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
     pub fn get(&self) -> T {
-        let inner = self.inner.read().map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::Other, "State lock poisoned")
-        })?;
-        inner.value.clone()
+        self.try_get().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get(&self) -> Result<T> {
+        self.try_with(T::clone)
+    }
+
+    /// Read the value without subscribing the running memo or effect
+    pub fn get_untracked(&self) -> T {
+        untrack(|| self.get())
+    }
+
+    /// Borrow the value without cloning it
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        self.try_with(f).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_with<R>(&self, f: impl FnOnce(&T) -> R) -> Result<R> {
+        self.source.track();
+        let value = read(&self.inner)?;
+        Ok(f(&value))
     }
 
     pub fn set(&self, value: T) {
-        let mut inner = self.inner.write().unwrap();
-        inner.value = value.clone();
+        self.try_set(value).unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        // Notify all subscribers
-        for callback in &inner.subscribers {
-            callback(&inner.value);
-        }
+    pub fn try_set(&self, value: T) -> Result<()> {
+        self.try_update(|current| *current = value)
     }
 
-    pub fn subscribe<F>(&self, callback: F)
+    /// Modify the value in place, then notify like [`Signal::set`]
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.try_update(f).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_update<R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R> {
+        let result = {
+            let mut value = write(&self.inner)?;
+            f(&mut value)
+        };
+
+        // Subscribers may read or write this signal, so they only run once
+        // the lock is released
+        self.source.trigger();
+        Ok(result)
+    }
+
+    /// Call `callback` with the new value after every change
+    ///
+    /// Inside [`batch`] the callback runs once, after the batch. Dropping
+    /// the returned [`Subscription`] unsubscribes it.
+    pub fn subscribe<F>(&self, callback: F) -> Subscription
     where
         F: Fn(&T) + Send + Sync + 'static,
     {
-        let mut inner = self.inner.write().unwrap();
-        inner.subscribers.push(Box::new(callback));
+        let node: Arc<dyn Observer> = Arc::new(SubscriberNode {
+            id: runtime::next_id(),
+            signal: self.clone(),
+            callback: Box::new(callback),
+            queued: AtomicBool::new(false),
+        });
+        self.source.subscribe(&node);
+
+        Subscription {
+            source: self.source.clone(),
+            node,
+        }
     }
 }
 
-/// State store for managing application-wide state
-pub struct Store {
-    signals: HashMap<String, Box<dyn std::any::Any + Send + Sync>>,
+/// Runs a [`Signal::subscribe`] callback when the signal changes
+struct SubscriberNode<T> {
+    id: usize,
+    signal: Signal<T>,
+    callback: Callback<T>,
+    queued: AtomicBool,
 }
 
-impl Store {
-pub fn new() -> Self {
-        Self {
-            signals: HashMap::new(),
-        }
+impl<T> Observer for SubscriberNode<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    fn id(&self) -> usize {
+        self.id
     }
 
-    pub fn signal<T>(&mut self, key: &str, initial_value: T) -> Signal<T>
-    where
-        T: Clone + Send + Sync + 'static,
-    {
-        let signal = Signal::new(initial_value);
+    fn add_source(&self, _source: Arc<Source>) {
+        // Subscribers only ever follow their own signal
+    }
 
-        // Store the signal for later access
-        let signal_clone = signal.clone();
-        self.signals.insert(key.to_string(), Box::new(signal_clone));
+    fn notify(&self) -> Notify {
+        if self.queued.swap(true, Ordering::SeqCst) {
+            Notify::Ignore
+        } else {
+            Notify::Schedule
+        }
+    }
 
-        signal
+    fn run(self: Arc<Self>) {
+        self.queued.store(false, Ordering::SeqCst);
+        match self.signal.try_with(T::clone) {
+            Ok(value) => (self.callback)(&value),
+            Err(e) => log::error!("Skipping subscriber: {}", e),
+        }
     }
 
-    pub fn get(&self) -> Arc<RwLock<T>> {
-        let inner = self.inner.read().map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::Other, "State lock poisoned")
-        })?;
-        inner.value.clone()
+    fn unqueue(&self) {
+        self.queued.store(false, Ordering::SeqCst);
     }
+}
 
-    pub fn set(&self, value: T) -> Result<(), Box<dyn std::error::Error>> {
-        let mut inner = self.inner.write().map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::Other, "State lock poisoned")
-        })?;
-        inner.value = value.clone();
+impl<T> State for Signal<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Value = T;
 
-        // Notify all subscribers
-        for callback in &inner.subscribers {
-            callback(&inner.value);
-        }
-        Ok(())
+    fn try_get(&self) -> Result<T> {
+        Signal::try_get(self)
     }
 
-    pub fn subscribe<F>(&self, callback: F) -> Result<(), Box<dyn std::error::Error>>
-    where
-        F: Fn(&T) + Send + Sync + 'static,
-    {
-        let mut inner = self.inner.write().map_err(|_| {
-            std::io::Error::new(std::io::ErrorKind::Other, "State lock poisoned")
-        })?;
-        inner.subscribers.push(Box::new(callback));
-        Ok(())
+    fn try_set(&self, value: T) -> Result<()> {
+        Signal::try_set(self, value)
     }
+
+    fn subscribe(&self, callback: Callback<T>) -> Subscription {
+        Signal::subscribe(self, callback)
+    }
+}
+
+/// Keeps a [`Signal::subscribe`] callback registered until dropped
+#[must_use = "dropping a Subscription unsubscribes its callback"]
+pub struct Subscription {
+    source: Arc<Source>,
+    node: Arc<dyn Observer>,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.source.unsubscribe(self.node.id());
+    }
+}
+
+/// State store for managing application-wide state
+#[derive(Default)]
+pub struct Store {
+    signals: HashMap<String, StoreEntry>,
+}
+
+struct StoreEntry {
+    signal: Box<dyn std::any::Any + Send + Sync>,
+    /// Present for signals added with [`Store::register`]
+    codec: Option<Arc<dyn persist::SignalCodec>>,
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Self::default()
     }
 
     pub fn signal<T>(&mut self, key: &str, initial_value: T) -> Signal<T>
@@ -127,8 +245,13 @@ pub fn new() -> Self {
         let signal = Signal::new(initial_value);
 
         // Store the signal for later access
-        let signal_clone = signal.clone();
-        self.signals.insert(key.to_string(), Box::new(signal_clone));
+        self.signals.insert(
+            key.to_string(),
+            StoreEntry {
+                signal: Box::new(signal.clone()),
+                codec: None,
+            },
+        );
 
         signal
     }
@@ -137,7 +260,31 @@ pub fn new() -> Self {
     where
         T: Clone + Send + Sync + 'static,
     {
-        self.signals.get(key)?.downcast_ref::<Signal<T>>().cloned()
+        self.try_get_signal(key).ok()
+    }
+
+    /// Like [`Store::get_signal`], but says whether the key is missing or
+    /// holds a different type
+    pub fn try_get_signal<T>(&self, key: &str) -> Result<Signal<T>>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let entry = self
+            .signals
+            .get(key)
+            .ok_or_else(|| FerrumError::State(format!("no signal registered as '{}'", key)))?;
+
+        entry
+            .signal
+            .downcast_ref::<Signal<T>>()
+            .cloned()
+            .ok_or_else(|| {
+                FerrumError::State(format!(
+                    "signal '{}' is not a Signal<{}>",
+                    key,
+                    std::any::type_name::<T>()
+                ))
+            })
     }
 }
 
@@ -150,13 +297,140 @@ where
 }
 
 /// Hook for creating derived signals (computed values)
-pub fn create_memo<F, T>(compute_fn: F) -> Signal<T>
+///
+/// The memo tracks the signals and memos `compute_fn` reads, and recomputes
+/// lazily on the next [`Memo::get`] after one of them changed.
+pub fn create_memo<F, T>(compute_fn: F) -> Memo<T>
 where
     F: Fn() -> T + Send + Sync + 'static,
     T: Clone + Send + Sync + 'static,
 {
-    let initial_value = compute_fn();
-    Signal::new(initial_value)
+    Memo {
+        node: Arc::new(MemoNode {
+            id: runtime::next_id(),
+            source: Source::new(),
+            compute: Box::new(compute_fn),
+            value: RwLock::new(None),
+            stale: AtomicBool::new(true),
+            sources: Mutex::new(Vec::new()),
+        }),
+    }
+}
+
+/// A cached value derived from other signals
+///
+/// Any number of changes to the memo's sources cause at most one recompute,
+/// so a value depending on two memos that share a signal (a diamond) is only
+/// recalculated once per change.
+#[derive(Clone)]
+pub struct Memo<T> {
+    node: Arc<MemoNode<T>>,
+}
+
+struct MemoNode<T> {
+    id: usize,
+    source: Arc<Source>,
+    compute: Box<dyn Fn() -> T + Send + Sync>,
+    value: RwLock<Option<T>>,
+    stale: AtomicBool,
+    sources: Mutex<Vec<Arc<Source>>>,
+}
+
+impl<T> Memo<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    pub fn get(&self) -> T {
+        self.try_get().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get(&self) -> Result<T> {
+        self.try_with(T::clone)
+    }
+
+    /// Borrow the current value without cloning it
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        self.try_with(f).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_with<R>(&self, f: impl FnOnce(&T) -> R) -> Result<R> {
+        self.node.source.track();
+
+        if self.node.stale.swap(false, Ordering::SeqCst) || read(&self.node.value)?.is_none() {
+            runtime::clear_sources(self.node.id, &self.node.sources);
+            let observer: Arc<dyn Observer> = self.node.clone();
+            let value = runtime::with_observer(observer, || (self.node.compute)());
+            *write(&self.node.value)? = Some(value);
+        }
+
+        let value = read(&self.node.value)?;
+        Ok(f(value
+            .as_ref()
+            .expect("memo value is computed before it is read")))
+    }
+}
+
+impl<T> Observer for MemoNode<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn add_source(&self, source: Arc<Source>) {
+        runtime::lock(&self.sources).push(source);
+    }
+
+    fn notify(&self) -> Notify {
+        if self.stale.swap(true, Ordering::SeqCst) {
+            Notify::Ignore
+        } else {
+            Notify::Propagate(self.source.clone())
+        }
+    }
+
+    fn run(self: Arc<Self>) {
+        // Memos are pulled by their readers rather than scheduled
+    }
+
+    fn unqueue(&self) {}
+}
+
+/// Effects live until they are disposed, since nothing else owns them
+static EFFECTS: Mutex<Vec<Arc<EffectNode>>> = Mutex::new(Vec::new());
+
+/// Hook for running side effects that depend on signals
+///
+/// `f` runs immediately, then again whenever a signal or memo it read
+/// changes. Subscriptions are re-recorded on every run.
+pub fn create_effect<F>(f: F) -> Effect
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let node = EffectNode::new(Box::new(f));
+    runtime::lock(&EFFECTS).push(node.clone());
+    node.clone().run();
+
+    Effect { node }
+}
+
+/// Handle to an effect created with [`create_effect`]
+#[derive(Clone)]
+pub struct Effect {
+    node: Arc<EffectNode>,
+}
+
+impl Effect {
+    /// Stop re-running the effect and drop its subscriptions
+    pub fn dispose(&self) {
+        self.node.dispose();
+        runtime::lock(&EFFECTS).retain(|effect| !Arc::ptr_eq(effect, &self.node));
+    }
+
+    pub fn is_disposed(&self) -> bool {
+        self.node.is_disposed()
+    }
 }
 
 /// Action pattern for side effects
@@ -183,54 +457,327 @@ where
     }
 }
 
-/// Resource pattern for async data fetching
-pub struct Resource<T> {
-    data: Signal<Option<T>>,
-    loading: Signal<bool>,
-    error: Signal<Option<String>>,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
 
-impl<T> Resource<T>
-where
-    T: Clone + Send + Sync + 'static,
-{
-    pub fn new() -> Self {
-        Self {
-            data: Signal::new(None),
-            loading: Signal::new(false),
-            error: Signal::new(None),
-        }
+    fn counter() -> Arc<AtomicUsize> {
+        Arc::new(AtomicUsize::new(0))
     }
 
-    pub async fn fetch<F, Fut>(&self, fetcher: F)
-    where
-        F: FnOnce() -> Fut + Send + Sync,
-        Fut: std::future::Future<Output = Result<T, String>> + Send,
-    {
-        self.loading.set(true);
-        self.error.set(None);
+    #[test]
+    fn test_memo_recomputes_lazily() {
+        let count = create_signal(1);
+        let runs = counter();
+
+        let doubled = create_memo({
+            let count = count.clone();
+            let runs = runs.clone();
+            move || {
+                runs.fetch_add(1, Ordering::SeqCst);
+                count.get() * 2
+            }
+        });
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+
+        assert_eq!(doubled.get(), 2);
+        assert_eq!(doubled.get(), 2);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        count.set(2);
+        count.set(3);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(doubled.get(), 6);
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_effect_drops_stale_subscriptions() {
+        let use_a = create_signal(true);
+        let a = create_signal(1);
+        let b = create_signal(10);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let effect = create_effect({
+            let (use_a, a, b, seen) = (use_a.clone(), a.clone(), b.clone(), seen.clone());
+            move || {
+                let value = if use_a.get() { a.get() } else { b.get() };
+                seen.lock().unwrap().push(value);
+            }
+        });
+
+        a.set(2);
+        use_a.set(false);
+        // `a` is no longer read, so it must not re-run the effect
+        a.set(3);
+        b.set(11);
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 10, 11]);
+
+        effect.dispose();
+        b.set(12);
+        assert_eq!(seen.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_subscriber_can_read_and_write_its_signal() {
+        let count = create_signal(0);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let _subscription = count.subscribe({
+            let (count, seen) = (count.clone(), seen.clone());
+            move |value| {
+                seen.lock().unwrap().push(*value);
+                // Would deadlock if subscribers ran under the write lock
+                if count.get() < 3 {
+                    count.set(count.get() + 1);
+                }
+            }
+        });
+
+        count.set(1);
+        assert_eq!(count.get(), 3);
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3]);
+    }
 
-        match fetcher().await {
-            Ok(result) => {
-                self.data.set(Some(result));
-                self.loading.set(false);
+    #[test]
+    fn test_batch_notifies_once() {
+        let first = create_signal("Ada".to_string());
+        let last = create_signal("Lovelace".to_string());
+        let age = create_signal(36);
+
+        let renders = counter();
+        let _effect = create_effect({
+            let (first, last, age, renders) =
+                (first.clone(), last.clone(), age.clone(), renders.clone());
+            move || {
+                let _ = (first.get(), last.get(), age.get());
+                renders.fetch_add(1, Ordering::SeqCst);
             }
-            Err(err) => {
-                self.error.set(Some(err));
-                self.loading.set(false);
+        });
+
+        let notified = counter();
+        let _subscription = age.subscribe({
+            let notified = notified.clone();
+            move |_| {
+                notified.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        batch(|| {
+            first.set("Grace".to_string());
+            last.set("Hopper".to_string());
+            age.set(85);
+            age.set(86);
+            assert_eq!(renders.load(Ordering::SeqCst), 1);
+        });
+
+        assert_eq!(renders.load(Ordering::SeqCst), 2);
+        assert_eq!(notified.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_dropping_subscription_unsubscribes() {
+        let count = create_signal(0);
+        let calls = counter();
+
+        let subscription = count.subscribe({
+            let calls = calls.clone();
+            move |_| {
+                calls.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        count.set(1);
+        drop(subscription);
+        count.set(2);
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(count.source.observer_count(), 0);
+    }
+
+    #[test]
+    fn test_subscribers_see_consistent_memos() {
+        let a = create_signal(1);
+        let doubled = create_memo({
+            let a = a.clone();
+            move || a.get() * 2
+        });
+        let sum = create_memo({
+            let (a, doubled) = (a.clone(), doubled.clone());
+            move || a.get() + doubled.get()
+        });
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let _effect = create_effect({
+            let seen = seen.clone();
+            move || seen.lock().unwrap().push(sum.get())
+        });
+
+        a.set(2);
+        a.set(5);
+        // Never a mix of an old and a new `a`
+        assert_eq!(*seen.lock().unwrap(), vec![3, 6, 15]);
+    }
+
+    #[test]
+    fn test_update_and_with_avoid_cloning() {
+        let tasks = create_signal(vec!["write docs".to_string()]);
+
+        tasks.update(|tasks| tasks.push("ship".to_string()));
+        let len = tasks.with(Vec::len);
+        let first = tasks.with(|tasks| tasks[0].clone());
+
+        assert_eq!(len, 2);
+        assert_eq!(first, "write docs");
+    }
+
+    #[test]
+    fn test_concurrent_writers() {
+        let count = create_signal(0usize);
+        let latest = counter();
+        let _subscription = count.subscribe({
+            let latest = latest.clone();
+            move |value| {
+                latest.fetch_max(*value, Ordering::SeqCst);
             }
+        });
+
+        let writers: Vec<_> = (0..8)
+            .map(|_| {
+                let count = count.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..500 {
+                        count.update(|value| *value += 1);
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
         }
+
+        assert_eq!(count.try_get().unwrap(), 4000);
+        // Notifications from different threads may be merged, but the last
+        // one always sees the final value
+        assert_eq!(latest.load(Ordering::SeqCst), 4000);
+    }
+
+    #[test]
+    fn test_panicking_subscriber_leaves_signal_usable() {
+        let count = create_signal(0);
+        let _bad = count.subscribe(|value| {
+            if *value == 1 {
+                panic!("subscriber failed");
+            }
+        });
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let _good = count.subscribe({
+            let seen = seen.clone();
+            move |value| seen.lock().unwrap().push(*value)
+        });
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| count.set(1)));
+        assert!(result.is_err());
+
+        // Subscribers run outside the lock, so nothing was poisoned
+        assert_eq!(count.try_get().unwrap(), 1);
+        count.try_set(2).unwrap();
+        assert_eq!(seen.lock().unwrap().last(), Some(&2));
     }
 
-    pub fn data(&self) -> Signal<Option<T>> {
-        self.data.clone()
+    #[test]
+    fn test_panicking_effect_leaves_the_rest_schedulable() {
+        let count = create_signal(0);
+        let _bad = create_effect({
+            let count = count.clone();
+            move || {
+                if count.get() == 1 {
+                    panic!("effect failed");
+                }
+            }
+        });
+        let runs = counter();
+        let _good = create_effect({
+            let (count, runs) = (count.clone(), runs.clone());
+            move || {
+                count.get();
+                runs.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| count.set(1)));
+        assert!(result.is_err());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // The effect queued behind the panic is dropped rather than stuck,
+        // so a write on another thread schedules it again
+        std::thread::spawn(move || count.set(2)).join().unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
     }
 
-    pub fn loading(&self) -> Signal<bool> {
-        self.loading.clone()
+    #[test]
+    fn test_panicking_update_poisons_with_state_error() {
+        let count = create_signal(0);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            count.update(|_| panic!("update failed"))
+        }));
+        assert!(result.is_err());
+
+        assert!(matches!(count.try_get(), Err(FerrumError::State(_))));
+        assert!(matches!(count.try_set(1), Err(FerrumError::State(_))));
     }
 
-    pub fn error(&self) -> Signal<Option<String>> {
-        self.error.clone()
+    #[test]
+    fn test_store_reports_missing_and_mistyped_signals() {
+        let mut store = Store::new();
+        store.signal("count", 0i32);
+
+        assert_eq!(store.try_get_signal::<i32>("count").unwrap().get(), 0);
+        assert!(matches!(
+            store.try_get_signal::<i32>("missing"),
+            Err(FerrumError::State(_))
+        ));
+        assert!(matches!(
+            store.try_get_signal::<String>("count"),
+            Err(FerrumError::State(_))
+        ));
+    }
+
+    #[test]
+    fn test_diamond_recomputes_once() {
+        let a = create_signal(1);
+        let left = create_memo({
+            let a = a.clone();
+            move || a.get() + 1
+        });
+        let right = create_memo({
+            let a = a.clone();
+            move || a.get() * 10
+        });
+
+        let sum_runs = counter();
+        let sum = create_memo({
+            let sum_runs = sum_runs.clone();
+            move || {
+                sum_runs.fetch_add(1, Ordering::SeqCst);
+                left.get() + right.get()
+            }
+        });
+
+        let effect_runs = counter();
+        let last = Arc::new(Mutex::new(0));
+        let _effect = create_effect({
+            let (effect_runs, last) = (effect_runs.clone(), last.clone());
+            move || {
+                effect_runs.fetch_add(1, Ordering::SeqCst);
+                *last.lock().unwrap() = sum.get();
+            }
+        });
+
+        a.set(2);
+        assert_eq!(*last.lock().unwrap(), 23);
+        assert_eq!(sum_runs.load(Ordering::SeqCst), 2);
+        assert_eq!(effect_runs.load(Ordering::SeqCst), 2);
     }
 }
@@ -0,0 +1,417 @@
+//! Async actions for event handlers that talk to the backend
+//!
+//! An [`AsyncAction`] runs its handler in the background, on the Tokio
+//! runtime natively and on the page's event loop in the browser, and reports
+//! progress through signals, so a view can disable a button while a request
+//! is pending or show the last error. What happens when it is dispatched
+//! again before finishing is decided by its [`DispatchPolicy`].
+
+use super::runtime::lock;
+use super::task::{self, Task};
+use super::{batch, Signal};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+type ActionResult<O> = std::result::Result<O, String>;
+type Handler<I, O> =
+    Box<dyn Fn(I) -> Pin<Box<dyn Future<Output = ActionResult<O>> + Send>> + Send + Sync>;
+
+/// What to do when an action is dispatched while a previous run is pending
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DispatchPolicy {
+    /// Run dispatches one after another, in order
+    #[default]
+    Queue,
+    /// Ignore dispatches until the pending run finishes
+    DropNew,
+    /// Abort pending runs and start the new one
+    CancelPrevious,
+    /// Run every dispatch at once; an older run never overwrites a newer result
+    Parallel,
+}
+
+/// An async operation with `pending`, `value`, `error` and `version` signals
+pub struct AsyncAction<I, O> {
+    inner: Arc<ActionInner<I, O>>,
+}
+
+impl<I, O> Clone for AsyncAction<I, O> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+struct ActionInner<I, O> {
+    handler: Handler<I, O>,
+    policy: DispatchPolicy,
+    pending: Signal<bool>,
+    value: Signal<Option<O>>,
+    error: Signal<Option<String>>,
+    version: Signal<usize>,
+    next_run: AtomicUsize,
+    /// Newest run whose result has been written
+    latest: AtomicUsize,
+    /// Runs not yet finished; a run's task is filled in once spawned
+    running: Mutex<HashMap<usize, Option<Task>>>,
+    /// Closes when the last queued run finishes, for the next one to wait on
+    tail: Mutex<Option<oneshot::Receiver<()>>>,
+}
+
+impl<I, O> AsyncAction<I, O>
+where
+    I: Send + 'static,
+    O: Clone + Send + Sync + 'static,
+{
+    /// An action that queues overlapping dispatches
+    pub fn new<F, Fut>(handler: F) -> Self
+    where
+        F: Fn(I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ActionResult<O>> + Send + 'static,
+    {
+        Self::with_policy(DispatchPolicy::default(), handler)
+    }
+
+    pub fn with_policy<F, Fut>(policy: DispatchPolicy, handler: F) -> Self
+    where
+        F: Fn(I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ActionResult<O>> + Send + 'static,
+    {
+        Self {
+            inner: Arc::new(ActionInner {
+                handler: Box::new(move |input| Box::pin(handler(input))),
+                policy,
+                pending: Signal::new(false),
+                value: Signal::new(None),
+                error: Signal::new(None),
+                version: Signal::new(0),
+                next_run: AtomicUsize::new(1),
+                latest: AtomicUsize::new(0),
+                running: Mutex::new(HashMap::new()),
+                tail: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Run the handler with `input` in the background
+    ///
+    /// Natively this needs a Tokio runtime; in the browser the page's event
+    /// loop runs it.
+    pub fn dispatch(&self, input: I) {
+        let run = self.inner.next_run.fetch_add(1, Ordering::SeqCst);
+        // The run is reserved under the guard that checks the policy, so two
+        // dispatches can't both find the action idle
+        let cancelled = {
+            let mut running = lock(&self.inner.running);
+            let cancelled = match self.inner.policy {
+                DispatchPolicy::DropNew if !running.is_empty() => {
+                    log::debug!("Dropping dispatch while the action is pending");
+                    return;
+                }
+                DispatchPolicy::CancelPrevious => {
+                    running.drain().filter_map(|(_, task)| task).collect()
+                }
+                _ => Vec::new(),
+            };
+            running.insert(run, None);
+            cancelled
+        };
+        for task in cancelled {
+            task.abort();
+        }
+
+        batch(|| {
+            self.inner.pending.set(true);
+            self.inner.error.set(None);
+        });
+
+        // Each queued run waits for the one dispatched before it; the sender
+        // is dropped when the run ends, even if it is aborted
+        let (previous, done) = match self.inner.policy {
+            DispatchPolicy::Queue => {
+                let (done, next) = oneshot::channel::<()>();
+                (lock(&self.inner.tail).replace(next), Some(done))
+            }
+            _ => (None, None),
+        };
+
+        // `finish` is created out here so even a run aborted before it
+        // starts completes
+        let mut finish = Finish {
+            inner: self.inner.clone(),
+            run,
+        };
+        let task = task::spawn(async move {
+            let _done = done;
+            if let Some(previous) = previous {
+                let _ = previous.await;
+            }
+            let result = (finish.inner.handler)(input).await;
+            finish.complete(result);
+        });
+
+        match task {
+            Ok(task) => match lock(&self.inner.running).get_mut(&run) {
+                Some(slot) => *slot = Some(task),
+                // Already finished, or cancelled by a newer dispatch
+                None => task.abort(),
+            },
+            Err(run_future) => {
+                self.inner.complete(
+                    run,
+                    Err("actions can only run inside a Tokio runtime".to_string()),
+                );
+                drop(run_future);
+            }
+        }
+    }
+
+    /// True while any dispatch is running or queued
+    pub fn pending(&self) -> Signal<bool> {
+        self.inner.pending.clone()
+    }
+
+    /// Result of the most recent successful run
+    pub fn value(&self) -> Signal<Option<O>> {
+        self.inner.value.clone()
+    }
+
+    /// Error from the most recent failed run, cleared by the next dispatch
+    pub fn error(&self) -> Signal<Option<String>> {
+        self.inner.error.clone()
+    }
+
+    /// Number of successful runs; resources use it to know when to refetch
+    pub fn version(&self) -> Signal<usize> {
+        self.inner.version.clone()
+    }
+}
+
+impl<I, O> ActionInner<I, O>
+where
+    O: Clone + Send + Sync + 'static,
+{
+    fn complete(&self, run: usize, result: ActionResult<O>) {
+        let still_pending = {
+            let mut running = lock(&self.running);
+            if running.remove(&run).is_none() {
+                // Cancelled after its handler had already finished
+                return;
+            }
+            !running.is_empty()
+        };
+
+        let newest = self.latest.fetch_max(run, Ordering::SeqCst) < run;
+        batch(|| {
+            if newest {
+                match result {
+                    Ok(value) => {
+                        self.value.set(Some(value));
+                        self.error.set(None);
+                        self.version.update(|version| *version += 1);
+                    }
+                    Err(err) => self.error.set(Some(err)),
+                }
+            }
+            self.pending.set(still_pending);
+        });
+    }
+}
+
+/// Completes a run however its task ends, so a panicking handler doesn't
+/// leave the action pending forever
+struct Finish<I, O>
+where
+    O: Clone + Send + Sync + 'static,
+{
+    inner: Arc<ActionInner<I, O>>,
+    run: usize,
+}
+
+impl<I, O> Finish<I, O>
+where
+    O: Clone + Send + Sync + 'static,
+{
+    fn complete(&mut self, result: ActionResult<O>) {
+        self.inner.complete(self.run, result);
+    }
+}
+
+impl<I, O> Drop for Finish<I, O>
+where
+    O: Clone + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        // Does nothing if the run already completed or was cancelled
+        let error = if std::thread::panicking() {
+            "action handler panicked"
+        } else {
+            "action was aborted"
+        };
+        self.complete(Err(error.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::create_resource;
+    use std::time::Duration;
+
+    async fn settle<I: Send + 'static, O: Clone + Send + Sync + 'static>(
+        action: &AsyncAction<I, O>,
+    ) {
+        for _ in 0..200 {
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            if !action.pending().get() {
+                return;
+            }
+        }
+        panic!("action never finished");
+    }
+
+    fn counting(policy: DispatchPolicy, calls: &Arc<AtomicUsize>) -> AsyncAction<u64, u64> {
+        let calls = calls.clone();
+        AsyncAction::with_policy(policy, move |delay| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                Ok(delay)
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_queue_runs_in_order() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let action = AsyncAction::new({
+            let order = order.clone();
+            move |(id, delay): (u32, u64)| {
+                let order = order.clone();
+                async move {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    order.lock().unwrap().push(id);
+                    Ok(id)
+                }
+            }
+        });
+
+        action.dispatch((1, 20));
+        action.dispatch((2, 1));
+        assert!(action.pending().get());
+        settle(&action).await;
+
+        assert_eq!(*order.lock().unwrap(), vec![1, 2]);
+        assert_eq!(action.value().get(), Some(2));
+        assert_eq!(action.version().get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_drop_new_and_cancel_previous() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let action = counting(DispatchPolicy::DropNew, &calls);
+        action.dispatch(10);
+        action.dispatch(1);
+        settle(&action).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(action.value().get(), Some(10));
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let action = counting(DispatchPolicy::CancelPrevious, &calls);
+        action.dispatch(30);
+        tokio::task::yield_now().await;
+        action.dispatch(1);
+        settle(&action).await;
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(action.value().get(), Some(1));
+        assert_eq!(action.version().get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handler_finishes_the_run() {
+        let action = AsyncAction::with_policy(DispatchPolicy::DropNew, |input: u64| async move {
+            assert_ne!(input, 0, "handler failed");
+            Ok(input)
+        });
+        action.dispatch(0);
+        settle(&action).await;
+        assert_eq!(
+            action.error().get().as_deref(),
+            Some("action handler panicked")
+        );
+
+        // The failed run no longer blocks new dispatches
+        action.dispatch(5);
+        settle(&action).await;
+        assert_eq!(action.value().get(), Some(5));
+        assert_eq!(action.error().get(), None);
+    }
+
+    #[tokio::test]
+    async fn test_drop_new_while_the_first_run_registers() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let action = counting(DispatchPolicy::DropNew, &calls);
+
+        // Dispatches again between the first one's policy check and its
+        // task being spawned
+        let _subscription = action.pending().subscribe({
+            let action = action.clone();
+            move |pending| {
+                if *pending {
+                    action.dispatch(1);
+                }
+            }
+        });
+        action.dispatch(5);
+        settle(&action).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(action.value().get(), Some(5));
+    }
+
+    #[test]
+    fn test_dispatch_outside_a_runtime_reports_it() {
+        let action = AsyncAction::new(|input: u64| async move { Ok(input) });
+        action.dispatch(1);
+        assert!(!action.pending().get());
+        assert!(action.error().get().unwrap().contains("Tokio runtime"));
+    }
+
+    #[tokio::test]
+    async fn test_parallel_keeps_newest_result() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let action = counting(DispatchPolicy::Parallel, &calls);
+        action.dispatch(20);
+        action.dispatch(1);
+        settle(&action).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(action.value().get(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_resource_invalidated_by_action() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let tasks = create_resource(|| (), {
+            let fetches = fetches.clone();
+            move |_| {
+                let fetch = fetches.fetch_add(1, Ordering::SeqCst) + 1;
+                async move { Ok(fetch) }
+            }
+        });
+        let add_task = AsyncAction::new(|title: String| async move { Ok(title) });
+        let tasks = tasks.invalidated_by(&add_task);
+
+        add_task.dispatch("Write docs".to_string());
+        settle(&add_task).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+        assert_eq!(tasks.data().get(), Some(2));
+    }
+}
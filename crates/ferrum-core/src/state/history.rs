@@ -0,0 +1,439 @@
+//! Undo/redo and time travel over a [`Store`]'s registered signals
+//!
+//! Every change to a signal added with [`Store::register`] is recorded as a
+//! `(key, old, new)` [`Change`]. Changes made by the same write or
+//! [`batch`](super::batch) form one [`Transaction`], which is what
+//! [`History::undo`] and [`History::redo`] step over.
+
+use super::persist::SignalCodec;
+use super::runtime::{self, lock};
+use super::{Store, Subscription};
+use crate::{FerrumError, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, Weak};
+
+/// Transactions kept when no limit is given
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// One signal going from `old` to `new`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub key: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// Changes that are undone and redone together
+#[derive(Debug, Clone, Serialize)]
+pub struct Transaction {
+    pub id: usize,
+    pub changes: Vec<Change>,
+}
+
+/// Recorded transactions and how many of them are currently applied
+///
+/// Position `0` is the oldest state still kept; position `n` is the state
+/// after the first `n` transactions.
+#[derive(Debug, Clone, Serialize)]
+pub struct Timeline {
+    pub cursor: usize,
+    pub transactions: Vec<Transaction>,
+}
+
+/// Opt-in change history for a [`Store`]
+///
+/// Only signals registered before the history is created are recorded.
+/// Cloning shares the same timeline.
+#[derive(Clone)]
+pub struct History {
+    inner: Arc<HistoryInner>,
+}
+
+struct HistoryInner {
+    codecs: HashMap<String, Arc<dyn SignalCodec>>,
+    state: Mutex<HistoryState>,
+    _subscriptions: Vec<Subscription>,
+}
+
+struct HistoryState {
+    transactions: VecDeque<Transaction>,
+    cursor: usize,
+    limit: usize,
+    next_id: usize,
+    /// Last seen value of every key, which becomes the next change's `old`
+    values: HashMap<String, Value>,
+    /// Values being written by undo/redo, which must not be recorded again
+    expected: HashMap<String, Value>,
+    /// Flush that the newest transaction came from, while it may still grow
+    open: Option<usize>,
+    /// Every value at each checkpoint made inside the batch not yet
+    /// recorded, where its changes split into transactions
+    checkpoints: Vec<HashMap<String, Value>>,
+}
+
+/// Groups the changes made before a checkpoint; never a flush id
+const CHECKPOINT: usize = usize::MAX;
+
+impl History {
+    /// Start recording changes to `store`
+    pub fn new(store: &Store) -> Result<Self> {
+        let codecs: HashMap<_, _> = store.codecs().into_iter().collect();
+        let mut values = HashMap::new();
+        for (key, codec) in &codecs {
+            values.insert(key.clone(), runtime::untrack(|| codec.snapshot())?);
+        }
+
+        let inner = Arc::new_cyclic(|weak: &Weak<HistoryInner>| {
+            let subscriptions = codecs
+                .iter()
+                .map(|(key, codec)| {
+                    let key = key.clone();
+                    let weak = weak.clone();
+                    codec.subscribe(Box::new(move |value| {
+                        if let Some(inner) = weak.upgrade() {
+                            lock(&inner.state).record(&key, value);
+                        }
+                    }))
+                })
+                .collect();
+
+            HistoryInner {
+                codecs,
+                state: Mutex::new(HistoryState {
+                    transactions: VecDeque::new(),
+                    cursor: 0,
+                    limit: DEFAULT_LIMIT,
+                    next_id: 1,
+                    values,
+                    expected: HashMap::new(),
+                    open: None,
+                    checkpoints: Vec::new(),
+                }),
+                _subscriptions: subscriptions,
+            }
+        });
+
+        Ok(Self { inner })
+    }
+
+    /// Keep at most `limit` transactions, dropping the oldest first
+    pub fn with_limit(self, limit: usize) -> Self {
+        {
+            let mut state = lock(&self.inner.state);
+            state.limit = limit.max(1);
+            state.enforce_limit();
+        }
+        self
+    }
+
+    /// End the current transaction, so the next change starts a new one
+    /// even if it is made in the same batch
+    pub fn checkpoint(&self) {
+        // Changes in a batch are only recorded once it flushes, so keep what
+        // every signal holds now to tell which came before this point
+        let values = runtime::in_batch().then(|| {
+            self.inner
+                .codecs
+                .iter()
+                .filter_map(|(key, codec)| {
+                    Some((key.clone(), runtime::untrack(|| codec.snapshot()).ok()?))
+                })
+                .collect()
+        });
+
+        let mut state = lock(&self.inner.state);
+        state.open = None;
+        state.checkpoints.extend(values);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        lock(&self.inner.state).cursor > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        let state = lock(&self.inner.state);
+        state.cursor < state.transactions.len()
+    }
+
+    /// Revert the last applied transaction; returns false if there is none
+    pub fn undo(&self) -> Result<bool> {
+        let values = {
+            let state = lock(&self.inner.state);
+            let Some(cursor) = state.cursor.checked_sub(1) else {
+                return Ok(false);
+            };
+            state.transactions[cursor]
+                .changes
+                .iter()
+                .rev()
+                .map(|change| (change.key.clone(), change.old.clone()))
+                .collect()
+        };
+
+        self.apply(values)?;
+        lock(&self.inner.state).cursor -= 1;
+        Ok(true)
+    }
+
+    /// Re-apply the last undone transaction; returns false if there is none
+    pub fn redo(&self) -> Result<bool> {
+        let values = {
+            let state = lock(&self.inner.state);
+            let Some(transaction) = state.transactions.get(state.cursor) else {
+                return Ok(false);
+            };
+            transaction
+                .changes
+                .iter()
+                .map(|change| (change.key.clone(), change.new.clone()))
+                .collect()
+        };
+
+        self.apply(values)?;
+        lock(&self.inner.state).cursor += 1;
+        Ok(true)
+    }
+
+    /// Undo or redo until `position` transactions are applied
+    pub fn goto(&self, position: usize) -> Result<()> {
+        let len = lock(&self.inner.state).transactions.len();
+        if position > len {
+            return Err(FerrumError::State(format!(
+                "history position {} is past the end of the timeline ({})",
+                position, len
+            )));
+        }
+
+        while self.position() > position {
+            self.undo()?;
+        }
+        while self.position() < position {
+            self.redo()?;
+        }
+        Ok(())
+    }
+
+    /// Number of transactions currently applied
+    pub fn position(&self) -> usize {
+        lock(&self.inner.state).cursor
+    }
+
+    pub fn timeline(&self) -> Timeline {
+        let state = lock(&self.inner.state);
+        Timeline {
+            cursor: state.cursor,
+            transactions: state.transactions.iter().cloned().collect(),
+        }
+    }
+
+    /// Write `values` in one batch without recording them
+    fn apply(&self, values: Vec<(String, Value)>) -> Result<()> {
+        {
+            let mut state = lock(&self.inner.state);
+            state.open = None;
+            for (key, value) in &values {
+                state.expected.insert(key.clone(), value.clone());
+            }
+        }
+
+        let result = super::batch(|| {
+            for (key, value) in values {
+                let codec = self.inner.codecs.get(&key).ok_or_else(|| {
+                    FerrumError::State(format!("signal '{}' is not tracked by history", key))
+                })?;
+                codec.restore(value)?;
+            }
+            Ok(())
+        });
+
+        if result.is_err() {
+            lock(&self.inner.state).expected.clear();
+        }
+        result
+    }
+}
+
+impl std::fmt::Debug for History {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = lock(&self.inner.state);
+        f.debug_struct("History")
+            .field("cursor", &state.cursor)
+            .field("transactions", &state.transactions.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl HistoryState {
+    fn record(&mut self, key: &str, new: Value) {
+        for checkpoint in std::mem::take(&mut self.checkpoints) {
+            let mut values: Vec<_> = checkpoint.into_iter().collect();
+            values.sort_by(|a, b| a.0.cmp(&b.0));
+
+            // One transaction per checkpoint, or none if nothing changed
+            self.open = None;
+            for (key, value) in values {
+                self.change(&key, value, CHECKPOINT);
+            }
+            self.open = None;
+        }
+
+        self.change(key, new, runtime::flush_id());
+    }
+
+    /// Record `key` becoming `new`, joining the open transaction if it came
+    /// from the same `group`
+    fn change(&mut self, key: &str, new: Value, group: usize) {
+        let old = self
+            .values
+            .insert(key.to_string(), new.clone())
+            .unwrap_or(Value::Null);
+
+        if self.expected.get(key) == Some(&new) {
+            self.expected.remove(key);
+            return;
+        }
+        if old == new {
+            return;
+        }
+
+        // A new change discards everything that could have been redone
+        if self.cursor < self.transactions.len() {
+            self.transactions.truncate(self.cursor);
+            self.open = None;
+        }
+
+        match self.transactions.back_mut() {
+            Some(transaction) if self.open == Some(group) => {
+                match transaction.changes.iter_mut().find(|c| c.key == key) {
+                    Some(change) => change.new = new,
+                    None => transaction.changes.push(Change {
+                        key: key.to_string(),
+                        old,
+                        new,
+                    }),
+                }
+            }
+            _ => {
+                self.transactions.push_back(Transaction {
+                    id: self.next_id,
+                    changes: vec![Change {
+                        key: key.to_string(),
+                        old,
+                        new,
+                    }],
+                });
+                self.next_id += 1;
+                self.open = Some(group);
+                self.enforce_limit();
+            }
+        }
+        self.cursor = self.transactions.len();
+    }
+
+    fn enforce_limit(&mut self) {
+        while self.transactions.len() > self.limit {
+            self.transactions.pop_front();
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{batch, Signal};
+
+    fn store() -> (Store, Signal<String>, Signal<u32>) {
+        let mut store = Store::new();
+        let title = store.register("title", String::new());
+        let count = store.register("count", 0);
+        (store, title, count)
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let (store, title, count) = store();
+        let history = History::new(&store).unwrap();
+
+        title.set("Draft".to_string());
+        count.set(1);
+        assert_eq!(history.timeline().transactions.len(), 2);
+
+        assert!(history.undo().unwrap());
+        assert_eq!(count.get(), 0);
+        assert_eq!(title.get(), "Draft");
+        assert!(history.undo().unwrap());
+        assert_eq!(title.get(), "");
+        assert!(!history.undo().unwrap());
+
+        assert!(history.redo().unwrap());
+        assert_eq!(title.get(), "Draft");
+        assert_eq!(history.position(), 1);
+
+        // Undo and redo themselves are never recorded
+        assert_eq!(history.timeline().transactions.len(), 2);
+
+        // A new change drops the redo stack
+        count.set(5);
+        assert!(!history.can_redo());
+        assert_eq!(history.timeline().transactions.len(), 2);
+    }
+
+    #[test]
+    fn test_batch_is_one_transaction() {
+        let (store, title, count) = store();
+        let history = History::new(&store).unwrap();
+
+        batch(|| {
+            title.set("Saved".to_string());
+            count.set(3);
+        });
+        let timeline = history.timeline();
+        assert_eq!(timeline.transactions.len(), 1);
+        assert_eq!(timeline.transactions[0].changes.len(), 2);
+
+        history.undo().unwrap();
+        assert_eq!((title.get(), count.get()), (String::new(), 0));
+    }
+
+    #[test]
+    fn test_checkpoint_splits_a_batch() {
+        let (store, title, count) = store();
+        let history = History::new(&store).unwrap();
+
+        batch(|| {
+            title.set("Draft".to_string());
+            count.set(1);
+            history.checkpoint();
+            count.set(2);
+        });
+        assert_eq!(history.timeline().transactions.len(), 2);
+
+        history.undo().unwrap();
+        assert_eq!((title.get(), count.get()), ("Draft".to_string(), 1));
+        history.undo().unwrap();
+        assert_eq!((title.get(), count.get()), (String::new(), 0));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn test_limit_and_goto() {
+        let (store, _, count) = store();
+        let history = History::new(&store).unwrap().with_limit(3);
+
+        for i in 1..=5 {
+            count.set(i);
+        }
+        let timeline = history.timeline();
+        assert_eq!(timeline.transactions.len(), 3);
+        assert_eq!(timeline.transactions[0].changes[0].old, 2);
+
+        history.goto(0).unwrap();
+        assert_eq!(count.get(), 2);
+        history.goto(2).unwrap();
+        assert_eq!(count.get(), 4);
+        assert!(history.goto(4).is_err());
+    }
+}
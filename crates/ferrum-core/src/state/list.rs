@@ -0,0 +1,466 @@
+//! Keyed list signals that report what changed
+//!
+//! Replacing a whole `Signal<Vec<T>>` tells subscribers nothing about which
+//! item changed, so a list view has to rebuild every row. A [`SignalVec`]
+//! records each edit as a [`VecDiff`] instead, and views apply those to their
+//! rendered rows with [`VecDiff::apply`]; a [`For`](crate::component::For)
+//! does so for the rows of a component tree.
+
+use super::runtime::{self, Notify, Observer, Source};
+use super::{read, write, Subscription};
+use crate::{FerrumError, Result};
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+
+/// One edit to a [`SignalVec`]
+///
+/// Indices refer to the list as it was just before the edit, so diffs must
+/// be applied in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VecDiff<T> {
+    Insert {
+        index: usize,
+        item: T,
+    },
+    Update {
+        index: usize,
+        item: T,
+    },
+    Remove {
+        index: usize,
+    },
+    Move {
+        from: usize,
+        to: usize,
+    },
+    /// The whole list was replaced
+    Replace {
+        items: Vec<T>,
+    },
+}
+
+impl<T> VecDiff<T> {
+    /// Apply the edit to rows rendered from the list
+    ///
+    /// `render` is only called for inserted, updated or replaced items, so
+    /// untouched rows are kept as they are.
+    pub fn apply<R>(&self, rows: &mut Vec<R>, mut render: impl FnMut(&T) -> R) {
+        match self {
+            VecDiff::Insert { index, item } => rows.insert(*index, render(item)),
+            VecDiff::Update { index, item } => rows[*index] = render(item),
+            VecDiff::Remove { index } => {
+                rows.remove(*index);
+            }
+            VecDiff::Move { from, to } => {
+                let row = rows.remove(*from);
+                rows.insert(*to, row);
+            }
+            VecDiff::Replace { items } => *rows = items.iter().map(render).collect(),
+        }
+    }
+}
+
+type KeyFn<T, K> = Arc<dyn Fn(&T) -> K + Send + Sync>;
+type DiffCallback<T> = Box<dyn Fn(&[VecDiff<T>]) + Send + Sync>;
+
+/// A list signal whose items are identified by a key
+///
+/// Reading it inside a memo or effect subscribes to the whole list, like a
+/// [`Signal`](super::Signal); [`SignalVec::subscribe_diff`] receives the
+/// individual edits. Keys must be unique.
+pub struct SignalVec<T, K> {
+    items: Arc<RwLock<Vec<T>>>,
+    key: KeyFn<T, K>,
+    source: Arc<Source>,
+    subscribers: Arc<Mutex<Vec<Weak<DiffSubscriber<T>>>>>,
+}
+
+impl<T, K> Clone for SignalVec<T, K> {
+    fn clone(&self) -> Self {
+        Self {
+            items: self.items.clone(),
+            key: self.key.clone(),
+            source: self.source.clone(),
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+impl<T, K> SignalVec<T, K>
+where
+    T: Clone + Send + Sync + 'static,
+    K: PartialEq + Debug + 'static,
+{
+    pub fn new(key: impl Fn(&T) -> K + Send + Sync + 'static) -> Self {
+        Self {
+            items: Arc::new(RwLock::new(Vec::new())),
+            key: Arc::new(key),
+            source: Source::new(),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn with<R>(&self, f: impl FnOnce(&[T]) -> R) -> R {
+        self.try_with(f).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_with<R>(&self, f: impl FnOnce(&[T]) -> R) -> Result<R> {
+        self.source.track();
+        let items = read(&self.items)?;
+        Ok(f(&items))
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.with(<[T]>::to_vec)
+    }
+
+    pub fn len(&self) -> usize {
+        self.with(<[T]>::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The key `item` is identified by
+    pub fn key_of(&self, item: &T) -> K {
+        (self.key)(item)
+    }
+
+    /// Whether both handles refer to the same list
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.items, &other.items)
+    }
+
+    pub fn get_by_key(&self, key: &K) -> Option<T> {
+        self.with(|items| items.iter().find(|item| (self.key)(item) == *key).cloned())
+    }
+
+    pub fn push(&self, item: T) -> Result<()> {
+        self.insert(usize::MAX, item)
+    }
+
+    /// Insert `item` at `index`, or at the end if `index` is past it
+    pub fn insert(&self, index: usize, item: T) -> Result<()> {
+        self.edit(|items, key| {
+            let new_key = key(&item);
+            if items.iter().any(|existing| key(existing) == new_key) {
+                return Err(FerrumError::State(format!(
+                    "list already has an item with key {:?}",
+                    new_key
+                )));
+            }
+            let index = index.min(items.len());
+            items.insert(index, item.clone());
+            Ok((Some(VecDiff::Insert { index, item }), ()))
+        })
+    }
+
+    /// Remove the item with `key`, returning it if there was one
+    pub fn remove_by_key(&self, key: &K) -> Result<Option<T>> {
+        self.edit(|items, key_of| {
+            let Some(index) = items.iter().position(|item| key_of(item) == *key) else {
+                return Ok((None, None));
+            };
+            let item = items.remove(index);
+            Ok((Some(VecDiff::Remove { index }), Some(item)))
+        })
+    }
+
+    /// Modify the item with `key` in place; returns false if there is none
+    ///
+    /// `f` must not change the item's key.
+    pub fn update_item(&self, key: &K, f: impl FnOnce(&mut T)) -> Result<bool> {
+        self.edit(|items, key_of| {
+            let Some(index) = items.iter().position(|item| key_of(item) == *key) else {
+                return Ok((None, false));
+            };
+            f(&mut items[index]);
+            let item = items[index].clone();
+            Ok((Some(VecDiff::Update { index, item }), true))
+        })
+    }
+
+    /// Move the item with `key` to `to`; returns false if there is none
+    pub fn move_item(&self, key: &K, to: usize) -> Result<bool> {
+        self.edit(|items, key_of| {
+            let Some(from) = items.iter().position(|item| key_of(item) == *key) else {
+                return Ok((None, false));
+            };
+            let to = to.min(items.len() - 1);
+            if from == to {
+                return Ok((None, true));
+            }
+            let item = items.remove(from);
+            items.insert(to, item);
+            Ok((Some(VecDiff::Move { from, to }), true))
+        })
+    }
+
+    /// Replace every item
+    pub fn set(&self, new_items: Vec<T>) -> Result<()> {
+        self.edit(|items, key| {
+            for (i, item) in new_items.iter().enumerate() {
+                let item_key = key(item);
+                if new_items[..i].iter().any(|other| key(other) == item_key) {
+                    return Err(FerrumError::State(format!(
+                        "list already has an item with key {:?}",
+                        item_key
+                    )));
+                }
+            }
+            *items = new_items.clone();
+            Ok((Some(VecDiff::Replace { items: new_items }), ()))
+        })
+    }
+
+    /// Call `callback` with the edits made since it last ran
+    ///
+    /// Inside [`batch`](super::batch) the callback runs once with every edit
+    /// from the batch. Subscribing from inside [`with`](Self::with) reports
+    /// exactly the edits made after the items `with` saw. Dropping the
+    /// returned [`Subscription`] unsubscribes it.
+    pub fn subscribe_diff<F>(&self, callback: F) -> Subscription
+    where
+        F: Fn(&[VecDiff<T>]) + Send + Sync + 'static,
+    {
+        let subscriber = Arc::new(DiffSubscriber {
+            id: runtime::next_id(),
+            inbox: Mutex::new(Vec::new()),
+            callback: Box::new(callback),
+            queued: AtomicBool::new(false),
+            running: Mutex::new(()),
+        });
+        runtime::lock(&self.subscribers).push(Arc::downgrade(&subscriber));
+
+        let node: Arc<dyn Observer> = subscriber;
+        self.source.subscribe(&node);
+        Subscription {
+            source: self.source.clone(),
+            node,
+        }
+    }
+
+    /// Run `f` on the items under the write lock, then notify subscribers
+    /// once the lock is released
+    ///
+    /// The diff reaches the inboxes before the lock is released, so they
+    /// hold concurrent edits in the order they were applied.
+    fn edit<R>(
+        &self,
+        f: impl FnOnce(&mut Vec<T>, &dyn Fn(&T) -> K) -> Result<(Option<VecDiff<T>>, R)>,
+    ) -> Result<R> {
+        let (changed, result) = {
+            let mut items = write(&self.items)?;
+            let (diff, result) = f(&mut items, &*self.key)?;
+            if let Some(diff) = &diff {
+                runtime::lock(&self.subscribers).retain(|subscriber| match subscriber.upgrade() {
+                    Some(subscriber) => {
+                        runtime::lock(&subscriber.inbox).push(diff.clone());
+                        true
+                    }
+                    None => false,
+                });
+            }
+            (diff.is_some(), result)
+        };
+
+        if changed {
+            self.source.trigger();
+        }
+        Ok(result)
+    }
+}
+
+/// Runs a [`SignalVec::subscribe_diff`] callback with its pending edits
+struct DiffSubscriber<T> {
+    id: usize,
+    inbox: Mutex<Vec<VecDiff<T>>>,
+    callback: DiffCallback<T>,
+    queued: AtomicBool,
+    /// Held from taking the inbox until the callback returns, so runs on
+    /// different threads hand over their diffs in order
+    running: Mutex<()>,
+}
+
+impl<T> Observer for DiffSubscriber<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn add_source(&self, _source: Arc<Source>) {
+        // Subscribers only ever follow their own list
+    }
+
+    fn notify(&self) -> Notify {
+        if self.queued.swap(true, Ordering::SeqCst) {
+            Notify::Ignore
+        } else {
+            Notify::Schedule
+        }
+    }
+
+    fn run(self: Arc<Self>) {
+        self.queued.store(false, Ordering::SeqCst);
+        let _running = runtime::lock(&self.running);
+        let diffs = std::mem::take(&mut *runtime::lock(&self.inbox));
+        if !diffs.is_empty() {
+            (self.callback)(&diffs);
+        }
+    }
+
+    fn unqueue(&self) {
+        self.queued.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{batch, create_memo};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Task {
+        id: u32,
+        title: String,
+        done: bool,
+    }
+
+    fn task(id: u32, title: &str) -> Task {
+        Task {
+            id,
+            title: title.to_string(),
+            done: false,
+        }
+    }
+
+    fn tasks() -> SignalVec<Task, u32> {
+        let tasks = SignalVec::new(|task: &Task| task.id);
+        tasks.push(task(1, "Write docs")).unwrap();
+        tasks.push(task(2, "Review PR")).unwrap();
+        tasks
+    }
+
+    #[test]
+    fn test_edits_emit_diffs() {
+        let tasks = tasks();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let _subscription = tasks.subscribe_diff({
+            let seen = seen.clone();
+            move |diffs: &[VecDiff<Task>]| seen.lock().unwrap().push(diffs.to_vec())
+        });
+
+        tasks.update_item(&2, |task| task.done = true).unwrap();
+        batch(|| {
+            tasks.push(task(3, "Ship it")).unwrap();
+            tasks.move_item(&3, 0).unwrap();
+            tasks.remove_by_key(&1).unwrap();
+        });
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert!(matches!(seen[0][..], [VecDiff::Update { index: 1, .. }]));
+        assert_eq!(
+            seen[1][1..],
+            [
+                VecDiff::Move { from: 2, to: 0 },
+                VecDiff::Remove { index: 1 }
+            ]
+        );
+        assert_eq!(
+            tasks.to_vec().iter().map(|t| t.id).collect::<Vec<_>>(),
+            [3, 2]
+        );
+        assert!(tasks.push(task(2, "Duplicate")).is_err());
+    }
+
+    #[test]
+    fn test_rows_are_patched_not_rebuilt() {
+        let tasks = tasks();
+        let renders = Arc::new(Mutex::new(0));
+        let render = {
+            let renders = renders.clone();
+            move |task: &Task| {
+                *renders.lock().unwrap() += 1;
+                format!("{} [{}]", task.title, if task.done { "x" } else { " " })
+            }
+        };
+
+        let rows =
+            Arc::new(Mutex::new(tasks.with(|items| {
+                items.iter().map(render.clone()).collect::<Vec<_>>()
+            })));
+        let _subscription = tasks.subscribe_diff({
+            let rows = rows.clone();
+            move |diffs: &[VecDiff<Task>]| {
+                let mut rows = rows.lock().unwrap();
+                for diff in diffs {
+                    diff.apply(&mut rows, render.clone());
+                }
+            }
+        });
+
+        tasks.update_item(&1, |task| task.done = true).unwrap();
+        tasks.move_item(&2, 0).unwrap();
+
+        assert_eq!(*rows.lock().unwrap(), ["Review PR [ ]", "Write docs [x]"]);
+        // Two initial rows plus the one that changed
+        assert_eq!(*renders.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_concurrent_edits_replay_in_order() {
+        let tasks = SignalVec::new(|id: &u32| *id);
+        let replayed = Arc::new(Mutex::new(Vec::new()));
+        let _subscription = tasks.subscribe_diff({
+            let replayed = replayed.clone();
+            move |diffs: &[VecDiff<u32>]| {
+                let mut replayed = replayed.lock().unwrap();
+                for diff in diffs {
+                    diff.apply(&mut replayed, |id| *id);
+                }
+            }
+        });
+
+        let writers: Vec<_> = (0..8)
+            .map(|writer| {
+                let tasks = tasks.clone();
+                std::thread::spawn(move || {
+                    for i in 0..200 {
+                        let id = writer * 1000 + i;
+                        tasks.insert(i as usize % 3, id).unwrap();
+                        if i % 4 == 0 {
+                            tasks.move_item(&id, 0).unwrap();
+                        }
+                        if i % 5 == 0 {
+                            tasks.remove_by_key(&id).unwrap();
+                        }
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        // Every thread flushed its own writes, so nothing is left queued
+        assert_eq!(*replayed.lock().unwrap(), tasks.to_vec());
+    }
+
+    #[test]
+    fn test_reads_are_tracked() {
+        let tasks = tasks();
+        let count = create_memo({
+            let tasks = tasks.clone();
+            move || tasks.len()
+        });
+        assert_eq!(count.get(), 2);
+
+        tasks.remove_by_key(&1).unwrap();
+        assert_eq!(count.get(), 1);
+    }
+}
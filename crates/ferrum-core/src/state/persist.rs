@@ -0,0 +1,368 @@
+//! Store snapshots, hydration and persistence
+//!
+//! Signals added with [`Store::register`] carry a serde codec, so the store
+//! can write them out as one JSON object and read them back. Server-rendered
+//! pages embed that object for the client to hydrate from, and a
+//! [`PersistAdapter`] keeps it somewhere between runs.
+
+use super::{create_effect, Effect, Signal, Store, StoreEntry, Subscription};
+use crate::{FerrumError, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// `id` of the `<script>` element produced by [`Store::hydration_script`]
+pub const HYDRATION_ELEMENT_ID: &str = "ferrum-state";
+
+pub(crate) type PendingWrite<'a> = Box<dyn FnOnce() -> Result<()> + 'a>;
+
+/// Type-erased JSON access to a registered signal
+pub(crate) trait SignalCodec: Send + Sync {
+    fn snapshot(&self) -> Result<Value>;
+    /// Deserialize `value`, returning the write that sets the signal to it
+    fn decode(&self, value: Value) -> Result<PendingWrite<'_>>;
+    fn restore(&self, value: Value) -> Result<()> {
+        self.decode(value)?()
+    }
+    /// Call `callback` with the serialized value after every change
+    fn subscribe(&self, callback: Box<dyn Fn(Value) + Send + Sync>) -> Subscription;
+}
+
+impl<T> SignalCodec for Signal<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    fn snapshot(&self) -> Result<Value> {
+        self.try_with(|value| serde_json::to_value(value))?
+            .map_err(FerrumError::from)
+    }
+
+    fn decode(&self, value: Value) -> Result<PendingWrite<'_>> {
+        let value: T = serde_json::from_value(value)?;
+        Ok(Box::new(move || self.try_set(value)))
+    }
+
+    fn subscribe(&self, callback: Box<dyn Fn(Value) + Send + Sync>) -> Subscription {
+        Signal::subscribe(self, move |value| match serde_json::to_value(value) {
+            Ok(value) => callback(value),
+            Err(e) => log::error!("Failed to serialize signal: {}", e),
+        })
+    }
+}
+
+impl Store {
+    /// Create a signal that is included in snapshots
+    pub fn register<T>(&mut self, key: &str, initial_value: T) -> Signal<T>
+    where
+        T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    {
+        let signal = Signal::new(initial_value);
+        self.signals.insert(
+            key.to_string(),
+            StoreEntry {
+                signal: Box::new(signal.clone()),
+                codec: Some(Arc::new(signal.clone())),
+            },
+        );
+        signal
+    }
+
+    /// Current value of every registered signal, keyed by name
+    pub fn snapshot(&self) -> Result<Value> {
+        snapshot_of(&self.codecs())
+    }
+
+    /// Set registered signals from a [`Store::snapshot`]
+    ///
+    /// Every value is deserialized before any is applied, so a bad one
+    /// leaves the store untouched; then all are set in one
+    /// [`batch`](super::batch). Keys the store doesn't know are skipped, so
+    /// older snapshots keep loading.
+    pub fn restore(&self, snapshot: Value) -> Result<()> {
+        let Value::Object(values) = snapshot else {
+            return Err(FerrumError::State(
+                "store snapshot must be a JSON object".to_string(),
+            ));
+        };
+
+        let mut writes = Vec::new();
+        for (key, value) in values {
+            match self.signals.get(&key) {
+                Some(StoreEntry {
+                    codec: Some(codec), ..
+                }) => writes.push(codec.decode(value)?),
+                Some(_) => {
+                    return Err(FerrumError::State(format!(
+                        "signal '{}' was not registered with a serializer",
+                        key
+                    )))
+                }
+                None => log::warn!("Skipping unknown store key in snapshot: {}", key),
+            }
+        }
+
+        super::batch(|| writes.into_iter().try_for_each(|write| write()))
+    }
+
+    /// `<script>` tag embedding the snapshot in a server-rendered page
+    pub fn hydration_script(&self) -> Result<String> {
+        // `</script>` inside a string value must not end the element early
+        let json = serde_json::to_string(&self.snapshot()?)?.replace("</", "<\\/");
+        Ok(format!(
+            r#"<script type="application/json" id="{}">{}</script>"#,
+            HYDRATION_ELEMENT_ID, json
+        ))
+    }
+
+    /// Restore state from the contents of the hydration `<script>` tag
+    pub fn hydrate(&self, json: &str) -> Result<()> {
+        self.restore(serde_json::from_str(json)?)
+    }
+
+    /// Load state from `adapter`, then save a snapshot after every change
+    ///
+    /// Changes made inside a batch are saved once. Only signals registered
+    /// before this call are persisted; dropping the returned handle stops
+    /// saving.
+    pub fn persist(&self, adapter: Arc<dyn PersistAdapter>) -> Result<Persistence> {
+        if let Some(snapshot) = adapter.load()? {
+            self.restore(snapshot)?;
+        }
+
+        let codecs = self.codecs();
+        let loaded = AtomicBool::new(false);
+        let effect = create_effect(move || {
+            // Reading every signal here is what subscribes the effect to them
+            let snapshot = snapshot_of(&codecs);
+            if !loaded.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            if let Err(e) = snapshot.and_then(|snapshot| adapter.save(&snapshot)) {
+                log::error!("Failed to persist store: {}", e);
+            }
+        });
+
+        Ok(Persistence { effect })
+    }
+
+    pub(super) fn codecs(&self) -> Vec<(String, Arc<dyn SignalCodec>)> {
+        let mut codecs: Vec<_> = self
+            .signals
+            .iter()
+            .filter_map(|(key, entry)| Some((key.clone(), entry.codec.clone()?)))
+            .collect();
+        codecs.sort_by(|a, b| a.0.cmp(&b.0));
+        codecs
+    }
+}
+
+fn snapshot_of(codecs: &[(String, Arc<dyn SignalCodec>)]) -> Result<Value> {
+    let mut values = Map::new();
+    for (key, codec) in codecs {
+        values.insert(key.clone(), codec.snapshot()?);
+    }
+    Ok(Value::Object(values))
+}
+
+/// Keeps a store persisted until dropped
+#[must_use = "dropping Persistence stops saving the store"]
+pub struct Persistence {
+    effect: Effect,
+}
+
+impl Drop for Persistence {
+    fn drop(&mut self) {
+        self.effect.dispose();
+    }
+}
+
+/// Somewhere to keep store snapshots between runs
+pub trait PersistAdapter: Send + Sync {
+    /// The last saved snapshot, if any
+    fn load(&self) -> Result<Option<Value>>;
+    fn save(&self, snapshot: &Value) -> Result<()>;
+}
+
+/// Keeps the snapshot in memory; useful in tests
+#[derive(Debug, Clone, Default)]
+pub struct MemoryAdapter {
+    snapshot: Arc<Mutex<Option<Value>>>,
+}
+
+impl MemoryAdapter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The last saved snapshot
+    pub fn snapshot(&self) -> Option<Value> {
+        super::runtime::lock(&self.snapshot).clone()
+    }
+}
+
+impl PersistAdapter for MemoryAdapter {
+    fn load(&self) -> Result<Option<Value>> {
+        Ok(self.snapshot())
+    }
+
+    fn save(&self, snapshot: &Value) -> Result<()> {
+        *super::runtime::lock(&self.snapshot) = Some(snapshot.clone());
+        Ok(())
+    }
+}
+
+/// Keeps the snapshot in a JSON file, for desktop apps and tooling
+#[derive(Debug, Clone)]
+pub struct FileAdapter {
+    path: PathBuf,
+}
+
+impl FileAdapter {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl PersistAdapter for FileAdapter {
+    fn load(&self) -> Result<Option<Value>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(&self.path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    }
+
+    fn save(&self, snapshot: &Value) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Write then rename, so a crash never leaves a truncated file behind
+        let temp = self.path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_string_pretty(snapshot)?)?;
+        std::fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Filters {
+        tag: String,
+        page: u32,
+    }
+
+    fn store() -> (Store, Signal<i32>, Signal<Filters>) {
+        let mut store = Store::new();
+        let count = store.register("count", 1);
+        let filters = store.register(
+            "filters",
+            Filters {
+                tag: "rust".to_string(),
+                page: 1,
+            },
+        );
+        store.signal("local_only", "not serialized".to_string());
+        (store, count, filters)
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let (store, count, filters) = store();
+        assert_eq!(
+            store.snapshot().unwrap(),
+            json!({ "count": 1, "filters": { "tag": "rust", "page": 1 } })
+        );
+
+        store
+            .restore(json!({ "count": 5, "filters": { "tag": "web", "page": 3 }, "old_key": true }))
+            .unwrap();
+        assert_eq!(count.get(), 5);
+        assert_eq!(filters.get().page, 3);
+
+        assert!(store.restore(json!({ "local_only": "x" })).is_err());
+        assert!(store.restore(json!({ "count": "five" })).is_err());
+
+        // A bad value after a good one leaves both signals as they were
+        assert!(store
+            .restore(json!({ "count": 7, "filters": "bad" }))
+            .is_err());
+        assert_eq!(count.get(), 5);
+    }
+
+    /// Counts saves on top of a [`MemoryAdapter`]
+    #[derive(Default)]
+    struct CountingAdapter {
+        memory: MemoryAdapter,
+        saves: std::sync::atomic::AtomicUsize,
+    }
+
+    impl PersistAdapter for CountingAdapter {
+        fn load(&self) -> Result<Option<Value>> {
+            self.memory.load()
+        }
+
+        fn save(&self, snapshot: &Value) -> Result<()> {
+            self.saves.fetch_add(1, Ordering::SeqCst);
+            self.memory.save(snapshot)
+        }
+    }
+
+    #[test]
+    fn test_hydration_script_round_trip() {
+        let (server, _, filters) = store();
+        filters.update(|filters| filters.tag = "</script>".to_string());
+
+        let script = server.hydration_script().unwrap();
+        assert!(!script.contains("</script><"));
+
+        let json = script
+            .trim_start_matches(r#"<script type="application/json" id="ferrum-state">"#)
+            .trim_end_matches("</script>");
+        let (client, _, client_filters) = store();
+        client.hydrate(json).unwrap();
+        assert_eq!(client_filters.get().tag, "</script>");
+    }
+
+    #[test]
+    fn test_persist_saves_once_per_batch() {
+        let (store, count, filters) = store();
+        let adapter = Arc::new(CountingAdapter::default());
+        adapter.memory.save(&json!({ "count": 10 })).unwrap();
+
+        let _persistence = store.persist(adapter.clone()).unwrap();
+        assert_eq!(count.get(), 10);
+        assert_eq!(adapter.saves.load(Ordering::SeqCst), 0);
+
+        super::super::batch(|| {
+            count.set(11);
+            filters.update(|filters| filters.page = 2);
+        });
+        assert_eq!(adapter.saves.load(Ordering::SeqCst), 1);
+        let saved = adapter.memory.snapshot().unwrap();
+        assert_eq!(saved["count"], 11);
+        assert_eq!(saved["filters"]["page"], 2);
+    }
+
+    #[test]
+    fn test_file_adapter_round_trip() {
+        let path = std::env::temp_dir()
+            .join(format!("ferrum-store-{}", std::process::id()))
+            .join("state.json");
+        let adapter = FileAdapter::new(&path);
+        assert!(adapter.load().unwrap().is_none());
+
+        adapter.save(&json!({ "count": 3 })).unwrap();
+        assert_eq!(adapter.load().unwrap(), Some(json!({ "count": 3 })));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
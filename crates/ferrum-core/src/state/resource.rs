@@ -0,0 +1,718 @@
+//! Async data loading keyed on reactive state
+//!
+//! A keyed [`Resource`] re-runs its fetcher whenever the key read by its
+//! source closure changes. Each fetch gets a version number and only the
+//! newest one may write its result, so a slow response for an old key can
+//! never overwrite the data for the current one.
+
+use super::runtime::lock;
+use super::task::{self, Task};
+use super::{batch, create_effect, untrack, AsyncAction, Effect, Signal, Subscription};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type FetchResult<T> = std::result::Result<T, String>;
+type FetchFuture<T> = Pin<Box<dyn Future<Output = FetchResult<T>> + Send>>;
+type Fetcher<K, T> = Arc<dyn Fn(K) -> FetchFuture<T> + Send + Sync>;
+
+/// Resource pattern for async data fetching
+///
+/// `data` keeps the last loaded value while a new fetch is in flight, so
+/// views can keep showing it and use `loading` to mark it as stale.
+pub struct Resource<T> {
+    state: Arc<ResourceState<T>>,
+    keyed: Option<Arc<Keyed<T>>>,
+}
+
+impl<T> Clone for Resource<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            keyed: self.keyed.clone(),
+        }
+    }
+}
+
+struct ResourceState<T> {
+    data: Signal<Option<T>>,
+    loading: Signal<bool>,
+    error: Signal<Option<String>>,
+    /// Bumped by every fetch; only the newest may write its result
+    version: AtomicUsize,
+    task: Mutex<Option<Task>>,
+}
+
+/// Keeps a keyed resource's effect alive for as long as the resource is
+struct Keyed<T> {
+    loader: Arc<dyn Reload<T>>,
+    effect: Effect,
+    invalidations: Mutex<Vec<Subscription>>,
+}
+
+impl<T> Drop for Keyed<T> {
+    fn drop(&mut self) {
+        self.effect.dispose();
+        self.loader.cancel();
+    }
+}
+
+/// The type-erased half of a keyed resource, which knows its key type
+trait Reload<T>: Send + Sync {
+    fn refetch(self: Arc<Self>);
+    fn mutated(&self, value: &T);
+    fn cancel(&self);
+}
+
+impl<T> Default for Resource<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Resource<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// A resource that only loads when [`Resource::fetch`] is awaited
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(ResourceState {
+                data: Signal::new(None),
+                loading: Signal::new(false),
+                error: Signal::new(None),
+                version: AtomicUsize::new(0),
+                task: Mutex::new(None),
+            }),
+            keyed: None,
+        }
+    }
+
+    /// Run `fetcher` and store its result, unless a newer fetch started
+    /// while it was running
+    pub async fn fetch<F, Fut>(&self, fetcher: F)
+    where
+        F: FnOnce() -> Fut + Send + Sync,
+        Fut: Future<Output = FetchResult<T>> + Send,
+    {
+        let version = self.state.begin();
+        self.state.start_loading();
+        let result = fetcher().await;
+        self.state.finish(version, result);
+    }
+
+    /// Fetch the current key again, even if the cache holds a fresh value
+    ///
+    /// Does nothing for resources created with [`Resource::new`].
+    pub fn refetch(&self) {
+        if let Some(keyed) = &self.keyed {
+            keyed.loader.clone().refetch();
+        }
+    }
+
+    /// Refetch whenever `action` completes successfully
+    ///
+    /// Does nothing for resources created with [`Resource::new`].
+    pub fn invalidated_by<I, O>(self, action: &AsyncAction<I, O>) -> Self
+    where
+        I: Send + 'static,
+        O: Clone + Send + Sync + 'static,
+    {
+        if let Some(keyed) = &self.keyed {
+            let loader = keyed.loader.clone();
+            let subscription = action
+                .version()
+                .subscribe(move |_| loader.clone().refetch());
+            lock(&keyed.invalidations).push(subscription);
+        }
+        self
+    }
+
+    /// Show `value` right away, e.g. before a write reaches the server
+    ///
+    /// Any fetch in flight is cancelled so it can't overwrite the value, and
+    /// the cache entry for the current key is replaced. Call
+    /// [`Resource::refetch`] afterwards to confirm it.
+    pub fn mutate(&self, value: T) {
+        self.state.begin();
+        if let Some(keyed) = &self.keyed {
+            keyed.loader.mutated(&value);
+        }
+        batch(|| {
+            self.state.data.set(Some(value));
+            self.state.error.set(None);
+            self.state.loading.set(false);
+        });
+    }
+
+    pub fn data(&self) -> Signal<Option<T>> {
+        self.state.data.clone()
+    }
+
+    pub fn loading(&self) -> Signal<bool> {
+        self.state.loading.clone()
+    }
+
+    pub fn error(&self) -> Signal<Option<String>> {
+        self.state.error.clone()
+    }
+}
+
+impl<T> ResourceState<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Start a new fetch, cancelling the one in flight
+    fn begin(&self) -> usize {
+        if let Some(task) = lock(&self.task).take() {
+            task.abort();
+        }
+        self.version.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    fn start_loading(&self) {
+        batch(|| {
+            self.loading.set(true);
+            self.error.set(None);
+        });
+    }
+
+    fn finish(&self, version: usize, result: FetchResult<T>) {
+        if self.version.load(Ordering::SeqCst) != version {
+            log::debug!("Dropping stale resource response");
+            return;
+        }
+
+        batch(|| {
+            match result {
+                Ok(value) => self.data.set(Some(value)),
+                Err(err) => self.error.set(Some(err)),
+            }
+            self.loading.set(false);
+        });
+    }
+}
+
+/// Settings for [`create_resource_with`]
+pub struct ResourceOptions<K, T> {
+    /// Share results with other resources using the same cache
+    pub cache: Option<ResourceCache<K, T>>,
+    pub retry: RetryPolicy,
+}
+
+impl<K, T> Default for ResourceOptions<K, T> {
+    fn default() -> Self {
+        Self {
+            cache: None,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// Hook for loading data that depends on signals
+///
+/// `source` is tracked like an effect; whenever the key it returns changes,
+/// `fetcher` runs again in the background and the previous fetch is
+/// cancelled. Natively that needs a Tokio runtime; in the browser the
+/// page's event loop runs it.
+pub fn create_resource<K, T, S, F, Fut>(source: S, fetcher: F) -> Resource<T>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    T: Clone + Send + Sync + 'static,
+    S: Fn() -> K + Send + Sync + 'static,
+    F: Fn(K) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = FetchResult<T>> + Send + 'static,
+{
+    create_resource_with(source, fetcher, ResourceOptions::default())
+}
+
+/// Like [`create_resource`], with caching and retries
+pub fn create_resource_with<K, T, S, F, Fut>(
+    source: S,
+    fetcher: F,
+    options: ResourceOptions<K, T>,
+) -> Resource<T>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    T: Clone + Send + Sync + 'static,
+    S: Fn() -> K + Send + Sync + 'static,
+    F: Fn(K) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = FetchResult<T>> + Send + 'static,
+{
+    let resource = Resource::new();
+    let loader = Arc::new(Loader {
+        state: resource.state.clone(),
+        fetcher: Arc::new(move |key| Box::pin(fetcher(key)) as FetchFuture<T>),
+        cache: options.cache,
+        retry: options.retry,
+        key: Mutex::new(None),
+    });
+
+    let effect = {
+        let loader = loader.clone();
+        create_effect(move || {
+            let key = source();
+            // Invalidating the key re-runs the effect, which then finds the
+            // entry stale and revalidates it
+            if let Some(cache) = &loader.cache {
+                cache.track(&key);
+            }
+            untrack(|| loader.load(key, false));
+        })
+    };
+
+    Resource {
+        keyed: Some(Arc::new(Keyed {
+            loader,
+            effect,
+            invalidations: Mutex::new(Vec::new()),
+        })),
+        ..resource
+    }
+}
+
+struct Loader<K, T> {
+    state: Arc<ResourceState<T>>,
+    fetcher: Fetcher<K, T>,
+    cache: Option<ResourceCache<K, T>>,
+    retry: RetryPolicy,
+    key: Mutex<Option<K>>,
+}
+
+impl<K, T> Loader<K, T>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    T: Clone + Send + Sync + 'static,
+{
+    fn load(self: &Arc<Self>, key: K, force: bool) {
+        *lock(&self.key) = Some(key.clone());
+        let version = self.state.begin();
+
+        match self.cache.as_ref().and_then(|cache| cache.lookup(&key)) {
+            Some((value, fresh)) => {
+                let fresh = fresh && !force;
+                batch(|| {
+                    self.state.data.set(Some(value));
+                    self.state.error.set(None);
+                    self.state.loading.set(!fresh);
+                });
+                if fresh {
+                    return;
+                }
+            }
+            None => self.state.start_loading(),
+        }
+
+        let this = self.clone();
+        let task = task::spawn(async move {
+            let result = this.retry.run(|| (this.fetcher)(key.clone())).await;
+            if let (Ok(value), Some(cache)) = (&result, &this.cache) {
+                cache.insert(key, value.clone());
+            }
+            this.state.finish(version, result);
+        });
+        match task {
+            Ok(task) => *lock(&self.state.task) = Some(task),
+            Err(_) => self.state.finish(
+                version,
+                Err("resources can only fetch inside a Tokio runtime".to_string()),
+            ),
+        }
+    }
+}
+
+impl<K, T> Reload<T> for Loader<K, T>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    T: Clone + Send + Sync + 'static,
+{
+    fn refetch(self: Arc<Self>) {
+        let key = lock(&self.key).clone();
+        if let Some(key) = key {
+            self.load(key, true);
+        }
+    }
+
+    fn mutated(&self, value: &T) {
+        if let (Some(cache), Some(key)) = (&self.cache, lock(&self.key).clone()) {
+            cache.insert(key, value.clone());
+        }
+    }
+
+    fn cancel(&self) {
+        self.state.begin();
+    }
+}
+
+/// Results shared between resources, fresh for a fixed time to live
+///
+/// Stale entries are still shown while they are refetched. Once no
+/// resource shows its key, an expired entry is evicted.
+pub struct ResourceCache<K, T> {
+    entries: Arc<Mutex<HashMap<K, CacheEntry<T>>>>,
+    ttl: Duration,
+    /// Bumped by invalidating a key, so resources showing it revalidate;
+    /// only kept while a resource shows the key
+    generations: Arc<Mutex<HashMap<K, Signal<u64>>>>,
+    /// Bumped by [`ResourceCache::invalidate_all`]
+    generation: Signal<u64>,
+}
+
+struct CacheEntry<T> {
+    value: T,
+    expires: Instant,
+}
+
+impl<K, T> Clone for ResourceCache<K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            ttl: self.ttl,
+            generations: self.generations.clone(),
+            generation: self.generation.clone(),
+        }
+    }
+}
+
+impl<K, T> ResourceCache<K, T>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    T: Clone + Send + Sync + 'static,
+{
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+            generations: Arc::new(Mutex::new(HashMap::new())),
+            generation: Signal::new(0),
+        }
+    }
+
+    /// The cached value for `key`, if it is still fresh
+    pub fn get(&self, key: &K) -> Option<T> {
+        match self.lookup(key)? {
+            (value, true) => Some(value),
+            (_, false) => None,
+        }
+    }
+
+    pub fn insert(&self, key: K, value: T) {
+        self.evict();
+        lock(&self.entries).insert(
+            key,
+            CacheEntry {
+                value,
+                expires: Instant::now() + self.ttl,
+            },
+        );
+    }
+
+    /// Mark `key` stale; resources showing it fetch it again
+    pub fn invalidate(&self, key: &K) {
+        if let Some(entry) = lock(&self.entries).get_mut(key) {
+            entry.expires = Instant::now();
+        }
+        let generation = lock(&self.generations).get(key).cloned();
+        if let Some(generation) = generation {
+            generation.update(|generation| *generation += 1);
+        }
+    }
+
+    /// Mark every entry stale
+    pub fn invalidate_all(&self) {
+        let now = Instant::now();
+        for entry in lock(&self.entries).values_mut() {
+            entry.expires = now;
+        }
+        self.generation.update(|generation| *generation += 1);
+    }
+
+    /// Subscribe the running effect to invalidations of `key`
+    fn track(&self, key: &K) {
+        self.generation.with(|_| ());
+        self.evict();
+        let generation = lock(&self.generations)
+            .entry(key.clone())
+            .or_insert_with(|| Signal::new(0))
+            .clone();
+        generation.with(|_| ());
+    }
+
+    /// Forget the keys no resource shows any more, and their expired entries
+    fn evict(&self) {
+        let now = Instant::now();
+        let mut generations = lock(&self.generations);
+        generations.retain(|_, generation| generation.source.observer_count() > 0);
+        lock(&self.entries)
+            .retain(|key, entry| now < entry.expires || generations.contains_key(key));
+    }
+
+    /// The cached value and whether it is still fresh
+    fn lookup(&self, key: &K) -> Option<(T, bool)> {
+        let entries = lock(&self.entries);
+        let entry = entries.get(key)?;
+        Some((entry.value.clone(), Instant::now() < entry.expires))
+    }
+}
+
+/// How often and how patiently a failed fetch is retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl RetryPolicy {
+    /// Give up after the first failure
+    pub fn none() -> Self {
+        Self::exponential(1, Duration::ZERO)
+    }
+
+    /// Retry up to `max_attempts` times in total, doubling the delay after
+    /// each failure
+    pub fn exponential(max_attempts: u32, initial_delay: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_delay,
+            max_delay: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Delay before the given retry, counting from 1
+    fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+
+    async fn run<T, F, Fut>(&self, fetch: F) -> FetchResult<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = FetchResult<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match fetch().await {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt >= self.max_attempts => return Err(err),
+                Err(err) => {
+                    log::debug!("Fetch attempt {} failed: {}", attempt, err);
+                    task::sleep(self.delay(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wait for spawned fetches to land
+    async fn settle<T: Clone + Send + Sync + 'static>(resource: &Resource<T>) {
+        for _ in 0..200 {
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            if !resource.loading().get() {
+                return;
+            }
+        }
+        panic!("resource never finished loading");
+    }
+
+    #[tokio::test]
+    async fn test_stale_responses_are_dropped() {
+        let resource = Resource::new();
+        let slow = resource.fetch(|| async {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            Ok("old")
+        });
+        let fast = async {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            resource.fetch(|| async { Ok("new") }).await;
+        };
+        tokio::join!(slow, fast);
+
+        assert_eq!(resource.data().get(), Some("new"));
+        assert!(!resource.loading().get());
+    }
+
+    #[tokio::test]
+    async fn test_refetches_when_key_changes() {
+        let id = Signal::new(1);
+        let resource = create_resource(
+            {
+                let id = id.clone();
+                move || id.get()
+            },
+            |id| async move {
+                // Earlier ids answer last, as if the server were slower for them
+                tokio::time::sleep(Duration::from_millis(20 / id)).await;
+                Ok(format!("user {}", id))
+            },
+        );
+
+        id.set(2);
+        settle(&resource).await;
+        assert_eq!(resource.data().get().as_deref(), Some("user 2"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_ttl_and_invalidation() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = ResourceCache::new(Duration::from_secs(60));
+        let fetcher = {
+            let calls = calls.clone();
+            move |key: &'static str| {
+                let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                async move { Ok(format!("{} #{}", key, call)) }
+            }
+        };
+        let options = || ResourceOptions {
+            cache: Some(cache.clone()),
+            ..Default::default()
+        };
+
+        let first = create_resource_with(|| "posts", fetcher.clone(), options());
+        settle(&first).await;
+        let second = create_resource_with(|| "posts", fetcher, options());
+        assert_eq!(second.data().get().as_deref(), Some("posts #1"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Both show the stale value until the refetch lands
+        cache.invalidate(&"posts");
+        assert_eq!(first.data().get().as_deref(), Some("posts #1"));
+        settle(&first).await;
+        settle(&second).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_invalidation_only_reloads_its_key() {
+        let fetches = Arc::new(Mutex::new(Vec::new()));
+        let cache = ResourceCache::new(Duration::from_secs(60));
+        let fetcher = {
+            let fetches = fetches.clone();
+            move |key: &'static str| {
+                lock(&fetches).push(key);
+                async move {
+                    if key == "b" {
+                        tokio::time::sleep(Duration::from_millis(30)).await;
+                    }
+                    Ok(key)
+                }
+            }
+        };
+        let options = || ResourceOptions {
+            cache: Some(cache.clone()),
+            ..Default::default()
+        };
+
+        let a = create_resource_with(|| "a", fetcher.clone(), options());
+        settle(&a).await;
+        let b = create_resource_with(|| "b", fetcher, options());
+        assert!(b.loading().get());
+
+        // B's fetch keeps running rather than being restarted
+        cache.invalidate(&"a");
+        settle(&a).await;
+        settle(&b).await;
+        assert_eq!(*lock(&fetches), ["a", "b", "a"]);
+        assert_eq!(b.data().get(), Some("b"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_forgets_keys_no_resource_shows() {
+        let cache = ResourceCache::new(Duration::ZERO);
+        let id = Signal::new(0);
+        let resource = create_resource_with(
+            {
+                let id = id.clone();
+                move || id.get()
+            },
+            |id| async move { Ok(id) },
+            ResourceOptions {
+                cache: Some(cache.clone()),
+                ..Default::default()
+            },
+        );
+
+        for next in 1..=5 {
+            settle(&resource).await;
+            id.set(next);
+        }
+        settle(&resource).await;
+        assert_eq!(resource.data().get(), Some(5));
+        assert_eq!(lock(&cache.generations).len(), 1);
+        assert_eq!(lock(&cache.entries).len(), 1);
+
+        // The key still shown keeps reloading when invalidated
+        cache.invalidate(&5);
+        assert!(resource.loading().get());
+        settle(&resource).await;
+    }
+
+    #[tokio::test]
+    async fn test_retry_mutate_and_refetch() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let resource = create_resource_with(
+            || (),
+            {
+                let calls = calls.clone();
+                move |_| {
+                    let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                    async move {
+                        if call < 3 {
+                            Err(format!("attempt {} failed", call))
+                        } else {
+                            Ok(call)
+                        }
+                    }
+                }
+            },
+            ResourceOptions {
+                retry: RetryPolicy::exponential(3, Duration::from_millis(1)),
+                ..Default::default()
+            },
+        );
+        settle(&resource).await;
+        assert_eq!(resource.data().get(), Some(3));
+
+        resource.mutate(100);
+        assert_eq!(resource.data().get(), Some(100));
+
+        resource.refetch();
+        settle(&resource).await;
+        assert_eq!(resource.data().get(), Some(4));
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy::exponential(10, Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(500));
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(4), Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,298 @@
+//! Dependency tracking shared by signals, memos and effects
+//!
+//! Reading a [`Source`] while an observer is running records the source as
+//! one of the observer's dependencies. Writing the source later notifies those
+//! observers: memos mark themselves stale and recompute the next time they are
+//! read, effects and subscribers are queued and run once the write (or the
+//! enclosing [`batch`]) has finished.
+//!
+//! Because memos are pulled rather than pushed, anything that runs from the
+//! queue sees every value it depends on already updated, which gives the same
+//! result as running observers in topological order without glitches.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+static NEXT_FLUSH: AtomicUsize = AtomicUsize::new(1);
+
+pub(crate) fn next_id() -> usize {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+thread_local! {
+    /// The memo or effect currently running on this thread
+    static OBSERVER: RefCell<Option<Arc<dyn Observer>>> = const { RefCell::new(None) };
+    /// Effects waiting to re-run
+    static PENDING: RefCell<VecDeque<Arc<dyn Observer>>> = const { RefCell::new(VecDeque::new()) };
+    static FLUSHING: Cell<bool> = const { Cell::new(false) };
+    /// Identifies the flush in progress, so observers can tell which changes
+    /// came from the same write or batch
+    static FLUSH_ID: Cell<usize> = const { Cell::new(0) };
+    static BATCH_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// What an observer wants done after one of its sources changed
+pub(crate) enum Notify {
+    /// Already stale or queued
+    Ignore,
+    /// Queue the observer to run
+    Schedule,
+    /// The observer is itself a source whose readers must be notified
+    Propagate(Arc<Source>),
+}
+
+/// Anything that re-evaluates when the sources it read change
+pub(crate) trait Observer: Send + Sync {
+    fn id(&self) -> usize;
+    fn add_source(&self, source: Arc<Source>);
+    fn notify(&self) -> Notify;
+    fn run(self: Arc<Self>);
+    /// Forget a [`Notify::Schedule`] that will not be run after all
+    fn unqueue(&self);
+}
+
+/// The tracking half of a signal or memo
+pub(crate) struct Source {
+    observers: Mutex<Vec<(usize, Weak<dyn Observer>)>>,
+}
+
+impl Source {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self {
+            observers: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Record a read by the currently running observer, if there is one
+    pub(crate) fn track(self: &Arc<Self>) {
+        let Some(observer) = OBSERVER.with(|current| current.borrow().clone()) else {
+            return;
+        };
+
+        if self.subscribe(&observer) {
+            observer.add_source(self.clone());
+        }
+    }
+
+    /// Notify `observer` of future changes; returns false if it already was
+    pub(crate) fn subscribe(&self, observer: &Arc<dyn Observer>) -> bool {
+        let mut observers = lock(&self.observers);
+        if observers.iter().any(|(id, _)| *id == observer.id()) {
+            return false;
+        }
+        observers.push((observer.id(), Arc::downgrade(observer)));
+        true
+    }
+
+    pub(crate) fn unsubscribe(&self, observer_id: usize) {
+        lock(&self.observers).retain(|(id, _)| *id != observer_id);
+    }
+
+    pub(crate) fn observer_count(&self) -> usize {
+        lock(&self.observers).len()
+    }
+
+    /// Notify everything that read this source, then run queued effects
+    /// unless a batch is open
+    pub(crate) fn trigger(&self) {
+        self.mark();
+        if BATCH_DEPTH.with(Cell::get) == 0 {
+            flush();
+        }
+    }
+
+    fn mark(&self) {
+        let observers: Vec<Arc<dyn Observer>> = {
+            let mut observers = lock(&self.observers);
+            observers.retain(|(_, observer)| observer.strong_count() > 0);
+            observers
+                .iter()
+                .filter_map(|(_, observer)| observer.upgrade())
+                .collect()
+        };
+
+        for observer in observers {
+            match observer.notify() {
+                Notify::Ignore => {}
+                Notify::Schedule => {
+                    PENDING.with(|pending| pending.borrow_mut().push_back(observer))
+                }
+                Notify::Propagate(source) => source.mark(),
+            }
+        }
+    }
+}
+
+/// Run `f` with `observer` recording every source it reads
+pub(crate) fn with_observer<R>(observer: Arc<dyn Observer>, f: impl FnOnce() -> R) -> R {
+    let previous = OBSERVER.with(|current| current.replace(Some(observer)));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// Run `f` without recording any reads
+pub fn untrack<R>(f: impl FnOnce() -> R) -> R {
+    let previous = OBSERVER.with(|current| current.replace(None));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// Puts the previous observer back, even if the observer panicked
+struct Restore(Option<Arc<dyn Observer>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        OBSERVER.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Apply several updates at once
+///
+/// Effects and subscribers triggered inside `f` are queued and run once,
+/// after the outermost batch returns.
+pub fn batch<R>(f: impl FnOnce() -> R) -> R {
+    BATCH_DEPTH.with(|depth| depth.set(depth.get() + 1));
+    let result = {
+        let _batch = EndBatch;
+        f()
+    };
+    if BATCH_DEPTH.with(Cell::get) == 0 {
+        flush();
+    }
+    result
+}
+
+/// Whether a [`batch`] is open on this thread
+pub(crate) fn in_batch() -> bool {
+    BATCH_DEPTH.with(Cell::get) > 0
+}
+
+struct EndBatch;
+
+impl Drop for EndBatch {
+    fn drop(&mut self) {
+        BATCH_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/// Run queued effects until none are left
+///
+/// Effects that are triggered while the queue is being flushed are appended
+/// and run in the same pass, so each one runs at most once per change.
+fn flush() {
+    if FLUSHING.with(|flushing| flushing.replace(true)) {
+        return;
+    }
+    let _flushing = ResetFlushing;
+    FLUSH_ID.with(|id| id.set(NEXT_FLUSH.fetch_add(1, Ordering::Relaxed)));
+
+    while let Some(observer) = PENDING.with(|pending| pending.borrow_mut().pop_front()) {
+        // Whatever triggered the flush must not pick up the observer's reads
+        untrack(|| observer.run());
+    }
+}
+
+/// The flush currently running observers on this thread
+pub(crate) fn flush_id() -> usize {
+    FLUSH_ID.with(Cell::get)
+}
+
+struct ResetFlushing;
+
+impl Drop for ResetFlushing {
+    fn drop(&mut self) {
+        // An observer panicked: drop the rest of the queue, leaving each one
+        // free to be scheduled again by its next change
+        if std::thread::panicking() {
+            let dropped = PENDING.with(|pending| std::mem::take(&mut *pending.borrow_mut()));
+            for observer in dropped {
+                observer.unqueue();
+            }
+        }
+        FLUSHING.with(|flushing| flushing.set(false));
+    }
+}
+
+/// A side effect that re-runs whenever the sources it read change
+pub(crate) struct EffectNode {
+    id: usize,
+    f: Box<dyn Fn() + Send + Sync>,
+    sources: Mutex<Vec<Arc<Source>>>,
+    queued: AtomicBool,
+    disposed: AtomicBool,
+}
+
+impl EffectNode {
+    pub(crate) fn new(f: Box<dyn Fn() + Send + Sync>) -> Arc<Self> {
+        Arc::new(Self {
+            id: next_id(),
+            f,
+            sources: Mutex::new(Vec::new()),
+            queued: AtomicBool::new(false),
+            disposed: AtomicBool::new(false),
+        })
+    }
+
+    pub(crate) fn dispose(&self) {
+        self.disposed.store(true, Ordering::SeqCst);
+        clear_sources(self.id, &self.sources);
+    }
+
+    pub(crate) fn is_disposed(&self) -> bool {
+        self.disposed.load(Ordering::SeqCst)
+    }
+}
+
+impl Observer for EffectNode {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn add_source(&self, source: Arc<Source>) {
+        lock(&self.sources).push(source);
+    }
+
+    fn notify(&self) -> Notify {
+        if self.is_disposed() || self.queued.swap(true, Ordering::SeqCst) {
+            Notify::Ignore
+        } else {
+            Notify::Schedule
+        }
+    }
+
+    fn run(self: Arc<Self>) {
+        self.queued.store(false, Ordering::SeqCst);
+        if self.is_disposed() {
+            return;
+        }
+
+        // Dependencies are re-recorded on every run, so branches that are no
+        // longer taken stop triggering the effect
+        clear_sources(self.id, &self.sources);
+        let this = self.clone();
+        with_observer(this, || (self.f)());
+    }
+
+    fn unqueue(&self) {
+        self.queued.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Drop every subscription an observer holds
+pub(crate) fn clear_sources(observer_id: usize, sources: &Mutex<Vec<Arc<Source>>>) {
+    let sources = std::mem::take(&mut *lock(sources));
+    for source in sources {
+        source.unsubscribe(observer_id);
+    }
+}
+
+/// Tracking state survives a panicking observer, so poisoning is ignored here
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
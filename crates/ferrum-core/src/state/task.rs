@@ -0,0 +1,162 @@
+//! Background futures for resources and actions
+//!
+//! Natively a future runs on the current Tokio runtime. In the browser,
+//! with the `client` feature, there is no Tokio runtime, so the page's
+//! event loop drives it through `wasm_bindgen_futures` instead. Either way
+//! the returned [`Task`] aborts it.
+
+use super::runtime::lock;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// Aborts a spawned future
+pub(crate) struct Task {
+    abort: Arc<Abort>,
+}
+
+#[derive(Default)]
+struct Abort {
+    aborted: AtomicBool,
+    /// Wakes the future so it sees it was aborted
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Task {
+    /// Drop the future instead of polling it again
+    pub(crate) fn abort(&self) {
+        self.abort.aborted.store(true, Ordering::SeqCst);
+        if let Some(waker) = lock(&self.abort.waker).take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A future that finishes early, dropping its inner future, once aborted
+struct Abortable<F> {
+    future: Pin<Box<F>>,
+    abort: Arc<Abort>,
+}
+
+impl<F: Future<Output = ()>> Future for Abortable<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        *lock(&self.abort.waker) = Some(cx.waker().clone());
+        // Checked after storing the waker, so an abort in between still wakes
+        if self.abort.aborted.load(Ordering::SeqCst) {
+            return Poll::Ready(());
+        }
+        self.future.as_mut().poll(cx)
+    }
+}
+
+fn abortable<F>(future: F) -> (Abortable<F>, Task) {
+    let abort = Arc::new(Abort::default());
+    let task = Task {
+        abort: abort.clone(),
+    };
+    (
+        Abortable {
+            future: Box::pin(future),
+            abort,
+        },
+        task,
+    )
+}
+
+/// Run `future` in the background, or give it back outside a Tokio runtime
+#[cfg(not(all(feature = "client", target_arch = "wasm32")))]
+pub(crate) fn spawn<F>(future: F) -> Result<Task, F>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return Err(future);
+    };
+    let (future, task) = abortable(future);
+    runtime.spawn(future);
+    Ok(task)
+}
+
+/// Run `future` on the page's event loop
+#[cfg(all(feature = "client", target_arch = "wasm32"))]
+pub(crate) fn spawn<F>(future: F) -> Result<Task, F>
+where
+    F: Future<Output = ()> + 'static,
+{
+    let (future, task) = abortable(future);
+    wasm_bindgen_futures::spawn_local(future);
+    Ok(task)
+}
+
+/// Wait for `duration` without blocking the thread
+#[cfg(not(all(feature = "client", target_arch = "wasm32")))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// Wait for `duration` on a browser timer
+#[cfg(all(feature = "client", target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: Duration) {
+    use wasm_bindgen_futures::js_sys::Promise;
+    use wasm_bindgen_futures::JsFuture;
+
+    let millis = i32::try_from(duration.as_millis()).unwrap_or(i32::MAX);
+    let promise = Promise::new(&mut |resolve, _| {
+        let scheduled = web_sys::window().map(|window| {
+            window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis)
+        });
+        if !matches!(scheduled, Some(Ok(_))) {
+            let _ = resolve.call0(&wasm_bindgen::JsValue::UNDEFINED);
+        }
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn test_aborted_tasks_stop_and_drop_their_future() {
+        struct Dropped(Arc<AtomicBool>);
+
+        impl Drop for Dropped {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let task = spawn({
+            let (ticks, dropped) = (ticks.clone(), Dropped(dropped.clone()));
+            async move {
+                let _dropped = dropped;
+                loop {
+                    sleep(Duration::from_millis(1)).await;
+                    ticks.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        })
+        .unwrap_or_else(|_| panic!("no runtime"));
+
+        sleep(Duration::from_millis(10)).await;
+        task.abort();
+        sleep(Duration::from_millis(5)).await;
+        let stopped = ticks.load(Ordering::SeqCst);
+        assert!(dropped.load(Ordering::SeqCst));
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(ticks.load(Ordering::SeqCst), stopped);
+    }
+
+    #[test]
+    fn test_spawn_needs_a_runtime_natively() {
+        assert!(spawn(async {}).is_err());
+    }
+}
@@ -0,0 +1,645 @@
+//! Diffing rendered views into patches
+//!
+//! [`diff`] compares two [`ComponentView`] trees and returns the patches that
+//! turn the first into the second. Fragments take no part: both trees are
+//! read as the nodes they render to, so the root of a view is a list of
+//! nodes, the children of the element it is mounted in. Patches address a
+//! node by child indices into that list and are applied in order, each one
+//! against the nodes the previous ones left behind. [`apply`] runs them on
+//! in-memory nodes; with the `client` feature, [`apply_to_dom`] runs them on
+//! the page.
+//!
+//! Children with a `key` prop are matched by key, so reordering a list moves
+//! its nodes instead of rewriting each one. Children without a key are
+//! matched by their position among the unkeyed children.
+//!
+//! Rows rendered from a [`SignalVec`](crate::state::SignalVec) don't need a
+//! full diff: [`list_patches`] turns each [`VecDiff`] it reports into the
+//! patches for just the rows it touched.
+
+use crate::component::{ComponentView, Element, EventHandler, PropValue};
+use crate::state::VecDiff;
+use crate::{FerrumError, Result};
+use std::collections::HashSet;
+
+#[cfg(feature = "client")]
+mod dom;
+
+#[cfg(feature = "client")]
+pub(crate) use dom::js_error;
+#[cfg(feature = "client")]
+pub use dom::{apply_to_dom, create_node};
+
+/// Prop that identifies a child among its siblings
+pub const KEY_PROP: &str = "key";
+
+/// One change to a rendered tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum Patch {
+    /// Put `node` in place of the node at `path`
+    Replace {
+        path: Vec<usize>,
+        node: ComponentView,
+    },
+    SetAttribute {
+        path: Vec<usize>,
+        name: String,
+        value: PropValue,
+    },
+    RemoveAttribute {
+        path: Vec<usize>,
+        name: String,
+    },
+    /// Change the content of the text node at `path`
+    SetText {
+        path: Vec<usize>,
+        text: String,
+    },
+    /// Insert `node` as child `index` of the node at `path`, or of the root
+    /// list if `path` is empty
+    Insert {
+        path: Vec<usize>,
+        index: usize,
+        node: ComponentView,
+    },
+    /// Remove child `index` of the node at `path`
+    Remove {
+        path: Vec<usize>,
+        index: usize,
+    },
+    /// Take child `from` out of the node at `path` and put it back at `to`
+    Move {
+        path: Vec<usize>,
+        from: usize,
+        to: usize,
+    },
+}
+
+/// The patches that turn `old` into `new`
+pub fn diff(old: &ComponentView, new: &ComponentView) -> Vec<Patch> {
+    let mut patches = Vec::new();
+    diff_children(&old.nodes(), &new.nodes(), &mut Vec::new(), &mut patches);
+    patches
+}
+
+/// The patches that apply `diff` to the rows under the node at `path`, or
+/// to the root list if `path` is empty
+///
+/// `rows` holds the nodes rendered from the list so far, one node per item,
+/// and is kept in sync. `render` is only called for the items the diff
+/// inserts, updates or replaces; an updated row is diffed against its old
+/// node and a replaced list is matched by key, so untouched rows get no
+/// patches.
+pub fn list_patches<T>(
+    rows: &mut Vec<ComponentView>,
+    path: &[usize],
+    diff: &VecDiff<T>,
+    render: impl FnMut(&T) -> ComponentView,
+) -> Vec<Patch> {
+    let mut path = path.to_vec();
+    let mut patches = Vec::new();
+    match diff {
+        VecDiff::Insert { index, .. } => {
+            diff.apply(rows, render);
+            patches.push(Patch::Insert {
+                path,
+                index: *index,
+                node: rows[*index].clone(),
+            });
+        }
+        VecDiff::Update { index, .. } => {
+            let old = rows[*index].clone();
+            diff.apply(rows, render);
+            path.push(*index);
+            diff_node(&old, &rows[*index], &mut path, &mut patches);
+        }
+        VecDiff::Remove { index } => {
+            diff.apply(rows, render);
+            patches.push(Patch::Remove {
+                path,
+                index: *index,
+            });
+        }
+        VecDiff::Move { from, to } => {
+            diff.apply(rows, render);
+            patches.push(Patch::Move {
+                path,
+                from: *from,
+                to: *to,
+            });
+        }
+        VecDiff::Replace { .. } => {
+            let old = std::mem::take(rows);
+            diff.apply(rows, render);
+            diff_children(
+                &old.iter().collect::<Vec<_>>(),
+                &rows.iter().collect::<Vec<_>>(),
+                &mut path,
+                &mut patches,
+            );
+        }
+    }
+    patches
+}
+
+/// `view` as the nodes it renders to, with fragments flattened away at
+/// every level
+pub fn flatten(view: &ComponentView) -> Vec<ComponentView> {
+    match view {
+        ComponentView::Fragment(children) => children.iter().flat_map(flatten).collect(),
+        ComponentView::Element(element) => vec![ComponentView::Element(Element {
+            tag: element.tag.clone(),
+            props: element.props.clone(),
+            children: element.children.iter().flat_map(flatten).collect(),
+            handlers: element.handlers.clone(),
+        })],
+        node => vec![node.clone()],
+    }
+}
+
+fn child_nodes(element: &Element) -> Vec<&ComponentView> {
+    element
+        .children
+        .iter()
+        .flat_map(ComponentView::nodes)
+        .collect()
+}
+
+fn diff_node(
+    old: &ComponentView,
+    new: &ComponentView,
+    path: &mut Vec<usize>,
+    patches: &mut Vec<Patch>,
+) {
+    match (old, new) {
+        (ComponentView::Text(before), ComponentView::Text(after)) => {
+            if before != after {
+                patches.push(Patch::SetText {
+                    path: path.clone(),
+                    text: after.clone(),
+                });
+            }
+        }
+        (ComponentView::Element(before), ComponentView::Element(after))
+            if before.tag == after.tag =>
+        {
+            diff_props(before, after, path, patches);
+            diff_children(&child_nodes(before), &child_nodes(after), path, patches);
+        }
+        (ComponentView::Component(before), ComponentView::Component(after)) if before == after => {}
+        _ => patches.push(Patch::Replace {
+            path: path.clone(),
+            node: new.clone(),
+        }),
+    }
+}
+
+fn diff_props(old: &Element, new: &Element, path: &[usize], patches: &mut Vec<Patch>) {
+    let mut names: Vec<&String> = old.props.keys().chain(new.props.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        match (old.props.get(name), new.props.get(name)) {
+            (Some(before), Some(after)) if before == after => {}
+            (_, Some(value)) => patches.push(Patch::SetAttribute {
+                path: path.to_vec(),
+                name: name.clone(),
+                value: value.clone(),
+            }),
+            (Some(_), None) => patches.push(Patch::RemoveAttribute {
+                path: path.to_vec(),
+                name: name.clone(),
+            }),
+            (None, None) => {}
+        }
+    }
+}
+
+/// How a child is matched against the other list
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Identity {
+    Key(String),
+    /// The nth child without a key
+    Position(usize),
+}
+
+fn key_of(view: &ComponentView) -> Option<&str> {
+    match view {
+        ComponentView::Element(element) => match element.props.get(KEY_PROP) {
+            Some(PropValue::String(key)) => Some(key),
+            _ => None,
+        },
+        ComponentView::Component(node) => Some(&node.key),
+        _ => None,
+    }
+}
+
+/// Identities for `children`, or `None` if two share a key
+fn identities(children: &[&ComponentView]) -> Option<Vec<Identity>> {
+    let mut seen = HashSet::new();
+    let mut unkeyed = 0;
+    children
+        .iter()
+        .map(|child| match key_of(child) {
+            Some(key) => seen.insert(key).then(|| Identity::Key(key.to_string())),
+            None => {
+                unkeyed += 1;
+                Some(Identity::Position(unkeyed - 1))
+            }
+        })
+        .collect()
+}
+
+fn diff_children(
+    old: &[&ComponentView],
+    new: &[&ComponentView],
+    path: &mut Vec<usize>,
+    patches: &mut Vec<Patch>,
+) {
+    // With duplicate keys, fall back to matching every child by position
+    let (old_ids, new_ids) = match (identities(old), identities(new)) {
+        (Some(old_ids), Some(new_ids)) => (old_ids, new_ids),
+        _ => (
+            (0..old.len()).map(Identity::Position).collect(),
+            (0..new.len()).map(Identity::Position).collect(),
+        ),
+    };
+    let wanted: HashSet<&Identity> = new_ids.iter().collect();
+
+    // Children of the tree as patched so far: the identity and, for nodes
+    // kept from `old`, the old node to diff against
+    let mut current: Vec<(Identity, Option<&ComponentView>)> = Vec::new();
+    let mut removed = Vec::new();
+    for (index, id) in old_ids.into_iter().enumerate() {
+        if wanted.contains(&id) {
+            current.push((id, Some(old[index])));
+        } else {
+            removed.push(index);
+        }
+    }
+    // From the back, so each index is still valid when it is removed
+    patches.extend(removed.into_iter().rev().map(|index| Patch::Remove {
+        path: path.clone(),
+        index,
+    }));
+
+    for (index, id) in new_ids.iter().enumerate() {
+        if current
+            .get(index)
+            .is_some_and(|(current_id, _)| current_id == id)
+        {
+            continue;
+        }
+        match current.iter().position(|(current_id, _)| current_id == id) {
+            Some(from) => {
+                let moved = current.remove(from);
+                current.insert(index, moved);
+                patches.push(Patch::Move {
+                    path: path.clone(),
+                    from,
+                    to: index,
+                });
+            }
+            None => {
+                current.insert(index, (id.clone(), None));
+                patches.push(Patch::Insert {
+                    path: path.clone(),
+                    index,
+                    node: new[index].clone(),
+                });
+            }
+        }
+    }
+
+    for (index, (_, old_child)) in current.into_iter().enumerate() {
+        if let Some(old_child) = old_child {
+            path.push(index);
+            diff_node(old_child, new[index], path, patches);
+            path.pop();
+        }
+    }
+}
+
+/// Handlers for `event` on the node at `path` and its ancestors, innermost
+/// first; empty if there is no node at `path`
+pub fn handlers_at(root: &ComponentView, path: &[usize], event: &str) -> Vec<EventHandler> {
+    let mut handlers = Vec::new();
+    let mut nodes = root.nodes();
+    for (depth, &index) in path.iter().enumerate() {
+        match nodes.get(index) {
+            Some(ComponentView::Element(element)) => {
+                handlers.extend(element.handlers.get(event).cloned());
+                nodes = child_nodes(element);
+            }
+            // Text and component nodes have no children or handlers
+            Some(_) if depth + 1 == path.len() => {}
+            _ => return Vec::new(),
+        }
+    }
+    handlers.reverse();
+    handlers
+}
+
+/// Apply `patches` to the nodes of the old view, as from [`flatten`]
+pub fn apply(nodes: &mut Vec<ComponentView>, patches: &[Patch]) -> Result<()> {
+    for patch in patches {
+        match patch {
+            Patch::Replace { path, node } => *node_at(nodes, path)? = flat(node),
+            Patch::SetAttribute { path, name, value } => {
+                element_at(nodes, path)?
+                    .props
+                    .insert(name.clone(), value.clone());
+            }
+            Patch::RemoveAttribute { path, name } => {
+                element_at(nodes, path)?.props.remove(name);
+            }
+            Patch::SetText { path, text } => match node_at(nodes, path)? {
+                ComponentView::Text(content) => *content = text.clone(),
+                _ => return Err(wrong_node(path, "a text node")),
+            },
+            Patch::Insert { path, index, node } => {
+                let children = children_at(nodes, path)?;
+                if *index > children.len() {
+                    return Err(bad_index(path, *index));
+                }
+                children.insert(*index, flat(node));
+            }
+            Patch::Remove { path, index } => {
+                let children = children_at(nodes, path)?;
+                if *index >= children.len() {
+                    return Err(bad_index(path, *index));
+                }
+                children.remove(*index);
+            }
+            Patch::Move { path, from, to } => {
+                let children = children_at(nodes, path)?;
+                if *from >= children.len() || *to >= children.len() {
+                    return Err(bad_index(path, (*from).max(*to)));
+                }
+                let child = children.remove(*from);
+                children.insert(*to, child);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A node from a patch, with the fragments below it flattened
+fn flat(node: &ComponentView) -> ComponentView {
+    let mut nodes = flatten(node);
+    if nodes.len() == 1 {
+        nodes.remove(0)
+    } else {
+        ComponentView::Fragment(nodes)
+    }
+}
+
+fn children_at<'a>(
+    nodes: &'a mut Vec<ComponentView>,
+    path: &[usize],
+) -> Result<&'a mut Vec<ComponentView>> {
+    if path.is_empty() {
+        return Ok(nodes);
+    }
+    Ok(&mut element_at(nodes, path)?.children)
+}
+
+fn node_at<'a>(nodes: &'a mut [ComponentView], path: &[usize]) -> Result<&'a mut ComponentView> {
+    let (&last, parent) = path
+        .split_last()
+        .ok_or_else(|| wrong_node(path, "a node"))?;
+    let siblings = if parent.is_empty() {
+        nodes
+    } else {
+        &mut element_at(nodes, parent)?.children[..]
+    };
+    siblings
+        .get_mut(last)
+        .ok_or_else(|| bad_index(parent, last))
+}
+
+fn element_at<'a>(nodes: &'a mut [ComponentView], path: &[usize]) -> Result<&'a mut Element> {
+    match node_at(nodes, path)? {
+        ComponentView::Element(element) => Ok(element),
+        _ => Err(wrong_node(path, "an element")),
+    }
+}
+
+fn bad_index(path: &[usize], index: usize) -> FerrumError {
+    FerrumError::Component(format!("no child {} under node {:?}", index, path))
+}
+
+fn wrong_node(path: &[usize], expected: &str) -> FerrumError {
+    FerrumError::Component(format!("node {:?} is not {}", path, expected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::ComponentNode;
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+
+    fn item(key: &str, text: &str) -> ComponentView {
+        Element::new("li")
+            .prop(KEY_PROP, PropValue::String(key.to_string()))
+            .child(ComponentView::text(text))
+            .into()
+    }
+
+    fn list(attr: (&str, &str), items: Vec<ComponentView>) -> ComponentView {
+        Element::new("ul")
+            .prop(attr.0, PropValue::String(attr.1.to_string()))
+            .children(items)
+            .into()
+    }
+
+    #[test]
+    fn test_keyed_children_move_instead_of_rewrite() {
+        let old = list(
+            ("class", "list"),
+            vec![item("a", "A"), item("b", "B"), item("c", "C")],
+        );
+        let new = list(
+            ("id", "items"),
+            vec![item("c", "C"), item("a", "A!"), item("d", "D")],
+        );
+
+        let patches = diff(&old, &new);
+        assert_eq!(
+            patches,
+            [
+                Patch::RemoveAttribute {
+                    path: vec![0],
+                    name: "class".to_string(),
+                },
+                Patch::SetAttribute {
+                    path: vec![0],
+                    name: "id".to_string(),
+                    value: PropValue::String("items".to_string()),
+                },
+                Patch::Remove {
+                    path: vec![0],
+                    index: 1,
+                },
+                Patch::Move {
+                    path: vec![0],
+                    from: 1,
+                    to: 0,
+                },
+                Patch::Insert {
+                    path: vec![0],
+                    index: 2,
+                    node: item("d", "D"),
+                },
+                Patch::SetText {
+                    path: vec![0, 1, 0],
+                    text: "A!".to_string(),
+                },
+            ]
+        );
+
+        let mut nodes = flatten(&old);
+        apply(&mut nodes, &patches).unwrap();
+        assert_eq!(nodes, flatten(&new));
+        let bad = Patch::Remove {
+            path: vec![5],
+            index: 0,
+        };
+        assert!(apply(&mut nodes, &[bad]).is_err());
+
+        // Wrapping nodes in a fragment renders the same nodes
+        let wrapped = list(
+            ("id", "items"),
+            vec![
+                ComponentView::Fragment(vec![item("c", "C"), item("a", "A!")]),
+                item("d", "D"),
+            ],
+        );
+        assert!(diff(&new, &wrapped).is_empty());
+    }
+
+    #[test]
+    fn test_list_diffs_patch_only_their_rows() {
+        use crate::state::SignalVec;
+        use std::sync::{Arc, Mutex};
+
+        let tasks = SignalVec::new(|task: &(String, String)| task.0.clone());
+        for key in ["a", "b", "c"] {
+            tasks.push((key.to_string(), key.to_uppercase())).unwrap();
+        }
+        let diffs = Arc::new(Mutex::new(Vec::new()));
+        let _subscription = tasks.subscribe_diff({
+            let diffs = diffs.clone();
+            move |new: &[VecDiff<(String, String)>]| diffs.lock().unwrap().extend_from_slice(new)
+        });
+
+        let mut renders = 0;
+        let mut render = |task: &(String, String)| {
+            renders += 1;
+            item(&task.0, &task.1)
+        };
+        let mut rows: Vec<ComponentView> =
+            tasks.with(|items| items.iter().map(&mut render).collect());
+        let mut nodes = vec![list(("class", "tasks"), rows.clone())];
+
+        tasks
+            .update_item(&"b".to_string(), |task| task.1 = "B!".to_string())
+            .unwrap();
+        tasks.push(("d".to_string(), "D".to_string())).unwrap();
+        tasks.move_item(&"d".to_string(), 0).unwrap();
+        tasks.remove_by_key(&"a".to_string()).unwrap();
+
+        let mut patches = Vec::new();
+        for diff in diffs.lock().unwrap().drain(..) {
+            patches.extend(list_patches(&mut rows, &[0], &diff, &mut render));
+        }
+        assert_eq!(
+            patches[0],
+            Patch::SetText {
+                path: vec![0, 1, 0],
+                text: "B!".to_string(),
+            }
+        );
+        assert_eq!(patches.len(), 4);
+        // Three initial rows plus the updated and inserted ones
+        assert_eq!(renders, 5);
+
+        apply(&mut nodes, &patches).unwrap();
+        let expected: Vec<ComponentView> =
+            tasks.with(|items| items.iter().map(|task| item(&task.0, &task.1)).collect());
+        assert_eq!(nodes, flatten(&list(("class", "tasks"), expected.clone())));
+        assert_eq!(rows, expected);
+
+        // Replacing the list keeps the rows that are still there
+        tasks
+            .set(vec![
+                ("c".to_string(), "C".to_string()),
+                ("e".to_string(), "E".to_string()),
+            ])
+            .unwrap();
+        let replaced = diffs.lock().unwrap().pop().unwrap();
+        let patches = list_patches(&mut rows, &[0], &replaced, |task| item(&task.0, &task.1));
+        apply(&mut nodes, &patches).unwrap();
+        assert_eq!(nodes, flatten(&list(("class", "tasks"), rows.clone())));
+        assert!(!patches
+            .iter()
+            .any(|patch| matches!(patch, Patch::Replace { .. })));
+    }
+
+    fn prop_value() -> impl Strategy<Value = PropValue> {
+        prop_oneof![
+            "[xy]".prop_map(PropValue::String),
+            any::<bool>().prop_map(PropValue::Boolean),
+            (0..3).prop_map(|n| PropValue::Number(n as f64)),
+        ]
+    }
+
+    fn element(
+        tag: String,
+        props: HashMap<String, PropValue>,
+        children: Vec<ComponentView>,
+    ) -> ComponentView {
+        ComponentView::Element(Element {
+            tag,
+            props,
+            children,
+            ..Default::default()
+        })
+    }
+
+    fn view() -> impl Strategy<Value = ComponentView> {
+        let props = || proptest::collection::hash_map("key|class|id", prop_value(), 0..3);
+        let leaf = prop_oneof![
+            "[ab]{0,2}".prop_map(ComponentView::Text),
+            ("div|span", props()).prop_map(|(tag, props)| element(tag, props, Vec::new())),
+            ("A|B", "[xy]").prop_map(|(name, key)| {
+                ComponentView::Component(ComponentNode {
+                    name,
+                    key,
+                    props: HashMap::new(),
+                })
+            }),
+        ];
+        leaf.prop_recursive(3, 32, 5, move |child| {
+            prop_oneof![
+                (
+                    "div|span",
+                    props(),
+                    proptest::collection::vec(child.clone(), 0..5)
+                )
+                    .prop_map(|(tag, props, children)| element(tag, props, children)),
+                proptest::collection::vec(child, 0..3).prop_map(ComponentView::Fragment),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_applying_diff_gives_new_tree(old in view(), new in view()) {
+            let mut nodes = flatten(&old);
+            apply(&mut nodes, &diff(&old, &new)).unwrap();
+            prop_assert_eq!(nodes, flatten(&new));
+        }
+    }
+}
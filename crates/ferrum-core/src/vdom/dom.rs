@@ -0,0 +1,132 @@
+//! Applying patches to the page
+
+use super::Patch;
+use crate::component::{ComponentView, PropValue};
+use crate::{FerrumError, Result};
+use wasm_bindgen::JsCast;
+
+/// Apply `patches` to `container`, the element the old view is mounted in
+pub fn apply_to_dom(container: &web_sys::Node, patches: &[Patch]) -> Result<()> {
+    let document = container
+        .owner_document()
+        .ok_or_else(|| FerrumError::Component("node has no document".to_string()))?;
+
+    for patch in patches {
+        match patch {
+            Patch::Replace { path, node } => {
+                let old = node_at(container, path)?;
+                let new = create_node(&document, node)?;
+                let parent = old
+                    .parent_node()
+                    .ok_or_else(|| FerrumError::Component("node has no parent".to_string()))?;
+                parent.replace_child(&new, &old).map_err(js_error)?;
+            }
+            Patch::SetAttribute { path, name, value } => {
+                let element = element_at(container, path)?;
+                match attribute_value(value) {
+                    Some(value) => element.set_attribute(name, &value).map_err(js_error)?,
+                    None => element.remove_attribute(name).map_err(js_error)?,
+                }
+            }
+            Patch::RemoveAttribute { path, name } => {
+                element_at(container, path)?
+                    .remove_attribute(name)
+                    .map_err(js_error)?;
+            }
+            Patch::SetText { path, text } => {
+                node_at(container, path)?.set_text_content(Some(text));
+            }
+            Patch::Insert { path, index, node } => {
+                let parent = node_at(container, path)?;
+                let new = create_node(&document, node)?;
+                let before = parent.child_nodes().item(*index as u32);
+                parent
+                    .insert_before(&new, before.as_ref())
+                    .map_err(js_error)?;
+            }
+            Patch::Remove { path, index } => {
+                let parent = node_at(container, path)?;
+                let child = child_at(&parent, *index)?;
+                parent.remove_child(&child).map_err(js_error)?;
+            }
+            Patch::Move { path, from, to } => {
+                let parent = node_at(container, path)?;
+                let child = child_at(&parent, *from)?;
+                parent.remove_child(&child).map_err(js_error)?;
+                let before = parent.child_nodes().item(*to as u32);
+                parent
+                    .insert_before(&child, before.as_ref())
+                    .map_err(js_error)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Build the DOM for `view`
+///
+/// A child component that was never mounted leaves a comment, so the nodes
+/// keep the indices patches expect.
+pub fn create_node(document: &web_sys::Document, view: &ComponentView) -> Result<web_sys::Node> {
+    let element = match view {
+        ComponentView::Text(text) => return Ok(document.create_text_node(text).into()),
+        ComponentView::Component(node) => return Ok(document.create_comment(&node.name).into()),
+        ComponentView::Fragment(children) => {
+            let fragment = document.create_document_fragment();
+            for child in children {
+                fragment
+                    .append_child(&create_node(document, child)?)
+                    .map_err(js_error)?;
+            }
+            return Ok(fragment.into());
+        }
+        ComponentView::Element(element) => element,
+    };
+
+    let node = document.create_element(&element.tag).map_err(js_error)?;
+    for (name, value) in &element.props {
+        if let Some(value) = attribute_value(value) {
+            node.set_attribute(name, &value).map_err(js_error)?;
+        }
+    }
+    for child in &element.children {
+        node.append_child(&create_node(document, child)?)
+            .map_err(js_error)?;
+    }
+    Ok(node.into())
+}
+
+/// The attribute a prop sets; `None` leaves it off
+fn attribute_value(value: &PropValue) -> Option<String> {
+    match value {
+        PropValue::String(value) => Some(value.clone()),
+        PropValue::Number(value) => Some(value.to_string()),
+        PropValue::Boolean(true) => Some(String::new()),
+        _ => None,
+    }
+}
+
+fn node_at(container: &web_sys::Node, path: &[usize]) -> Result<web_sys::Node> {
+    let mut node = container.clone();
+    for &index in path {
+        node = child_at(&node, index)?;
+    }
+    Ok(node)
+}
+
+fn element_at(container: &web_sys::Node, path: &[usize]) -> Result<web_sys::Element> {
+    node_at(container, path)?
+        .dyn_into()
+        .map_err(|_| FerrumError::Component(format!("node {:?} is not an element", path)))
+}
+
+fn child_at(parent: &web_sys::Node, index: usize) -> Result<web_sys::Node> {
+    parent
+        .child_nodes()
+        .item(index as u32)
+        .ok_or_else(|| FerrumError::Component(format!("no child {} in the DOM", index)))
+}
+
+pub(crate) fn js_error(e: wasm_bindgen::JsValue) -> FerrumError {
+    FerrumError::Component(format!("{:?}", e))
+}
@@ -0,0 +1,6 @@
+#[test]
+fn test_component_macro() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/component-pass.rs");
+    cases.compile_fail("tests/ui/component-*-fail.rs");
+}
@@ -0,0 +1,23 @@
+use ferrum_core::component;
+use ferrum_core::component::ComponentView;
+
+component! {
+    Toggle {
+        props: {},
+        state: { on: bool },
+        msg: { Flip, Set(bool) }
+    }
+}
+
+impl Toggle {
+    fn on_flip(&mut self) -> bool {
+        self.on = !self.on;
+        true
+    }
+
+    fn render(&self) -> ComponentView {
+        ComponentView::text(self.on.to_string())
+    }
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use ferrum_core::component;
+
+component! {
+    Toggle {
+        props: {},
+        msg: { Flip }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,64 @@
+use ferrum_core::component;
+use ferrum_core::component::{ComponentView, Runtime};
+
+component! {
+    pub Counter {
+        props: { label: String, step: i32 = 1 },
+        state: { count: i32, history: Vec<i32> = Vec::with_capacity(4) },
+        msg: { Increment, SetCount(i32), Reset }
+    }
+}
+
+impl Counter {
+    fn on_increment(&mut self) -> bool {
+        self.count += self.props.step;
+        true
+    }
+
+    fn on_set_count(&mut self, count: i32) -> bool {
+        self.history.push(self.count);
+        self.count = count;
+        true
+    }
+
+    fn on_reset(&mut self) -> bool {
+        let changed = self.count != 0;
+        self.count = 0;
+        changed
+    }
+
+    fn render(&self) -> ComponentView {
+        ComponentView::text(format!("{}: {}", self.props.label, self.count))
+    }
+}
+
+component! {
+    Empty {
+        props: {},
+        state: {},
+        msg: {},
+    }
+}
+
+impl Empty {
+    fn render(&self) -> ComponentView {
+        ComponentView::Fragment(Vec::new())
+    }
+}
+
+fn main() {
+    let props = CounterProps::default().label("Clicks").step(2);
+    let mut counter = Runtime::<Counter>::mount(props).unwrap();
+    assert_eq!(counter.view(), &ComponentView::text("Clicks: 0"));
+
+    counter.dispatch(CounterMsg::Increment).unwrap();
+    counter.dispatch(CounterMsg::SetCount(7)).unwrap();
+    counter.dispatch(CounterMsg::Increment).unwrap();
+    assert_eq!(counter.view(), &ComponentView::text("Clicks: 9"));
+    assert_eq!(counter.component().history, [2]);
+
+    assert!(counter.dispatch(CounterMsg::Reset).unwrap());
+    assert!(!counter.dispatch(CounterMsg::Reset).unwrap());
+
+    Runtime::<Empty>::mount(EmptyProps::default()).unwrap();
+}
@@ -0,0 +1,577 @@
+use axum::{
+    async_trait,
+    extract::{FromRequest, Query, Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use ferrum_core::formatter::FerrumFormatter;
+use ferrum_core::parser::{BinaryOperator, Expression, FerrumNode, FerrumParser};
+use ferrum_core::state::History;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{
+    generate_body_html_from_nodes, generate_html_from_nodes, project_stylesheet, AppState,
+};
+
+/// File used when a request doesn't name one
+const DEFAULT_FILE: &str = "src/main.frr";
+
+/// Request accepted by the editor tooling endpoints, as `?file=` or a JSON body
+///
+/// `file` is relative to the project root. `source` lets an editor send an
+/// unsaved buffer instead of reading the file from disk.
+#[derive(Debug, Default, Deserialize)]
+pub struct ToolRequest {
+    pub file: Option<String>,
+    pub source: Option<String>,
+}
+
+/// A structured problem report, suitable for an editor's problems panel
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticCode {
+    /// The request body isn't the JSON the endpoint expects
+    InvalidRequest,
+    /// A write sent from a page the server didn't serve
+    CrossOrigin,
+    InvalidPath,
+    OutsideProject,
+    NotFound,
+    Io,
+    Parse,
+    Format,
+    /// No state history is attached to the server
+    NoHistory,
+    /// The history can't move to the requested position
+    History,
+}
+
+impl DiagnosticCode {
+    fn status(self) -> StatusCode {
+        match self {
+            DiagnosticCode::InvalidRequest | DiagnosticCode::InvalidPath => StatusCode::BAD_REQUEST,
+            DiagnosticCode::CrossOrigin => StatusCode::FORBIDDEN,
+            DiagnosticCode::OutsideProject => StatusCode::FORBIDDEN,
+            DiagnosticCode::NotFound | DiagnosticCode::NoHistory => StatusCode::NOT_FOUND,
+            DiagnosticCode::Io => StatusCode::INTERNAL_SERVER_ERROR,
+            DiagnosticCode::Parse | DiagnosticCode::Format | DiagnosticCode::History => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+        }
+    }
+}
+
+impl Diagnostic {
+    fn error(code: DiagnosticCode, message: impl Into<String>, file: Option<&str>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            file: file.map(str::to_string),
+        }
+    }
+}
+
+impl IntoResponse for Diagnostic {
+    fn into_response(self) -> Response {
+        let status = self.code.status();
+        let body = json!({
+            "file": self.file,
+            "diagnostics": [self],
+        });
+        (status, Json(body)).into_response()
+    }
+}
+
+/// A JSON request body, rejected with a [`Diagnostic`] when it's malformed
+pub struct JsonBody<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Diagnostic;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Diagnostic> {
+        let Json(value) = Json::<T>::from_request(request, state)
+            .await
+            .map_err(|e| Diagnostic::error(DiagnosticCode::InvalidRequest, e.body_text(), None))?;
+        Ok(Self(value))
+    }
+}
+
+/// Refuse requests that change something when a page from another origin
+/// sent them
+///
+/// Without this any site open in the developer's browser could save files
+/// into the project. Requests without an `Origin`, like an editor's, pass.
+pub async fn refuse_cross_origin_writes(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match check_origin(request.method(), request.headers(), state.address) {
+        Ok(()) => next.run(request).await,
+        Err(diagnostic) => diagnostic.into_response(),
+    }
+}
+
+fn check_origin(
+    method: &Method,
+    headers: &HeaderMap,
+    address: SocketAddr,
+) -> Result<(), Diagnostic> {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(());
+    }
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return Ok(());
+    };
+    match origin.to_str() {
+        Ok(origin) if is_own_origin(origin, address) => Ok(()),
+        _ => Err(Diagnostic::error(
+            DiagnosticCode::CrossOrigin,
+            "Only pages served by the dev server may change the project",
+            None,
+        )),
+    }
+}
+
+/// Whether `origin` is this server, reached through its own address or
+/// through localhost
+fn is_own_origin(origin: &str, address: SocketAddr) -> bool {
+    let Some((host, port)) = origin
+        .strip_prefix("http://")
+        .and_then(|authority| authority.rsplit_once(':'))
+    else {
+        return false;
+    };
+    if port.parse() != Ok(address.port()) {
+        return false;
+    }
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback() || ip == address.ip())
+}
+
+/// A `.frr` file inside the project, as named by a request
+struct ProjectFile {
+    /// Path relative to the project root, as echoed back to clients
+    name: String,
+    path: PathBuf,
+}
+
+/// Resolve a requested file, refusing anything that escapes the project root
+fn resolve_project_file(
+    project_root: &Path,
+    file: Option<&str>,
+) -> Result<ProjectFile, Diagnostic> {
+    let name = file.unwrap_or(DEFAULT_FILE);
+    let requested = Path::new(name);
+
+    if requested.extension().and_then(|ext| ext.to_str()) != Some("frr") {
+        return Err(Diagnostic::error(
+            DiagnosticCode::InvalidPath,
+            "Only .frr files can be used with this endpoint",
+            Some(name),
+        ));
+    }
+
+    if requested
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(Diagnostic::error(
+            DiagnosticCode::OutsideProject,
+            "File must be a relative path inside the project",
+            Some(name),
+        ));
+    }
+
+    let path = project_root.join(requested);
+    if !path.exists() {
+        return Err(Diagnostic::error(
+            DiagnosticCode::NotFound,
+            "File does not exist",
+            Some(name),
+        ));
+    }
+
+    // Symlinks could still point outside the root, so compare canonical paths
+    let canonical_root = project_root
+        .canonicalize()
+        .map_err(|e| Diagnostic::error(DiagnosticCode::Io, e.to_string(), Some(name)))?;
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| Diagnostic::error(DiagnosticCode::Io, e.to_string(), Some(name)))?;
+    if !canonical.starts_with(&canonical_root) {
+        return Err(Diagnostic::error(
+            DiagnosticCode::OutsideProject,
+            "File resolves to a location outside the project",
+            Some(name),
+        ));
+    }
+
+    Ok(ProjectFile {
+        name: name.to_string(),
+        path: canonical,
+    })
+}
+
+/// The request's inline source, or the file's contents
+fn load_source(file: &ProjectFile, source: Option<String>) -> Result<String, Diagnostic> {
+    match source {
+        Some(source) => Ok(source),
+        None => fs::read_to_string(&file.path)
+            .map_err(|e| Diagnostic::error(DiagnosticCode::Io, e.to_string(), Some(&file.name))),
+    }
+}
+
+fn parse(file: &ProjectFile, source: &str) -> Result<Vec<FerrumNode>, Diagnostic> {
+    FerrumParser::new()
+        .parse(source)
+        .map_err(|e| Diagnostic::error(DiagnosticCode::Parse, e.to_string(), Some(&file.name)))
+}
+
+fn format(file: &ProjectFile, source: &str) -> Result<String, Diagnostic> {
+    FerrumFormatter::default()
+        .format(source)
+        .map_err(|e| Diagnostic::error(DiagnosticCode::Format, e, Some(&file.name)))
+}
+
+/// `GET /api/render?file=src/pages/about.frr`
+pub async fn render_query(
+    State(state): State<AppState>,
+    Query(request): Query<ToolRequest>,
+) -> Response {
+    render(&state, request).into_response()
+}
+
+/// `POST /api/render` with `{ "file": .., "source": .. }`
+pub async fn render_body(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<ToolRequest>,
+) -> Response {
+    render(&state, request).into_response()
+}
+
+/// Show parsed nodes and rendered body HTML for a file
+fn render(state: &AppState, request: ToolRequest) -> Result<Json<Value>, Diagnostic> {
+    let file = resolve_project_file(&state.project_root, request.file.as_deref())?;
+    let source = load_source(&file, request.source)?;
+    let nodes = parse(&file, &source)?;
+
+    let body_html = generate_body_html_from_nodes(&nodes)
+        .map_err(|e| Diagnostic::error(DiagnosticCode::Parse, e.to_string(), Some(&file.name)))?;
+
+    Ok(Json(json!({
+        "file": file.name,
+        "nodes": nodes.iter().map(node_to_json).collect::<Vec<_>>(),
+        "body_html": body_html,
+        "diagnostics": [],
+    })))
+}
+
+/// `GET /api/format?file=..`
+pub async fn format_query(
+    State(state): State<AppState>,
+    Query(request): Query<ToolRequest>,
+) -> Response {
+    format_file(&state, request).into_response()
+}
+
+/// `POST /api/format` with `{ "file": .., "source": .. }`
+pub async fn format_body(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<ToolRequest>,
+) -> Response {
+    format_file(&state, request).into_response()
+}
+
+/// Format a file without writing it back
+fn format_file(state: &AppState, request: ToolRequest) -> Result<Json<Value>, Diagnostic> {
+    let file = resolve_project_file(&state.project_root, request.file.as_deref())?;
+    let original = load_source(&file, request.source)?;
+    let formatted = format(&file, &original)?;
+
+    Ok(Json(json!({
+        "file": file.name,
+        "original": original,
+        "formatted": formatted,
+        "changed": original != formatted,
+        "diagnostics": [],
+    })))
+}
+
+/// `POST /api/save` with `{ "file": .., "source": .. }`
+///
+/// Formats the file (or the given source) and writes it back to disk.
+pub async fn save(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<ToolRequest>,
+) -> Response {
+    let result = async {
+        let file = resolve_project_file(&state.project_root, request.file.as_deref())?;
+        let source = load_source(&file, request.source)?;
+        let formatted = format(&file, &source)?;
+        let nodes = parse(&file, &formatted)?;
+        let compiled = generate_html_from_nodes(
+            &nodes,
+            &project_stylesheet(&state.project_root),
+            &Default::default(),
+        )
+        .map_err(|e| Diagnostic::error(DiagnosticCode::Parse, e.to_string(), Some(&file.name)))?;
+
+        fs::write(&file.path, &formatted)
+            .map_err(|e| Diagnostic::error(DiagnosticCode::Io, e.to_string(), Some(&file.name)))?;
+
+        let mut current_state = state.server.write().await;
+        current_state.last_reload = SystemTime::now();
+        current_state
+            .compiled_files
+            .insert(file.path.to_string_lossy().to_string(), compiled.clone());
+
+        Ok::<_, Diagnostic>(Json(json!({
+            "message": "File formatted and saved successfully",
+            "file": file.name,
+            "timestamp": current_state.last_reload.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs(),
+            "formatted": formatted,
+            "compiled": compiled,
+            "diagnostics": [],
+        })))
+    };
+
+    result.await.into_response()
+}
+
+/// Body of `POST /api/state/history`
+#[derive(Debug, Deserialize)]
+pub struct HistoryRequest {
+    pub position: usize,
+}
+
+/// `GET /api/state/history`: the app's recorded state timeline, if attached
+pub async fn state_history(State(state): State<AppState>) -> Response {
+    match &state.history {
+        Some(history) => timeline(history).into_response(),
+        None => Json(json!({ "enabled": false, "diagnostics": [] })).into_response(),
+    }
+}
+
+/// `POST /api/state/history` with `{ "position": n }` replays to that point
+pub async fn state_history_goto(
+    State(state): State<AppState>,
+    JsonBody(request): JsonBody<HistoryRequest>,
+) -> Response {
+    let Some(history) = &state.history else {
+        return Diagnostic::error(
+            DiagnosticCode::NoHistory,
+            "No state history is attached to this server",
+            None,
+        )
+        .into_response();
+    };
+
+    match history.goto(request.position) {
+        Ok(()) => timeline(history).into_response(),
+        Err(e) => Diagnostic::error(DiagnosticCode::History, e.to_string(), None).into_response(),
+    }
+}
+
+fn timeline(history: &History) -> Json<Value> {
+    Json(json!({
+        "enabled": true,
+        "timeline": history.timeline(),
+        "diagnostics": [],
+    }))
+}
+
+/// Structured JSON view of a parsed node, for editor outlines
+fn node_to_json(node: &FerrumNode) -> Value {
+    match node {
+        FerrumNode::Element {
+            tag,
+            props,
+            children,
+        } => json!({
+            "type": "element",
+            "tag": tag,
+            "props": props,
+            "children": children.iter().map(node_to_json).collect::<Vec<_>>(),
+        }),
+        FerrumNode::Text(text) => json!({ "type": "text", "text": text }),
+        FerrumNode::Component {
+            name,
+            props,
+            children,
+        } => json!({
+            "type": "component",
+            "name": name,
+            "props": props,
+            "children": children.iter().map(node_to_json).collect::<Vec<_>>(),
+        }),
+        FerrumNode::StateBinding { signal, operation } => json!({
+            "type": "state_binding",
+            "signal": signal,
+            "operation": operation,
+        }),
+        FerrumNode::Import { names, from } => json!({
+            "type": "import",
+            "names": names,
+            "from": from,
+        }),
+        FerrumNode::Expression(expr) => json!({
+            "type": "expression",
+            "expression": expression_to_json(expr),
+        }),
+    }
+}
+
+fn expression_to_json(expr: &Expression) -> Value {
+    match expr {
+        Expression::StringLiteral(s) => json!({ "type": "string", "value": s }),
+        Expression::Number(n) => json!({ "type": "number", "value": n }),
+        Expression::SignalAccess(s) => json!({ "type": "signal", "name": s }),
+        Expression::PropertyAccess { signal, property } => json!({
+            "type": "property",
+            "signal": signal,
+            "property": property,
+        }),
+        Expression::BinaryOperation {
+            left,
+            operator,
+            right,
+        } => {
+            let operator = match operator {
+                BinaryOperator::Add => "+",
+                BinaryOperator::Subtract => "-",
+                BinaryOperator::Multiply => "*",
+                BinaryOperator::Divide => "/",
+                BinaryOperator::Equals => "==",
+                BinaryOperator::NotEquals => "!=",
+                BinaryOperator::GreaterThan => ">",
+                BinaryOperator::LessThan => "<",
+                BinaryOperator::And => "&&",
+                BinaryOperator::Or => "||",
+            };
+            json!({
+                "type": "binary",
+                "operator": operator,
+                "left": expression_to_json(left),
+                "right": expression_to_json(right),
+            })
+        }
+        Expression::FunctionCall { function, args } => json!({
+            "type": "call",
+            "function": function,
+            "args": args.iter().map(expression_to_json).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_paths_outside_project() {
+        let root = std::env::temp_dir();
+
+        for file in ["../etc/passwd.frr", "/etc/passwd.frr", "src/../../x.frr"] {
+            let err = resolve_project_file(&root, Some(file)).err().unwrap();
+            assert!(
+                matches!(err.code, DiagnosticCode::OutsideProject),
+                "{}",
+                file
+            );
+        }
+
+        let err = resolve_project_file(&root, Some("src/main.rs"))
+            .err()
+            .unwrap();
+        assert!(matches!(err.code, DiagnosticCode::InvalidPath));
+    }
+
+    #[test]
+    fn test_refuses_writes_from_other_origins() {
+        let address: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let check = |method: Method, origin: Option<&str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(origin) = origin {
+                headers.insert(header::ORIGIN, origin.parse().unwrap());
+            }
+            check_origin(&method, &headers, address).is_ok()
+        };
+
+        for origin in [
+            "http://127.0.0.1:7777",
+            "http://localhost:7777",
+            "http://[::1]:7777",
+        ] {
+            assert!(check(Method::POST, Some(origin)), "{}", origin);
+        }
+        assert!(check(Method::POST, None));
+        assert!(check(Method::GET, Some("https://example.com")));
+
+        for origin in [
+            "https://example.com",
+            "http://localhost:8080",
+            "http://localhost.example.com:7777",
+            "null",
+        ] {
+            assert!(!check(Method::POST, Some(origin)), "{}", origin);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_bodies_are_diagnostics() {
+        use axum::body::{to_bytes, Body};
+        use axum::routing::post;
+        use tower::ServiceExt;
+
+        let app = axum::Router::new().route(
+            "/",
+            post(|JsonBody(request): JsonBody<ToolRequest>| async move {
+                request.file.unwrap_or_default()
+            }),
+        );
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{\"file\": "))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["diagnostics"][0]["code"], "invalid_request");
+    }
+}
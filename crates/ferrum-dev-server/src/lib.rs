@@ -0,0 +1,986 @@
+//! Ferrum development server
+//!
+//! Serves a Ferrum project's `.frr` files as HTML and recompiles them as they
+//! change. Embed it with [`RustDevServer::builder`], or run it through
+//! `ferrum dev` / the `ferrum-dev-server` binary.
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Path as AxumPath, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post, Router},
+    Json,
+};
+use ferrum_core::component::ComponentRegistry;
+use ferrum_core::parser::FerrumParser;
+use ferrum_core::routing::{self as app_routing, Resolution};
+use ferrum_core::state::History;
+use ferrum_core::FerrumConfig;
+use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tower::ServiceExt;
+use tower_http::services::ServeDir;
+
+mod api;
+pub mod pages;
+
+/// Stylesheet used when the project doesn't provide its own
+const DEFAULT_STYLESHEET: &str = include_str!("../static/ferrum.css");
+
+/// Port used when none is configured
+pub const DEFAULT_PORT: u16 = 7777;
+
+/// Pure Rust Development Server
+/// NO JavaScript, NO Single HTML - Everything handled by Rust
+pub struct RustDevServer {
+    host: String,
+    port: u16,
+    open_browser: bool,
+    config: FerrumConfig,
+    history: Option<History>,
+    router: Option<Arc<app_routing::Router>>,
+    registered_components: Arc<[String]>,
+    project_path: String,
+    compiled_components: Arc<RwLock<HashMap<String, String>>>,
+    server_state: Arc<RwLock<ServerState>>,
+}
+
+/// Builder for [`RustDevServer`]
+#[derive(Debug, Clone)]
+pub struct DevServerBuilder {
+    root: PathBuf,
+    host: String,
+    port: u16,
+    open_browser: bool,
+    config: FerrumConfig,
+    history: Option<History>,
+    router: Option<Arc<app_routing::Router>>,
+    registered_components: Arc<[String]>,
+}
+
+impl Default for DevServerBuilder {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("."),
+            host: "127.0.0.1".to_string(),
+            port: DEFAULT_PORT,
+            open_browser: false,
+            config: FerrumConfig::default(),
+            history: None,
+            router: None,
+            registered_components: Arc::from([]),
+        }
+    }
+}
+
+impl DevServerBuilder {
+    /// Project directory containing `src/main.frr`
+    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Port to listen on; `0` picks a free one
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    /// Open the served app in the default browser once listening
+    pub fn open_browser(mut self, open_browser: bool) -> Self {
+        self.open_browser = open_browser;
+        self
+    }
+
+    pub fn config(mut self, config: FerrumConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Expose an app's state history at `/api/state/history`
+    pub fn history(mut self, history: History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Apply an app router's redirects, guards and loaders to served pages
+    ///
+    /// Routes without a page in `src/pages` render
+    /// `src/components/<Component>.frr`, and so does the fallback route.
+    pub fn router(mut self, router: app_routing::Router) -> Self {
+        self.router = Some(Arc::new(router));
+        self
+    }
+
+    /// List an app's registered components at `/api/components`
+    pub fn components(mut self, registry: &ComponentRegistry) -> Self {
+        self.registered_components = registry.names().map(String::from).collect();
+        self
+    }
+
+    /// Validate the project directory and create the server
+    pub fn build(self) -> Result<RustDevServer> {
+        if !self.root.join("src/main.frr").exists() {
+            return Err(anyhow!(
+                "Not a Ferrum project directory: {} has no src/main.frr",
+                self.root.display()
+            ));
+        }
+
+        let project_path = self.root.canonicalize()?.to_string_lossy().to_string();
+        let compiled_components = Arc::new(RwLock::new(HashMap::new()));
+
+        let server_state = Arc::new(RwLock::new(ServerState {
+            last_reload: SystemTime::now(),
+            active_routes: Vec::new(),
+            compiled_files: HashMap::new(),
+        }));
+
+        Ok(RustDevServer {
+            host: self.host,
+            port: self.port,
+            open_browser: self.open_browser,
+            config: self.config,
+            history: self.history,
+            router: self.router,
+            registered_components: self.registered_components,
+            project_path,
+            compiled_components,
+            server_state,
+        })
+    }
+}
+
+/// State shared by every request handler
+#[derive(Clone)]
+struct AppState {
+    project_root: Arc<PathBuf>,
+    /// The address actually bound, which differs from the configured one for port 0
+    address: SocketAddr,
+    app_name: Arc<String>,
+    history: Option<History>,
+    router: Option<Arc<app_routing::Router>>,
+    registered_components: Arc<[String]>,
+    server: Arc<RwLock<ServerState>>,
+}
+
+#[derive(Clone)]
+struct ServerState {
+    last_reload: SystemTime,
+    active_routes: Vec<String>,
+    compiled_files: HashMap<String, String>,
+}
+
+impl RustDevServer {
+    pub fn builder() -> DevServerBuilder {
+        DevServerBuilder::default()
+    }
+
+    pub fn new(project_path: String, port: u16) -> Result<Self> {
+        Self::builder().root(project_path).port(port).build()
+    }
+
+    /// Start pure Rust development server, stopping on Ctrl-C
+    pub async fn run(&self) -> Result<()> {
+        self.run_until(async {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                eprintln!("❌ Failed to listen for Ctrl-C: {}", e);
+                std::future::pending::<()>().await;
+            }
+            println!("👋 Shutting down Ferrum server");
+        })
+        .await
+    }
+
+    /// Start the server and shut it down gracefully once `shutdown` completes
+    pub async fn run_until<F>(&self, shutdown: F) -> Result<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        println!("🦀 Starting Pure Rust Ferrum Server");
+        println!("📦 App: {}", self.config.app_name);
+        println!("📁 Project: {}", self.project_path);
+        println!("🔥 Pure Rust: NO JavaScript, NO Single HTML");
+        println!("💾 Manual save only (no auto-format)");
+        println!("👀 Watching .frr files for compilation...");
+
+        // Setup file watcher for .frr files
+        let watcher = self.setup_frr_watcher().await?;
+
+        // Start pure Rust web server
+        let result = self.start_rust_server(shutdown).await;
+        watcher.abort();
+        result
+    }
+
+    /// Watch .frr files and recompile on changes
+    async fn setup_frr_watcher(&self) -> Result<JoinHandle<()>> {
+        let project_path = self.project_path.clone();
+        let compiled_components = self.compiled_components.clone();
+        let server_state = self.server_state.clone();
+
+        let watcher = tokio::spawn(async move {
+            let (tx, mut rx) =
+                tokio::sync::mpsc::unbounded_channel::<notify::Result<notify::Event>>();
+            let mut watcher = match RecommendedWatcher::new(
+                move |res| {
+                    let _ = tx.send(res);
+                },
+                Config::default(),
+            ) {
+                Ok(w) => w,
+                Err(e) => {
+                    eprintln!("❌ Failed to create watcher: {}", e);
+                    return;
+                }
+            };
+
+            let src_path = Path::new(&project_path).join("src");
+            if !src_path.exists() {
+                eprintln!("❌ src/ directory not found");
+                return;
+            }
+
+            println!("👀 Watching: {:?}", src_path);
+
+            if let Err(e) = watcher.watch(&src_path, RecursiveMode::Recursive) {
+                eprintln!("❌ Failed to watch directory: {}", e);
+                return;
+            }
+
+            // Debounce: track file changes with timestamps
+            let debounce_duration = std::time::Duration::from_millis(300);
+            let mut pending_changes: std::collections::HashMap<String, tokio::time::Instant> =
+                std::collections::HashMap::new();
+
+            // Debounce task: process pending changes after debounce period
+            let mut debounce_interval = tokio::time::interval(debounce_duration);
+            debounce_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    // Handle file watcher events
+                    res = rx.recv() => {
+                        let Some(res) = res else { break };
+
+                        match res {
+                            Ok(event) => {
+                                if !matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
+                                    continue;
+                                }
+
+                                for path in event.paths {
+                                    if path.extension().and_then(|ext| ext.to_str()) != Some("frr") {
+                                        continue;
+                                    }
+
+                                    // Update pending change timestamp
+                                    let path_str = path.to_string_lossy().to_string();
+                                    pending_changes.insert(path_str, tokio::time::Instant::now());
+                                }
+                            }
+                            Err(e) => {
+                                eprintln!("❌ Watch error: {:?}", e);
+                            }
+                        }
+                    }
+
+                    // Process pending changes after debounce
+                    _ = debounce_interval.tick() => {
+                        let now = tokio::time::Instant::now();
+
+                        // Process files that haven't changed in debounce_duration
+                        let mut files_to_process = Vec::new();
+                        pending_changes.retain(|path, timestamp| {
+                            if now.duration_since(*timestamp) >= debounce_duration {
+                                files_to_process.push(path.clone());
+                                false // Remove from pending
+                            } else {
+                                true // Keep pending
+                            }
+                        });
+
+                        // Process each file once
+                        for path_str in files_to_process {
+                            let path = Path::new(&path_str);
+                            println!("🔄 Changed: {:?}", path.file_name());
+
+                            // Compile file only (no auto-format)
+                            match compile_frr_file(Path::new(&project_path), path) {
+                                Ok(compiled) => {
+                                    // Update compiled components
+                                    let mut components = compiled_components.write().await;
+                                    let path_str = path.to_string_lossy().to_string();
+                                    components.insert(path_str.clone(), compiled.clone());
+
+                                    // Update server state
+                                    let mut state = server_state.write().await;
+                                    state.last_reload = SystemTime::now();
+                                    state
+                                        .compiled_files
+                                        .insert(path_str.clone(), compiled.clone());
+
+                                    println!("✅ Compiled: {:?}", path.file_name());
+                                }
+                                Err(e) => {
+                                    eprintln!("❌ Compilation failed: {}", e);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(watcher)
+    }
+
+    /// Start pure Rust web server with HTML generation
+    async fn start_rust_server<F>(&self, shutdown: F) -> Result<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let project_root = PathBuf::from(&self.project_path);
+        let listener = tokio::net::TcpListener::bind((self.host.as_str(), self.port)).await?;
+        let address = listener.local_addr()?;
+
+        let app_state = AppState {
+            project_root: Arc::new(project_root.clone()),
+            address,
+            app_name: Arc::new(self.config.app_name.clone()),
+            history: self.history.clone(),
+            router: self.router.clone(),
+            registered_components: self.registered_components.clone(),
+            server: self.server_state.clone(),
+        };
+
+        // Project assets are revalidated on every request so edits show up immediately
+        let static_assets = Router::new()
+            .nest_service("/static", ServeDir::new(project_root.join("static")))
+            .layer(middleware::map_response(dev_cache_headers));
+
+        // Create Axum router with pure Rust handlers
+        let app = Router::new()
+            // Main page - generated from .frr
+            .route("/", get(generate_main_page))
+            // Component endpoints
+            .route("/components/:component", get(generate_component_page))
+            // API endpoints for monitoring
+            .route("/api/status", get(api_status))
+            .route("/api/components", get(api_components))
+            // Editor tooling API, scoped to files inside the project
+            .route("/api/render", get(api::render_query).post(api::render_body))
+            .route("/api/format", get(api::format_query).post(api::format_body))
+            .route("/api/save", post(api::save))
+            // Time-travel debugging for the app's store
+            .route(
+                "/api/state/history",
+                get(api::state_history).post(api::state_history_goto),
+            )
+            // Static assets
+            .merge(static_assets)
+            // Pages from src/pages, then files from public/
+            .fallback(serve_page_or_public)
+            .layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                apply_route_guards,
+            ))
+            // No CORS: only the server's own pages may call its API
+            .layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                api::refuse_cross_origin_writes,
+            ))
+            .with_state(app_state);
+
+        let url = format!("http://{}", address);
+        println!("🌐 Pure Rust server ready at: {}", url);
+
+        if self.open_browser {
+            open_in_browser(&url);
+        }
+
+        axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Generate main page from main.frr - NO JavaScript!
+async fn generate_main_page(State(state): State<AppState>, request: Request) -> Response {
+    // Try to read and compile main.frr
+    let html = match compile_main_frr(&state.project_root) {
+        Ok(html_content) => with_route_data(&state, &request_path(&request), html_content).await,
+        Err(e) => Err(e),
+    };
+    match html {
+        Ok(html_content) => Html(html_content).into_response(),
+        Err(e) => {
+            let error_html = generate_error_page(&format!("Failed to compile main.frr: {}", e));
+            (StatusCode::INTERNAL_SERVER_ERROR, Html(error_html)).into_response()
+        }
+    }
+}
+
+/// Generate individual component pages
+async fn generate_component_page(
+    AxumPath(component): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Response {
+    let component_path = state
+        .project_root
+        .join(format!("src/components/{}.frr", component));
+
+    match compile_frr_file(&state.project_root, &component_path) {
+        Ok(html_content) => Html(html_content).into_response(),
+        Err(e) => {
+            let error_html =
+                generate_error_page(&format!("Failed to compile component {}: {}", component, e));
+            (StatusCode::INTERNAL_SERVER_ERROR, Html(error_html)).into_response()
+        }
+    }
+}
+
+/// API endpoint for server status
+async fn api_status(State(state): State<AppState>) -> impl IntoResponse {
+    let current_state = state.server.read().await;
+
+    let status = json!({
+        "framework": "Ferrum",
+        "version": "0.1.0",
+        "server": "Pure Rust (No JavaScript)",
+        "status": "running",
+        "app": state.app_name.as_str(),
+        "host": state.address.ip().to_string(),
+        "port": state.address.port(),
+        "last_reload": current_state.last_reload.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs(),
+        "compiled_files": current_state.compiled_files.len(),
+        "active_routes": current_state.active_routes.len(),
+        "features": {
+            "auto_format": false,
+            "live_reload": false,
+            "manual_save": true,
+            "hot_reload": false,
+        }
+    });
+
+    (StatusCode::OK, Json(status))
+}
+
+/// API endpoint to list all compiled components
+async fn api_components(State(state): State<AppState>) -> impl IntoResponse {
+    let current_state = state.server.read().await;
+
+    let components = json!({
+        "components": current_state.compiled_files,
+        "total": current_state.compiled_files.len(),
+        "registered": &*state.registered_components
+    });
+
+    (StatusCode::OK, Json(components))
+}
+
+/// Serve a page from src/pages, falling back to the project's public/ dir
+async fn serve_page_or_public(State(state): State<AppState>, request: Request) -> Response {
+    let path = request.uri().path().to_string();
+    let pages_dir = state.project_root.join("src/pages");
+
+    if let Some(page) = pages::resolve(&pages_dir, &path) {
+        let html = match compile_page(&state.project_root, &page) {
+            Ok(html_content) => {
+                with_route_data(&state, &request_path(&request), html_content).await
+            }
+            Err(e) => Err(e),
+        };
+        return match html {
+            Ok(html_content) => {
+                let mut response = Html(html_content).into_response();
+                response
+                    .headers_mut()
+                    .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+                response
+            }
+            Err(e) => {
+                let error_html = generate_error_page(&format!(
+                    "Failed to compile page {}: {}",
+                    page.route.file.display(),
+                    e
+                ));
+                (StatusCode::INTERNAL_SERVER_ERROR, Html(error_html)).into_response()
+            }
+        };
+    }
+
+    // ServeDir guesses the MIME type from the extension and answers
+    // conditional requests with 304s
+    let path_and_query = request_path(&request);
+    let public = ServeDir::new(state.project_root.join("public"));
+    let response = match public.oneshot(request).await {
+        Ok(response) => dev_cache_headers(response.map(axum::body::Body::new)).await,
+        Err(e) => match e {},
+    };
+
+    if response.status() == StatusCode::NOT_FOUND {
+        if let Some(response) = serve_route_component(&state, &path_and_query).await {
+            return response;
+        }
+        let error_html = generate_error_page(&format!("No page or public file matches {}", path));
+        return (StatusCode::NOT_FOUND, Html(error_html)).into_response();
+    }
+
+    response
+}
+
+/// Path and query of a request, as the app router sees it
+fn request_path(request: &Request) -> String {
+    request.uri().path_and_query().map_or_else(
+        || request.uri().path().to_string(),
+        |p| p.as_str().to_string(),
+    )
+}
+
+/// Answer redirects and blocked routes of the app router before anything
+/// else is served
+async fn apply_route_guards(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request_path(&request);
+    let is_dev_path = ["/api/", "/static/", "/components/"]
+        .iter()
+        .any(|prefix| path.starts_with(prefix));
+
+    if let (Some(router), false) = (&state.router, is_dev_path) {
+        match router.resolve(&path) {
+            Resolution::Redirect(to) => return Redirect::temporary(&to).into_response(),
+            Resolution::Blocked => {
+                let error_html =
+                    generate_error_page(&format!("Navigation to {} was blocked", path));
+                return (StatusCode::FORBIDDEN, Html(error_html)).into_response();
+            }
+            Resolution::Render(_) | Resolution::NotFound(_) => {}
+        }
+    }
+    next.run(request).await
+}
+
+/// Run the app router's loader for `path` and embed its data in `html`
+async fn with_route_data(state: &AppState, path: &str, html: String) -> Result<String> {
+    let Some(router) = &state.router else {
+        return Ok(html);
+    };
+    let (Resolution::Render(route) | Resolution::NotFound(Some(route))) = router.resolve(path)
+    else {
+        return Ok(html);
+    };
+    let Some(load) = router.load(&route) else {
+        return Ok(html);
+    };
+
+    let data = load
+        .await
+        .map_err(|e| anyhow!("Loader for {} failed: {}", route.path, e))?;
+    let script = app_routing::loader_script(&data)?;
+    Ok(html.replacen("</body>", &format!("{}</body>", script), 1))
+}
+
+/// Render the component the app router picks for a path with no page,
+/// answering 404 for the fallback route
+async fn serve_route_component(state: &AppState, path: &str) -> Option<Response> {
+    let (route, status) = match state.router.as_ref()?.resolve(path) {
+        Resolution::Render(route) => (route, StatusCode::OK),
+        Resolution::NotFound(Some(route)) => (route, StatusCode::NOT_FOUND),
+        Resolution::Redirect(_) | Resolution::Blocked | Resolution::NotFound(None) => return None,
+    };
+    let file = state
+        .project_root
+        .join(format!("src/components/{}.frr", route.component));
+    if !file.exists() {
+        return None;
+    }
+
+    let html = match compile_frr_file_with_params(&state.project_root, &file, &route.params) {
+        Ok(html_content) => with_route_data(state, path, html_content).await,
+        Err(e) => Err(e),
+    };
+    Some(match html {
+        Ok(html_content) => (status, Html(html_content)).into_response(),
+        Err(e) => {
+            let error_html =
+                generate_error_page(&format!("Failed to render {}: {}", route.component, e));
+            (StatusCode::INTERNAL_SERVER_ERROR, Html(error_html)).into_response()
+        }
+    })
+}
+
+/// Dev assets must always be revalidated so edits are picked up on reload
+async fn dev_cache_headers(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+/// The project's own stylesheet, or the built-in one
+fn project_stylesheet(project_root: &Path) -> String {
+    ["style/ferrum.css", "static/ferrum.css"]
+        .iter()
+        .find_map(|candidate| fs::read_to_string(project_root.join(candidate)).ok())
+        .unwrap_or_else(|| DEFAULT_STYLESHEET.to_string())
+}
+
+/// Compile main.frr file
+fn compile_main_frr(project_root: &Path) -> Result<String> {
+    let main_frr_path = project_root.join("src/main.frr");
+    compile_frr_file(project_root, &main_frr_path)
+}
+
+/// Compile individual .frr file
+fn compile_frr_file(project_root: &Path, path: &Path) -> Result<String> {
+    compile_frr_file_with_params(project_root, path, &HashMap::new())
+}
+
+/// Compile a .frr file, exposing route params on the app root
+fn compile_frr_file_with_params(
+    project_root: &Path,
+    path: &Path,
+    params: &HashMap<String, String>,
+) -> Result<String> {
+    let content = fs::read_to_string(path)?;
+    let mut parser = FerrumParser::new();
+
+    // Parse .frr content
+    let nodes = parser.parse(&content)?;
+
+    // Generate HTML directly from .frr (no JavaScript!)
+    let html_content = generate_html_from_nodes(&nodes, &project_stylesheet(project_root), params)?;
+
+    Ok(html_content)
+}
+
+/// Compile a file-routed page inside its layouts, exposing its params on
+/// the app root
+fn compile_page(project_root: &Path, page: &pages::PageMatch) -> Result<String> {
+    let mut parser = FerrumParser::new();
+    let mut nodes = parser.parse(&fs::read_to_string(&page.route.file)?)?;
+
+    for layout in page.layouts.iter().rev() {
+        let mut layout_nodes = parser.parse(&fs::read_to_string(layout)?)?;
+        let mut child = Some(nodes);
+        fill_outlet(&mut layout_nodes, &mut child);
+        if child.is_some() {
+            return Err(anyhow!(
+                "{} has no <Outlet /> for the page",
+                layout.display()
+            ));
+        }
+        nodes = layout_nodes;
+    }
+
+    generate_html_from_nodes(&nodes, &project_stylesheet(project_root), &page.params)
+}
+
+/// Replace the first `Outlet` component in a layout with the page's nodes
+fn fill_outlet(
+    nodes: &mut Vec<ferrum_core::parser::FerrumNode>,
+    child: &mut Option<Vec<ferrum_core::parser::FerrumNode>>,
+) {
+    use ferrum_core::parser::FerrumNode;
+
+    let outlet = nodes
+        .iter()
+        .position(|node| matches!(node, FerrumNode::Component { name, .. } if name == "Outlet"));
+    if let Some(index) = outlet {
+        if let Some(child) = child.take() {
+            nodes.splice(index..=index, child);
+        }
+        return;
+    }
+
+    for node in nodes {
+        if child.is_none() {
+            return;
+        }
+        if let FerrumNode::Element { children, .. } | FerrumNode::Component { children, .. } = node
+        {
+            fill_outlet(children, child);
+        }
+    }
+}
+
+/// Generate inner body HTML for nodes (no <html>/<head>)
+fn generate_body_html_from_nodes(nodes: &[ferrum_core::parser::FerrumNode]) -> Result<String> {
+    let mut html = String::new();
+    for node in nodes {
+        html.push_str(&node_to_html(node, &mut Vec::new())?);
+    }
+    Ok(html)
+}
+
+/// Generate pure HTML from parsed .frr nodes (NO JavaScript)
+fn generate_html_from_nodes(
+    nodes: &[ferrum_core::parser::FerrumNode],
+    stylesheet: &str,
+    params: &HashMap<String, String>,
+) -> Result<String> {
+    let mut html = String::new();
+
+    // Generate full HTML page
+    html.push_str("<!DOCTYPE html>");
+    html.push_str("<html lang='en'>");
+    html.push_str("<head>");
+    html.push_str("<meta charset='UTF-8'>");
+    html.push_str("<meta name='viewport' content='width=device-width, initial-scale=1.0'>");
+    html.push_str("<title>Ferrum App - Pure Rust</title>");
+
+    // Pure CSS styling (no Tailwind JS)
+    html.push_str("<style>");
+    html.push_str(stylesheet);
+    html.push_str("</style>");
+
+    html.push_str("</head>");
+    html.push_str("<body>");
+    html.push_str("<div id='ferrum-app'");
+
+    // Route params from file-based routing, e.g. data-param-slug
+    let mut params: Vec<_> = params.iter().collect();
+    params.sort();
+    for (key, value) in params {
+        if !pages::is_param_name(key) {
+            log::warn!("Skipped route param with invalid name {:?}", key);
+            continue;
+        }
+        html.push_str(&format!(" data-param-{}='{}'", key, escape_attr(value)));
+    }
+    html.push('>');
+
+    // Generate HTML from nodes
+    for node in nodes {
+        html.push_str(&node_to_html(node, &mut Vec::new())?);
+    }
+
+    html.push_str("</div>");
+    html.push_str("</body>");
+    html.push_str("</html>");
+
+    Ok(html)
+}
+
+/// Escape a value taken from the request before putting it in an attribute
+fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('\'', "&#39;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Values from the enclosing `Provide(..)` nodes, innermost last
+type Provided<'a> = Vec<&'a HashMap<String, String>>;
+
+/// Convert Ferrum node to HTML (NO JavaScript)
+///
+/// `Provide(theme: "dark")` renders its children with `theme` available to
+/// them, and `Use(name: "theme")` renders the value from the nearest
+/// `Provide` that has it.
+fn node_to_html<'a>(
+    node: &'a ferrum_core::parser::FerrumNode,
+    provided: &mut Provided<'a>,
+) -> Result<String> {
+    match node {
+        ferrum_core::parser::FerrumNode::Component {
+            name,
+            props,
+            children,
+        } if name == "Provide" => {
+            provided.push(props);
+            let html = children
+                .iter()
+                .map(|child| node_to_html(child, provided))
+                .collect();
+            provided.pop();
+            html
+        }
+        ferrum_core::parser::FerrumNode::Component { name, props, .. } if name == "Use" => {
+            let key = props.get("name").ok_or_else(|| {
+                anyhow!("Use needs the name of a value, as in Use(name: \"theme\")")
+            })?;
+            let value = provided
+                .iter()
+                .rev()
+                .find_map(|values| values.get(key))
+                .ok_or_else(|| anyhow!("Use(name: \"{}\") has no Provide above it", key))?;
+            Ok(escape_attr(value))
+        }
+        ferrum_core::parser::FerrumNode::Element {
+            tag,
+            props,
+            children,
+        } => {
+            let mut html = format!("<{}", tag);
+
+            // Add props
+            for (key, value) in props {
+                html.push_str(&format!(" {}='{}'", key, value));
+            }
+
+            html.push('>');
+
+            // Add children
+            for child in children {
+                html.push_str(&node_to_html(child, provided)?);
+            }
+
+            html.push_str(&format!("</{}>", tag));
+            Ok(html)
+        }
+        ferrum_core::parser::FerrumNode::Text(text) => Ok(text.clone()),
+        ferrum_core::parser::FerrumNode::Component {
+            name,
+            props,
+            children,
+        } => {
+            // For components, generate div with component name
+            let mut html = format!("<div data-component='{}'", name);
+
+            // Add props as data attributes
+            for (key, value) in props {
+                html.push_str(&format!(" data-{}='{}'", key, value));
+            }
+
+            html.push('>');
+
+            for child in children {
+                html.push_str(&node_to_html(child, provided)?);
+            }
+
+            html.push_str("</div>");
+            Ok(html)
+        }
+        _ => Ok(String::new()),
+    }
+}
+
+/// Best-effort launch of the platform's default browser
+fn open_in_browser(url: &str) {
+    #[cfg(target_os = "macos")]
+    let result = std::process::Command::new("open").arg(url).spawn();
+    #[cfg(target_os = "windows")]
+    let result = std::process::Command::new("cmd")
+        .args(["/C", "start", "", url])
+        .spawn();
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let result = std::process::Command::new("xdg-open").arg(url).spawn();
+
+    if let Err(e) = result {
+        eprintln!("⚠️  Could not open a browser: {}", e);
+    }
+}
+
+/// Generate error page (pure HTML)
+fn generate_error_page(error_message: &str) -> String {
+    format!(
+        r#"
+<!DOCTYPE html>
+<html lang='en'>
+<head>
+    <meta charset='UTF-8'>
+    <title>Ferrum Error</title>
+    <style>
+        body {{
+            font-family: system-ui, sans-serif;
+            background: #1a1a1a;
+            color: white;
+            margin: 0;
+            padding: 2rem;
+        }}
+        .error-container {{
+            max-width: 600px;
+            margin: 0 auto;
+            background: #2d2d2d;
+            border-radius: 8px;
+            padding: 2rem;
+            border: 1px solid #ef4444;
+        }}
+        .error-title {{
+            color: #ef4444;
+            font-size: 1.5rem;
+            margin-bottom: 1rem;
+        }}
+        .error-message {{
+            font-size: 1rem;
+            line-height: 1.5;
+        }}
+    </style>
+</head>
+<body>
+    <div class='error-container'>
+        <h1 class='error-title'>⚠️ Ferrum Compilation Error</h1>
+        <p class='error-message'>{}</p>
+    </div>
+</body>
+</html>
+    "#,
+        error_message
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrum_core::parser::FerrumNode;
+
+    fn component(name: &str, props: &[(&str, &str)], children: Vec<FerrumNode>) -> FerrumNode {
+        FerrumNode::Component {
+            name: name.to_string(),
+            props: props
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            children,
+        }
+    }
+
+    #[test]
+    fn test_provide_and_use_nodes() {
+        let nodes = [component(
+            "Provide",
+            &[("theme", "dark"), ("lang", "en")],
+            vec![
+                component("Use", &[("name", "theme")], vec![]),
+                component(
+                    "Provide",
+                    &[("theme", "<light>")],
+                    vec![component("Use", &[("name", "theme")], vec![])],
+                ),
+                component("Use", &[("name", "lang")], vec![]),
+            ],
+        )];
+        assert_eq!(
+            generate_body_html_from_nodes(&nodes).unwrap(),
+            "dark&lt;light&gt;en"
+        );
+
+        let unprovided = [component("Use", &[("name", "theme")], vec![])];
+        assert!(generate_body_html_from_nodes(&unprovided).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use ferrum_core::routing::percent_decode;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of a directory's layout
+pub const LAYOUT_FILE: &str = "_layout.frr";
+
+/// File-based routing for `src/pages`
+///
+/// `about.frr` is served at `/about`, `blog/[slug].frr` at `/blog/:slug`
+/// and `docs/[...rest].frr` catches everything below `/docs`. `index.frr`
+/// maps to its directory, and files starting with `_` are never routable.
+/// A `_layout.frr` wraps every page in its directory and below it.
+/// Param names may only use ASCII letters, digits, `-` and `_`, since
+/// they end up in `data-param-*` attributes.
+#[derive(Debug, Clone)]
+pub struct PageRoute {
+    pub pattern: String,
+    pub file: PathBuf,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Static(String),
+    Param(String),
+    CatchAll(String),
+}
+
+/// A page that matched a request path, with its extracted params
+#[derive(Debug, Clone)]
+pub struct PageMatch {
+    pub route: PageRoute,
+    pub params: HashMap<String, String>,
+    /// `_layout.frr` files wrapping the page, outermost first
+    pub layouts: Vec<PathBuf>,
+}
+
+impl PageRoute {
+    fn from_file(pages_dir: &Path, file: &Path) -> Option<Self> {
+        let relative = file.strip_prefix(pages_dir).ok()?.with_extension("");
+        let mut segments = Vec::new();
+
+        let components: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+
+        for (i, name) in components.iter().enumerate() {
+            let is_file = i == components.len() - 1;
+
+            if name.starts_with('_') {
+                return None;
+            }
+            if is_file && name == "index" {
+                continue;
+            }
+
+            let segment = match name.strip_prefix('[').and_then(|n| n.strip_suffix(']')) {
+                Some(inner) => match inner.strip_prefix("...") {
+                    Some(rest) if is_file && is_param_name(rest) => {
+                        Segment::CatchAll(rest.to_string())
+                    }
+                    None if is_param_name(inner) => Segment::Param(inner.to_string()),
+                    _ => return None,
+                },
+                None => Segment::Static(name.clone()),
+            };
+            segments.push(segment);
+        }
+
+        let pattern = segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Static(name) => format!("/{}", name),
+                Segment::Param(name) => format!("/:{}", name),
+                Segment::CatchAll(name) => format!("/*{}", name),
+            })
+            .collect::<String>();
+
+        Some(Self {
+            pattern: if pattern.is_empty() {
+                "/".to_string()
+            } else {
+                pattern
+            },
+            file: file.to_path_buf(),
+            segments,
+        })
+    }
+
+    /// Static segments outrank params, which outrank catch-alls
+    fn specificity(&self) -> Vec<u8> {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Static(_) => 2,
+                Segment::Param(_) => 1,
+                Segment::CatchAll(_) => 0,
+            })
+            .collect()
+    }
+
+    fn matches(&self, path: &str) -> Option<HashMap<String, String>> {
+        let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+        let mut params = HashMap::new();
+
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Static(name) => {
+                    if parts.get(i) != Some(&name.as_str()) {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.insert(name.clone(), percent_decode(parts.get(i)?, false));
+                }
+                Segment::CatchAll(name) => {
+                    if i >= parts.len() {
+                        return None;
+                    }
+                    let rest: Vec<String> = parts[i..]
+                        .iter()
+                        .map(|part| percent_decode(part, false))
+                        .collect();
+                    params.insert(name.clone(), rest.join("/"));
+                    return Some(params);
+                }
+            }
+        }
+
+        (parts.len() == self.segments.len()).then_some(params)
+    }
+}
+
+/// Whether `name` can follow `data-param-` in an attribute name
+pub fn is_param_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Collect every routable `.frr` file below `pages_dir`, most specific first
+pub fn discover(pages_dir: &Path) -> Vec<PageRoute> {
+    let mut files = Vec::new();
+    collect_frr_files(pages_dir, &mut files);
+
+    let mut routes: Vec<PageRoute> = files
+        .iter()
+        .filter_map(|file| PageRoute::from_file(pages_dir, file))
+        .collect();
+
+    routes.sort_by(|a, b| {
+        b.specificity()
+            .cmp(&a.specificity())
+            .then_with(|| a.pattern.cmp(&b.pattern))
+    });
+    routes
+}
+
+/// Find the page serving `path`, if any
+pub fn resolve(pages_dir: &Path, path: &str) -> Option<PageMatch> {
+    discover(pages_dir).into_iter().find_map(|route| {
+        let params = route.matches(path)?;
+        let layouts = layouts_for(pages_dir, &route.file);
+        Some(PageMatch {
+            route,
+            params,
+            layouts,
+        })
+    })
+}
+
+/// Every `_layout.frr` from `pages_dir` down to the directory of `file`
+fn layouts_for(pages_dir: &Path, file: &Path) -> Vec<PathBuf> {
+    let Some(dir) = file.parent() else {
+        return Vec::new();
+    };
+
+    let mut layouts: Vec<PathBuf> = dir
+        .ancestors()
+        .take_while(|ancestor| ancestor.starts_with(pages_dir))
+        .map(|ancestor| ancestor.join(LAYOUT_FILE))
+        .filter(|layout| layout.is_file())
+        .collect();
+    layouts.reverse();
+    layouts
+}
+
+fn collect_frr_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_frr_files(&path, files);
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some("frr") {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(file: &str) -> PageRoute {
+        PageRoute::from_file(Path::new("pages"), &Path::new("pages").join(file)).unwrap()
+    }
+
+    #[test]
+    fn test_file_to_pattern() {
+        assert_eq!(route("index.frr").pattern, "/");
+        assert_eq!(route("about.frr").pattern, "/about");
+        assert_eq!(route("blog/index.frr").pattern, "/blog");
+        assert_eq!(route("blog/[slug].frr").pattern, "/blog/:slug");
+        assert_eq!(route("docs/[...rest].frr").pattern, "/docs/*rest");
+        assert!(PageRoute::from_file(Path::new("pages"), Path::new("pages/_layout.frr")).is_none());
+        for file in ["pages/[a b].frr", "pages/[x='y].frr", "pages/[...].frr"] {
+            assert!(PageRoute::from_file(Path::new("pages"), Path::new(file)).is_none());
+        }
+    }
+
+    #[test]
+    fn test_matching_extracts_params() {
+        let params = route("blog/[slug].frr")
+            .matches("/blog/hello-world")
+            .unwrap();
+        assert_eq!(params.get("slug").map(String::as_str), Some("hello-world"));
+
+        let params = route("docs/[...rest].frr").matches("/docs/a/b/c").unwrap();
+        assert_eq!(params.get("rest").map(String::as_str), Some("a/b/c"));
+
+        let params = route("blog/[slug].frr")
+            .matches("/blog/caf%C3%A9%20au%20lait")
+            .unwrap();
+        assert_eq!(params.get("slug").map(String::as_str), Some("café au lait"));
+        let params = route("docs/[...rest].frr")
+            .matches("/docs/a%2Fb/100%")
+            .unwrap();
+        assert_eq!(params.get("rest").map(String::as_str), Some("a/b/100%"));
+
+        assert!(route("blog/[slug].frr").matches("/blog").is_none());
+        assert!(route("about.frr").matches("/about/team").is_none());
+    }
+
+    #[test]
+    fn test_layouts_are_collected_outermost_first() {
+        let pages = std::env::temp_dir().join(format!("ferrum-layouts-{}", std::process::id()));
+        fs::create_dir_all(pages.join("blog/drafts")).unwrap();
+        for file in ["_layout.frr", "blog/_layout.frr", "blog/drafts/[slug].frr"] {
+            fs::write(pages.join(file), "").unwrap();
+        }
+
+        let page = resolve(&pages, "/blog/drafts/hello").unwrap();
+        assert_eq!(
+            page.layouts,
+            [pages.join("_layout.frr"), pages.join("blog/_layout.frr")]
+        );
+
+        fs::remove_dir_all(&pages).unwrap();
+    }
+
+    #[test]
+    fn test_static_routes_outrank_params() {
+        let pages = std::env::temp_dir().join(format!("ferrum-ranking-{}", std::process::id()));
+        fs::create_dir_all(pages.join("blog")).unwrap();
+        for file in ["blog/[...rest].frr", "blog/[slug].frr", "blog/new.frr"] {
+            fs::write(pages.join(file), "").unwrap();
+        }
+
+        let patterns: Vec<String> = discover(&pages)
+            .into_iter()
+            .map(|route| route.pattern)
+            .collect();
+        assert_eq!(patterns, ["/blog/new", "/blog/:slug", "/blog/*rest"]);
+        assert_eq!(
+            resolve(&pages, "/blog/new").unwrap().route.pattern,
+            "/blog/new"
+        );
+
+        fs::remove_dir_all(&pages).unwrap();
+    }
+}